@@ -1,6 +1,92 @@
+use colored::Colorize;
+use ipkg::modules::project;
 use ipkg::utils::shell;
+use ipkg::utils::shell::args::{ArgumentType, Command};
+
+/// 値を取る長いオプション (`--to json` と `--to=json` の両方を受け付ける)
+const VALUE_OPTIONS: [&str; 2] = ["--to", "--strategy"];
+
+/// オプションの値を除いた、プレーンな引数を返します。
+fn words(command_data: &Command) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut expects_value = false;
+    for arg in &command_data.args {
+        match arg.arg_type {
+            ArgumentType::Simple if !expects_value => words.push(arg.arg_str.as_str()),
+            _ => {}
+        }
+        expects_value = matches!(arg.arg_type, ArgumentType::LongOpt)
+            && arg.arg_values.is_empty()
+            && VALUE_OPTIONS.contains(&arg.arg_str.as_str());
+    }
+    words
+}
+
+/// 長いオプションの値を返します。
+fn option_value<'a>(command_data: &'a Command, name: &str) -> Option<&'a str> {
+    let position = command_data
+        .args
+        .iter()
+        .position(|arg| matches!(arg.arg_type, ArgumentType::LongOpt) && arg.arg_str == name)?;
+    let option = &command_data.args[position];
+    match option.arg_values.first() {
+        Some(value) => Some(value.as_str()),
+        None => command_data
+            .args
+            .get(position + 1)
+            .filter(|arg| matches!(arg.arg_type, ArgumentType::Simple))
+            .map(|arg| arg.arg_str.as_str()),
+    }
+}
+
+/// 長いオプションが指定されているかを返します。
+fn has_option(command_data: &Command, name: &str) -> bool {
+    command_data
+        .args
+        .iter()
+        .any(|arg| matches!(arg.arg_type, ArgumentType::LongOpt) && arg.arg_str == name)
+}
+
+/// サブコマンドを実行します。該当するサブコマンドがなければ引数の内容を表示します。
+fn run(command_data: &Command) -> Result<(), String> {
+    let words = words(command_data);
+    match words.as_slice() {
+        ["version", "bump", part] => project::bump_version(part),
+        ["version", "bump"] => {
+            Err("Usage: ipkg version bump <major|minor|patch|prerelease>".to_string())
+        }
+        ["check", rest @ ..] if rest.len() <= 1 => project::check_manifest(rest.first().copied()),
+        ["license", "check", rest @ ..] if rest.len() <= 1 => {
+            project::check_licenses(rest.first().copied())
+        }
+        ["resolve", rest @ ..] if rest.len() <= 1 => project::resolve_dependencies(
+            rest.first().copied(),
+            option_value(command_data, "--strategy"),
+            has_option(command_data, "--frozen"),
+        ),
+        ["lock", rest @ ..] if rest.len() <= 1 => project::lock_dependencies(
+            rest.first().copied(),
+            option_value(command_data, "--strategy"),
+        ),
+        ["manifest", "convert", rest @ ..] if rest.len() <= 1 => {
+            match option_value(command_data, "--to") {
+                Some(to) => project::convert_manifest(to, rest.first().copied()),
+                None => {
+                    Err("Usage: ipkg manifest convert --to <toml|json|yaml> [manifest]".to_string())
+                }
+            }
+        }
+        _ => {
+            println!("{}", command_data);
+            Ok(())
+        }
+    }
+}
 
 fn main() {
     let command_data = shell::args::init();
-    println!("{}", command_data);
+    if let Err(message) = run(&command_data) {
+        eprintln!("{}: {}", "error".red().bold(), message);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,194 @@
+//! ipkg の設定 (`.ipkg/config.toml`)。
+//!
+//! プロジェクトの `.ipkg/config.toml` があればそれを、なければ `~/.ipkg/config.toml` を使います。
+//!
+//! ```toml
+//! # 依存パッケージを探すインデックスのディレクトリ (設定ファイルのディレクトリからの相対パス)
+//! index = ["../packages"]
+//! # インストール済みのパッケージのマニフェストを集めたディレクトリ (`--strategy installed` で優先)
+//! installed = ["../installed"]
+//!
+//! [license]
+//! # 依存パッケージに使ってよいライセンス
+//! allow = ["MIT", "Apache-2.0", "BSD-3-Clause"]
+//! ```
+use super::pkg::index::PackageIndex;
+use super::pkg::license::LicensePolicy;
+use super::system::dir_path;
+use crate::modules::version::Version;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// ipkg の設定。
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// パッケージインデックスのディレクトリ
+    pub index: Vec<PathBuf>,
+    /// インストール済みのパッケージのマニフェストを集めたディレクトリ
+    pub installed: Vec<PathBuf>,
+    pub license: LicenseConfig,
+}
+
+/// ライセンスに関する設定 (`[license]`)。
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LicenseConfig {
+    /// 依存パッケージに使ってよいライセンス
+    pub allow: Vec<String>,
+}
+
+impl FromStr for Config {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml_edit::de::from_str(s).map_err(|e| format!("Invalid config: {}", e))
+    }
+}
+
+impl Config {
+    /// 設定ファイルを読み込みます。ディレクトリの相対パスはファイルのディレクトリを基準にします。
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let mut config: Config = text
+            .parse()
+            .map_err(|e| format!("{} ({})", e, path.display()))?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        config.index = config.index.iter().map(|dir| base.join(dir)).collect();
+        config.installed = config.installed.iter().map(|dir| base.join(dir)).collect();
+        Ok(config)
+    }
+
+    /// プロジェクトに使う設定ファイルを探します。
+    ///
+    /// プロジェクトの `.ipkg/config.toml`、ユーザーの `~/.ipkg/config.toml` の順に調べます。
+    pub fn find(project: &Path) -> Option<PathBuf> {
+        Some(dir_path::project_config_file(project))
+            .into_iter()
+            .chain(dir_path::user_config_file())
+            .find(|path| path.is_file())
+    }
+
+    /// プロジェクトの設定を読み込みます。設定ファイルがなければ既定の設定を返します。
+    ///
+    /// # 引数
+    ///
+    /// * `project` - プロジェクトのディレクトリ。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(Config)` - 読み込んだ設定。
+    /// * `Err(String)` - 設定ファイルが読めないか不正な場合、エラーメッセージを含む。
+    pub fn for_project(project: &Path) -> Result<Self, String> {
+        match Config::find(project) {
+            Some(path) => Config::load(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// ライセンスの許可リストを返します。
+    pub fn license_policy(&self) -> Result<LicensePolicy, String> {
+        LicensePolicy::new(&self.license.allow)
+    }
+
+    /// インデックスのディレクトリをすべて読み込みます。
+    pub fn package_index(&self) -> Result<PackageIndex, String> {
+        let mut index = PackageIndex::default();
+        for dir in &self.index {
+            index.extend_from_dir(dir)?;
+        }
+        Ok(index)
+    }
+
+    /// インストール済みのパッケージの、名前からバージョンへの対応を返します。
+    ///
+    /// [`Strategy::PreferInstalled`](super::pkg::resolve::Strategy::PreferInstalled) に
+    /// 渡すのに使います。
+    pub fn installed_versions(&self) -> Result<HashMap<String, Version>, String> {
+        let mut installed = PackageIndex::default();
+        for dir in &self.installed {
+            installed.extend_from_dir(dir)?;
+        }
+        Ok(installed
+            .packages()
+            .iter()
+            .map(|package| {
+                let about = &package.about.package;
+                (about.name.clone(), about.version.clone())
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_is_read() {
+        let config: Config =
+            "index = [\"packages\"]\n\n[license]\nallow = [\"MIT\", \"Apache-2.0\"]\n"
+                .parse()
+                .unwrap();
+        assert_eq!(config.index, [PathBuf::from("packages")]);
+        assert!(config.installed.is_empty());
+        assert!(!config.license_policy().unwrap().is_empty());
+        assert!(Config::default().license_policy().unwrap().is_empty());
+        let error = "[license]\nallowed = []\n".parse::<Config>().unwrap_err();
+        assert!(error.contains("allowed"), "{}", error);
+        let config: Config = "[license]\nallow = [\"Nope-1.0\"]\n".parse().unwrap();
+        assert!(config.license_policy().is_err());
+    }
+
+    #[test]
+    fn project_config_and_index_are_loaded() {
+        let dir = std::env::temp_dir().join(format!("ipkg-config-{}", std::process::id()));
+        let packages = dir.join("packages");
+        fs::create_dir_all(packages.join("libbar")).unwrap();
+        fs::create_dir_all(dir.join(dir_path::IPKG_DIR)).unwrap();
+        fs::write(
+            dir_path::project_config_file(&dir),
+            "index = [\"../packages\"]\ninstalled = [\"../installed\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            packages.join("libfoo-1.0.0.toml"),
+            "[about.package]\nname = \"libfoo\"\nversion = \"1.0.0\"\n",
+        )
+        .unwrap();
+        fs::write(
+            packages.join("libbar").join("ipkg.json"),
+            r#"{"about": {"package": {"name": "libbar", "version": "2.0.0"}}}"#,
+        )
+        .unwrap();
+        fs::write(packages.join("README.md"), "not a manifest").unwrap();
+
+        assert_eq!(
+            Config::find(&dir),
+            Some(dir_path::project_config_file(&dir))
+        );
+        let config = Config::for_project(&dir).unwrap();
+        let index = config.package_index().unwrap();
+        let names: Vec<&str> = index
+            .packages()
+            .iter()
+            .map(|package| package.about.package.name.as_str())
+            .collect();
+        assert_eq!(names, ["libbar", "libfoo"]);
+
+        let installed = dir.join("installed");
+        fs::create_dir_all(&installed).unwrap();
+        fs::write(
+            installed.join("libfoo.toml"),
+            "[about.package]\nname = \"libfoo\"\nversion = \"0.9.0\"\n",
+        )
+        .unwrap();
+        let versions = config.installed_versions().unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions["libfoo"].as_str(), "0.9.0");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,36 +1,438 @@
-use colored::Colorize;
+use colored::{Color, Colorize};
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::Display;
 
-use super::version::{Version, VersionRange};
+use super::version::{PrereleasePolicy, Scheme, Version, VersionRange};
+use license::License;
 
+pub mod check;
+pub mod index;
+pub mod license;
+pub mod lock;
+pub mod manifest;
+pub mod relation;
+pub mod resolve;
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PackageData {
     pub about: AboutData,
+    #[serde(default, skip_serializing_if = "RelationData::is_empty")]
     pub relation: RelationData,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AboutData {
-    pub author: AuthorAboutData,
     pub package: PackageAboutData,
+    // 作者のリスト（一人だけなら `[about.author]` とも書ける）
+    #[serde(
+        default,
+        alias = "author",
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "one_or_many"
+    )]
+    pub authors: Vec<AuthorAboutData>,
+    // 保守担当者のリスト
+    #[serde(
+        default,
+        alias = "maintainer",
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "one_or_many"
+    )]
+    pub maintainers: Vec<AuthorAboutData>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthorAboutData {
     pub name: String,
     pub email: String,
 }
 
+#[derive(Debug, Serialize)]
 pub struct PackageAboutData {
     pub name: String,
     pub version: Version,
+    #[serde(skip_serializing_if = "is_default")]
+    pub scheme: Scheme, // バージョンの解釈と比較の方式
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>, // 一行の説明
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub long_description: Option<String>, // 複数行の詳しい説明
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>, // ソースコードのリポジトリの URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<License>, // SPDX のライセンス式
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub architecture: Vec<String>, // 対応するアーキテクチャ（空ならすべて）
 }
 
+/// パッケージ間の関係。
+///
+/// `conflict` 以外はどれも `depend` と同じく、代替を内側の `Vec` で表すグループの列です。
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct RelationData {
-    pub depend: Vec<Vec<DependPackageData>>, // 依存関係のグループ（代替は内側のVecで表現）
-    pub conflict: Vec<DependPackageData>,    // 競合パッケージのリスト
+    // 依存関係のグループ（代替は内側のVecで表現）
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_groups",
+        deserialize_with = "deserialize_groups"
+    )]
+    pub depend: Vec<Vec<DependPackageData>>,
+    // 展開より前に設定まで済ませておく必要がある依存関係
+    #[serde(
+        rename = "pre-depends",
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_groups",
+        deserialize_with = "deserialize_groups"
+    )]
+    pub pre_depends: Vec<Vec<DependPackageData>>,
+    // 通常は一緒にインストールする推奨パッケージ
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_groups",
+        deserialize_with = "deserialize_groups"
+    )]
+    pub recommends: Vec<Vec<DependPackageData>>,
+    // あると便利な提案パッケージ
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_groups",
+        deserialize_with = "deserialize_groups"
+    )]
+    pub suggests: Vec<Vec<DependPackageData>>,
+    // ビルドにだけ必要な依存関係
+    #[serde(
+        rename = "build-depends",
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_groups",
+        deserialize_with = "deserialize_groups"
+    )]
+    pub build_depends: Vec<Vec<DependPackageData>>,
+    // このパッケージが提供する仮想パッケージ
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_groups",
+        deserialize_with = "deserialize_groups"
+    )]
+    pub provides: Vec<Vec<DependPackageData>>,
+    // このパッケージがファイルを置き換えるパッケージ (記録するだけで、依存関係の解決には使わない)
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_groups",
+        deserialize_with = "deserialize_groups"
+    )]
+    pub replaces: Vec<Vec<DependPackageData>>,
+    // このパッケージと一緒に動かなくなるパッケージ（アップグレードで解消できる）
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_groups",
+        deserialize_with = "deserialize_groups"
+    )]
+    pub breaks: Vec<Vec<DependPackageData>>,
+    // 競合パッケージのリスト
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflict: Vec<DependPackageData>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DependPackageData {
     pub name: String,
+    #[serde(default, skip_serializing_if = "is_default")]
     pub version: VersionRange,
+    // プレリリースを受け入れる条件
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub prerelease: PrereleasePolicy,
+}
+
+/// 既定値なら書き出さないための判定。
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+impl PackageAboutData {
+    /// 名前とバージョンだけを持つパッケージの情報を作ります。説明などの項目は空になります。
+    ///
+    /// # 引数
+    ///
+    /// * `name` - パッケージ名。
+    /// * `version` - `scheme` で解釈したバージョン。
+    /// * `scheme` - バージョンの方式。
+    pub fn new(name: &str, version: Version, scheme: Scheme) -> Self {
+        PackageAboutData {
+            name: name.to_string(),
+            version,
+            scheme,
+            description: None,
+            long_description: None,
+            homepage: None,
+            repository: None,
+            license: None,
+            keywords: Vec::new(),
+            categories: Vec::new(),
+            architecture: Vec::new(),
+        }
+    }
+}
+
+/// マニフェストでの `[about.package]` の表現。
+///
+/// バージョンは方式が決まってから解釈する必要があるため、いったん文字列で受け取ります。
+#[derive(Deserialize)]
+struct RawPackageAboutData {
+    name: String,
+    version: String,
+    #[serde(default)]
+    scheme: Scheme,
+    description: Option<String>,
+    long_description: Option<String>,
+    homepage: Option<String>,
+    repository: Option<String>,
+    license: Option<License>,
+    #[serde(default)]
+    keywords: Vec<String>,
+    #[serde(default)]
+    categories: Vec<String>,
+    #[serde(default)]
+    architecture: Vec<String>,
+}
+
+impl<'de> Deserialize<'de> for PackageAboutData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawPackageAboutData::deserialize(deserializer)?;
+        let version =
+            Version::parse_with(&raw.version, raw.scheme).map_err(serde::de::Error::custom)?;
+        Ok(PackageAboutData {
+            name: raw.name,
+            version,
+            scheme: raw.scheme,
+            description: raw.description,
+            long_description: raw.long_description,
+            homepage: raw.homepage,
+            repository: raw.repository,
+            license: raw.license,
+            keywords: raw.keywords,
+            categories: raw.categories,
+            architecture: raw.architecture,
+        })
+    }
+}
+
+/// 一つのテーブルか、テーブルの配列を受け取ります。
+fn one_or_many<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<AuthorAboutData>, D::Error> {
+    struct OneOrMany;
+
+    impl<'de> Visitor<'de> for OneOrMany {
+        type Value = Vec<AuthorAboutData>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "a person table or an array of person tables")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+            AuthorAboutData::deserialize(MapAccessDeserializer::new(map)).map(|one| vec![one])
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+            Vec::deserialize(SeqAccessDeserializer::new(seq))
+        }
+    }
+
+    deserializer.deserialize_any(OneOrMany)
+}
+
+/// マニフェストでの依存関係のグループの表現。
+///
+/// 代替のないグループは一つのテーブルとして、代替のあるグループは配列として書きます。
+#[derive(Serialize)]
+#[serde(untagged)]
+enum DependGroup<'a> {
+    Single(&'a DependPackageData),
+    Alternatives(&'a [DependPackageData]),
+}
+
+fn serialize_groups<S: Serializer>(
+    groups: &[Vec<DependPackageData>],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(groups.iter().map(|group| match group.as_slice() {
+        [single] => DependGroup::Single(single),
+        alternatives => DependGroup::Alternatives(alternatives),
+    }))
+}
+
+fn deserialize_groups<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Vec<DependPackageData>>, D::Error> {
+    /// テーブルか配列かで、単独の依存関係か代替のグループかを見分けます。
+    struct Group(Vec<DependPackageData>);
+
+    impl<'de> Deserialize<'de> for Group {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_any(GroupVisitor)
+        }
+    }
+
+    struct GroupVisitor;
+
+    impl<'de> Visitor<'de> for GroupVisitor {
+        type Value = Group;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "a dependency table or an array of alternatives")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Group, A::Error> {
+            let single = DependPackageData::deserialize(MapAccessDeserializer::new(map))?;
+            Ok(Group(vec![single]))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Group, A::Error> {
+            Vec::deserialize(SeqAccessDeserializer::new(seq)).map(Group)
+        }
+
+        /// `libfoo (>= 1.2) | libbar` の形の一つのグループ
+        fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Group, E> {
+            let mut groups = relation::parse_relations(s).map_err(E::custom)?;
+            match groups.len() {
+                1 => Ok(Group(groups.remove(0))),
+                _ => Err(E::custom(format!(
+                    "expected a single group of alternatives, found `{}`",
+                    s
+                ))),
+            }
+        }
+    }
+
+    /// グループの配列か、`libfoo (>= 1.2) | libbar, baz` の形の文字列
+    struct GroupsVisitor;
+
+    impl<'de> Visitor<'de> for GroupsVisitor {
+        type Value = Vec<Vec<DependPackageData>>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "an array of dependency groups or a relation string")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+            let groups = Vec::<Group>::deserialize(SeqAccessDeserializer::new(seq))?;
+            Ok(groups.into_iter().map(|group| group.0).collect())
+        }
+
+        fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Self::Value, E> {
+            relation::parse_relations(s).map_err(E::custom)
+        }
+    }
+
+    deserializer.deserialize_any(GroupsVisitor)
+}
+
+impl RelationData {
+    /// 関係が一つもないかを判定します。
+    pub fn is_empty(&self) -> bool {
+        self.groups().iter().all(|(_, groups)| groups.is_empty()) && self.conflict.is_empty()
+    }
+
+    /// グループで表す関係の、マニフェストでのキー
+    pub const GROUP_KEYS: [&'static str; 8] = [
+        "pre-depends",
+        "depend",
+        "recommends",
+        "suggests",
+        "build-depends",
+        "provides",
+        "replaces",
+        "breaks",
+    ];
+
+    /// グループで表す関係を、マニフェストでのキーと組にしてすべて返します。
+    pub fn groups(&self) -> [(&'static str, &Vec<Vec<DependPackageData>>); 8] {
+        let [
+            pre_depends,
+            depend,
+            recommends,
+            suggests,
+            build_depends,
+            provides,
+            replaces,
+            breaks,
+        ] = Self::GROUP_KEYS;
+        [
+            (pre_depends, &self.pre_depends),
+            (depend, &self.depend),
+            (recommends, &self.recommends),
+            (suggests, &self.suggests),
+            (build_depends, &self.build_depends),
+            (provides, &self.provides),
+            (replaces, &self.replaces),
+            (breaks, &self.breaks),
+        ]
+    }
+
+    /// インストールするときに満たす必要がある依存関係のグループを返します。
+    ///
+    /// `pre-depends` のグループが先に、`depend` のグループが後に並びます。
+    pub fn required_groups(&self) -> impl Iterator<Item = &Vec<DependPackageData>> {
+        self.pre_depends.iter().chain(&self.depend)
+    }
+}
+
+impl PackageData {
+    /// パッケージが依存関係を満たすかを判定します。
+    ///
+    /// パッケージ自身が満たすか、`provides` で提供する仮想パッケージが満たせばよいとします。
+    /// バージョンのない提供は、バージョンを指定しない依存関係だけを満たします。
+    pub fn satisfies(&self, depend: &DependPackageData) -> bool {
+        depend.is_satisfied_by(&self.about.package)
+            || self.relation.provides.iter().flatten().any(|provided| {
+                provided.name == depend.name
+                    && (depend.version == VersionRange::default()
+                        || (provided.version != VersionRange::default()
+                            && !provided.version.is_disjoint(&depend.version)))
+            })
+    }
+
+    /// このパッケージが `other` と一緒にインストールできないかを判定します。
+    ///
+    /// `conflict` と `breaks` の両方を調べます。自分自身とは競合しません。
+    pub fn is_incompatible_with(&self, other: &PackageData) -> bool {
+        self.about.package.name != other.about.package.name
+            && self
+                .relation
+                .conflict
+                .iter()
+                .chain(self.relation.breaks.iter().flatten())
+                .any(|relation| other.satisfies(relation))
+    }
+}
+
+impl DependPackageData {
+    /// パッケージがこの依存関係を満たすかを判定します。
+    ///
+    /// バージョンはパッケージが宣言している方式の規則で比較され、
+    /// プレリリースはこの依存関係の `prerelease` に従って受け入れられます。
+    pub fn is_satisfied_by(&self, package: &PackageAboutData) -> bool {
+        self.name == package.name
+            && self
+                .version
+                .matches_with(&package.version, package.scheme, self.prerelease)
+    }
 }
 
 impl Display for PackageData {
@@ -42,29 +444,79 @@ impl Display for PackageData {
             self.about.package.name.cyan()
         )?;
         writeln!(f, "{} {}", "Version:".bold(), self.about.package.version)?;
-        writeln!(
-            f,
-            "{} {} <{}>",
-            "Author:".bold(),
-            self.about.author.name,
-            self.about.author.email
-        )?;
+        let package = &self.about.package;
+        // 省略できる項目は、値があるときだけ表示する
+        let license = package.license.as_ref().map(License::to_string);
+        let optional = [
+            ("Description:", &package.description),
+            ("Homepage:", &package.homepage),
+            ("Repository:", &package.repository),
+            ("License:", &license),
+        ];
+        for (label, value) in optional {
+            if let Some(value) = value {
+                writeln!(f, "{} {}", label.bold(), value)?;
+            }
+        }
+        let lists = [
+            ("Keywords:", &package.keywords),
+            ("Categories:", &package.categories),
+            ("Architecture:", &package.architecture),
+        ];
+        for (label, values) in lists {
+            if !values.is_empty() {
+                writeln!(f, "{} {}", label.bold(), values.join(", "))?;
+            }
+        }
+        let people = [
+            ("Author:", "Authors:", &self.about.authors),
+            ("Maintainer:", "Maintainers:", &self.about.maintainers),
+        ];
+        for (singular, plural, people) in people {
+            match people.as_slice() {
+                [] => {}
+                [person] => writeln!(f, "{} {}", singular.bold(), person)?,
+                people => {
+                    writeln!(f, "{}", plural.bold())?;
+                    for person in people {
+                        writeln!(f, "  - {}", person)?;
+                    }
+                }
+            }
+        }
+        if let Some(long_description) = &package.long_description {
+            writeln!(f, "\n{}", long_description.trim_end())?;
+        }
 
-        if !self.relation.depend.is_empty() {
-            writeln!(f, "\n{}", "Dependencies:".bold())?;
-            for group in &self.relation.depend {
+        let relation = &self.relation;
+        let sections = [
+            ("Pre-Dependencies:", &relation.pre_depends, Color::Green),
+            ("Dependencies:", &relation.depend, Color::Green),
+            ("Recommends:", &relation.recommends, Color::Cyan),
+            ("Suggests:", &relation.suggests, Color::Cyan),
+            ("Build-Dependencies:", &relation.build_depends, Color::Green),
+            ("Provides:", &relation.provides, Color::Blue),
+            ("Replaces:", &relation.replaces, Color::Yellow),
+            ("Breaks:", &relation.breaks, Color::Red),
+        ];
+        for (title, groups, color) in sections {
+            if groups.is_empty() {
+                continue;
+            }
+            writeln!(f, "\n{}", title.bold())?;
+            for group in groups {
                 if group.len() == 1 {
-                    // 単一の依存関係
+                    // 単一の関係
                     let dep = &group[0];
-                    writeln!(f, "  - {} ({})", dep.name.green(), dep.version)?;
+                    writeln!(f, "  - {} ({})", dep.name.color(color), dep.version)?;
                 } else {
-                    // 代替依存のグループ
+                    // 代替のグループ
                     let alts: Vec<String> = group
                         .iter()
                         .map(|d| format!("{} ({})", d.name, d.version))
                         .collect();
                     let alts_str = alts.join(" | ");
-                    writeln!(f, "  - ({})", alts_str.green())?;
+                    writeln!(f, "  - ({})", alts_str.color(color))?;
                 }
             }
         }
@@ -80,23 +532,28 @@ impl Display for PackageData {
     }
 }
 
+impl Display for AuthorAboutData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} <{}>", self.name, self.email)
+    }
+}
+
 impl Default for PackageData {
     fn default() -> Self {
         PackageData {
             about: AboutData {
-                author: AuthorAboutData {
+                package: PackageAboutData::new(
+                    "default-package",
+                    Version::default(),
+                    Scheme::default(),
+                ),
+                authors: vec![AuthorAboutData {
                     name: "default".to_string(),
                     email: "default@default.com".to_string(),
-                },
-                package: PackageAboutData {
-                    name: "default-package".to_string(),
-                    version: Version::default(),
-                },
-            },
-            relation: RelationData {
-                depend: Vec::new(),
-                conflict: Vec::new(),
+                }],
+                maintainers: Vec::new(),
             },
+            relation: RelationData::default(),
         }
     }
 }
@@ -109,4 +566,145 @@ mod tests {
         let data = PackageData::default();
         println!("{}", data);
     }
+
+    #[test]
+    fn display_shows_metadata() {
+        colored::control::set_override(false);
+        let mut data = PackageData::default();
+        let text = data.to_string();
+        assert!(
+            text.contains("Author: default <default@default.com>\n"),
+            "{}",
+            text
+        );
+        assert!(!text.contains("License:"), "{}", text);
+
+        data.about.package.license = Some("MIT".parse().unwrap());
+        data.about.package.keywords = vec!["cli".to_string(), "tool".to_string()];
+        data.about.package.long_description = Some("Line one.\nLine two.\n".to_string());
+        data.about.authors.push(AuthorAboutData {
+            name: "Someone".to_string(),
+            email: "someone@example.com".to_string(),
+        });
+        let text = data.to_string();
+        assert!(text.contains("License: MIT\n"), "{}", text);
+        assert!(text.contains("Keywords: cli, tool\n"), "{}", text);
+        assert!(
+            text.contains("Authors:\n  - default <default@default.com>\n  - Someone"),
+            "{}",
+            text
+        );
+        assert!(text.contains("\n\nLine one.\nLine two.\n"), "{}", text);
+    }
+
+    fn depend(name: &str, version: &str) -> DependPackageData {
+        DependPackageData {
+            name: name.to_string(),
+            version: version.parse().unwrap(),
+            prerelease: PrereleasePolicy::default(),
+        }
+    }
+
+    #[test]
+    fn provides_and_breaks() {
+        let mut mta = PackageData::default();
+        mta.about.package.name = "postfix".to_string();
+        mta.relation.provides = vec![
+            vec![depend("mail-transport-agent", "*")],
+            vec![depend("sendmail", "= 8.0")],
+        ];
+        assert!(mta.satisfies(&depend("postfix", ">= 1.0")));
+        assert!(mta.satisfies(&depend("mail-transport-agent", "*")));
+        // バージョンのない提供は、バージョンを指定した依存関係を満たさない
+        assert!(!mta.satisfies(&depend("mail-transport-agent", ">= 1.0")));
+        assert!(mta.satisfies(&depend("sendmail", ">= 8.0")));
+        assert!(!mta.satisfies(&depend("sendmail", ">= 9.0")));
+
+        let mut other = PackageData::default();
+        other.about.package.name = "exim".to_string();
+        assert!(!other.is_incompatible_with(&mta));
+        other.relation.breaks = vec![vec![depend("sendmail", "< 9.0")]];
+        assert!(other.is_incompatible_with(&mta));
+        other.relation.breaks.clear();
+        other.relation.conflict = vec![depend("mail-transport-agent", "*")];
+        assert!(other.is_incompatible_with(&mta));
+        // 仮想パッケージを提供しつつ競合する自分自身とは競合しない
+        assert!(!mta.is_incompatible_with(&mta));
+    }
+
+    #[test]
+    fn display_shows_relations() {
+        colored::control::set_override(false);
+        let mut data = PackageData::default();
+        data.relation.pre_depends = vec![vec![depend("libc", ">= 2.0")]];
+        data.relation.recommends = vec![vec![depend("less", "*"), depend("more", "*")]];
+        data.relation.breaks = vec![vec![depend("old-tool", "< 1.0")]];
+        let text = data.to_string();
+        assert!(
+            text.contains("\nPre-Dependencies:\n  - libc (>= 2.0)\n"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("\nRecommends:\n  - (less (*) | more (*))\n"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("\nBreaks:\n  - old-tool (< 1.0)\n"),
+            "{}",
+            text
+        );
+        assert!(!text.contains("Suggests:"), "{}", text);
+    }
+
+    #[test]
+    fn depend_uses_package_scheme() {
+        let package = PackageAboutData::new(
+            "libfoo",
+            Version::parse_with("1.0~rc1", Scheme::Dpkg).unwrap(),
+            Scheme::Dpkg,
+        );
+        let depend = DependPackageData {
+            name: "libfoo".to_string(),
+            version: "< 1.0".parse().unwrap(),
+            prerelease: PrereleasePolicy::Include,
+        };
+        assert!(depend.is_satisfied_by(&package));
+
+        // 同じ文字列でも、PEP 440 ではプレリリース、RPM では 1.0 より後になる
+        let package = PackageAboutData {
+            scheme: Scheme::Pep440,
+            version: Version::parse_with("1.0rc1", Scheme::Pep440).unwrap(),
+            ..package
+        };
+        assert!(depend.is_satisfied_by(&package));
+        let package = PackageAboutData {
+            scheme: Scheme::Rpm,
+            version: Version::parse_with("1.0rc1", Scheme::Rpm).unwrap(),
+            ..package
+        };
+        assert!(!depend.is_satisfied_by(&package));
+    }
+
+    #[test]
+    fn prerelease_policy_is_per_dependency() {
+        let package =
+            PackageAboutData::new("libfoo", "2.0.0-beta.1".parse().unwrap(), Scheme::SemVer);
+        let mut depend = DependPackageData {
+            name: "libfoo".to_string(),
+            version: ">= 1.0".parse().unwrap(),
+            prerelease: PrereleasePolicy::default(),
+        };
+        assert!(!depend.is_satisfied_by(&package));
+        depend.prerelease = PrereleasePolicy::Include;
+        assert!(depend.is_satisfied_by(&package));
+
+        // 同じリリースのプレリリースを書けば、既定の方針でも受け入れられる
+        depend.version = ">= 2.0.0-alpha".parse().unwrap();
+        depend.prerelease = PrereleasePolicy::default();
+        assert!(depend.is_satisfied_by(&package));
+        depend.prerelease = PrereleasePolicy::Exclude;
+        assert!(!depend.is_satisfied_by(&package));
+    }
 }
@@ -0,0 +1,909 @@
+//! マニフェストの検証と、位置付きの診断メッセージ。
+//!
+//! 診断は rustc と同じ形で、ファイル名・行・列と、問題の部分に `^` で下線を引いた行を表示します。
+//!
+//! ```text
+//! error: package name `Hello_World` is not kebab-case
+//!  --> ipkg.toml:2:8
+//!   |
+//! 2 | name = "Hello_World"
+//!   |        ^^^^^^^^^^^^^ use lowercase letters, digits and `-`
+//! ```
+use super::license::License;
+use super::manifest::ManifestFormat;
+use super::relation;
+use super::{PackageData, RelationData};
+use crate::modules::version::{
+    PrereleasePolicy, Scheme, Version, VersionError, VersionErrorKind, VersionRange,
+};
+use crate::utils::shell::question::is_kebab_case;
+use colored::Colorize;
+use regex::Regex;
+use serde_json::Value;
+use std::{fmt, fs, ops::Range, path::Path, sync::LazyLock};
+use toml_edit::{ImDocument, Item};
+
+static EMAIL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)+$").unwrap());
+
+/// 各テーブルで使えるキー。ほかのキーは読み込むときに無視されるので警告します。
+const ROOT_KEYS: &[&str] = &["about", "relation"];
+const ABOUT_KEYS: &[&str] = &["package", "author", "authors", "maintainer", "maintainers"];
+const PACKAGE_KEYS: &[&str] = &[
+    "name",
+    "version",
+    "scheme",
+    "description",
+    "long_description",
+    "homepage",
+    "repository",
+    "license",
+    "keywords",
+    "categories",
+    "architecture",
+];
+const PERSON_KEYS: &[&str] = &["name", "email"];
+const DEPEND_KEYS: &[&str] = &["name", "version", "prerelease"];
+
+/// 診断の重大度。
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "{}", "error".red().bold()),
+            Severity::Warning => write!(f, "{}", "warning".yellow().bold()),
+        }
+    }
+}
+
+/// マニフェストの一つの問題。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// 問題のある部分のバイト範囲。位置が分からなければ `None`
+    pub span: Option<Range<usize>>,
+    /// 下線に添える説明
+    pub label: String,
+}
+
+impl Diagnostic {
+    fn error(message: String, span: Option<Range<usize>>, label: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message,
+            span,
+            label: label.into(),
+        }
+    }
+
+    fn warning(message: String, span: Option<Range<usize>>, label: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            message,
+            span,
+            label: label.into(),
+        }
+    }
+
+    /// rustc と同じ形の表示にします。
+    ///
+    /// # 引数
+    ///
+    /// * `path` - 表示するファイル名。
+    /// * `source` - マニフェストの内容 (`span` はこの中の位置)。
+    ///
+    /// # 戻り値
+    ///
+    /// 末尾に改行を含む、複数行の文字列。
+    pub fn render(&self, path: &str, source: &str) -> String {
+        let mut out = format!("{}: {}\n", self.severity, self.message.bold());
+        let Some(span) = &self.span else {
+            out.push_str(&format!(" {} {}\n", "-->".blue().bold(), path));
+            return out;
+        };
+        let start = span.start.min(source.len());
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        let line_number = source[..start].matches('\n').count() + 1;
+        let column = source[line_start..start].chars().count() + 1;
+        let line = source[line_start..line_end].trim_end_matches('\r');
+        // 下線は最初の行の中に収める
+        let end = span.end.clamp(start, line_start + line.len());
+        let width = source[start..end].chars().count().max(1);
+
+        let gutter = " ".repeat(line_number.to_string().len());
+        let bar = "|".blue().bold();
+        let marker = "^".repeat(width);
+        let marker = match self.severity {
+            Severity::Error => marker.red().bold(),
+            Severity::Warning => marker.yellow().bold(),
+        };
+        let label = match self.severity {
+            Severity::Error => self.label.red().bold(),
+            Severity::Warning => self.label.yellow().bold(),
+        };
+        out.push_str(&format!(
+            "{}{} {}:{}:{}\n",
+            gutter,
+            "-->".blue().bold(),
+            path,
+            line_number,
+            column
+        ));
+        out.push_str(&format!("{} {}\n", gutter, bar));
+        out.push_str(&format!(
+            "{} {} {}\n",
+            line_number.to_string().blue().bold(),
+            bar,
+            line
+        ));
+        let padding = " ".repeat(column - 1);
+        let underline = format!("{}{} {}", padding, marker, label);
+        out.push_str(&format!("{} {} {}\n", gutter, bar, underline.trim_end()));
+        out
+    }
+}
+
+/// マニフェストの中の値の位置を表すパスの一部分。
+#[derive(Debug, Clone, Copy)]
+enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+/// パスから、ソース中の値の位置を探すもの。
+enum Locator<'a> {
+    /// TOML はパーサーが値の位置を覚えている
+    Toml(ImDocument<&'a str>),
+    /// JSON と YAML は、値の構造を手がかりにソースの中のキーを探す
+    Text { source: &'a str, root: &'a Value },
+}
+
+impl Locator<'_> {
+    /// パスが指す値のバイト範囲を返します。文字列の値は引用符を含みます。
+    fn locate(&self, path: &[Segment]) -> Option<Range<usize>> {
+        match self {
+            Locator::Toml(document) => toml_item(document, path)?.span(),
+            Locator::Text { source, root } => locate_in_text(source, root, path),
+        }
+    }
+
+    /// パスの最後のキーそのもののバイト範囲を返します。引用符があれば含みます。
+    fn locate_key(&self, path: &[Segment]) -> Option<Range<usize>> {
+        let (Segment::Key(key), parent) = path.split_last()? else {
+            return None;
+        };
+        match self {
+            Locator::Toml(document) => {
+                let table = toml_item(document, parent)?.as_table_like()?;
+                table.get_key_value(key)?.0.span()
+            }
+            Locator::Text { source, root } => find_in_text(source, root, path).map(|(key, _)| key),
+        }
+    }
+}
+
+/// TOML の文書の中で、パスが指す値を返します。
+fn toml_item<'a>(document: &'a ImDocument<&str>, path: &[Segment]) -> Option<&'a Item> {
+    let mut item = document.as_item();
+    for segment in path {
+        item = match *segment {
+            Segment::Key(key) => item.get(key)?,
+            Segment::Index(index) => item.get(index)?,
+        };
+    }
+    Some(item)
+}
+
+/// `"key"`、`'key'` または `key` の後に空白と `:` が続く箇所を、ソースの先頭から順に返します。
+///
+/// それぞれ、キーの範囲 (引用符を含む) と `:` の直後の位置の組です。
+fn key_positions<'a>(
+    source: &'a str,
+    key: &'a str,
+) -> impl Iterator<Item = (Range<usize>, usize)> + 'a {
+    source.match_indices(key).filter_map(move |(i, _)| {
+        let end = i + key.len();
+        let before = source[..i].chars().next_back();
+        let (start, end) = match before {
+            Some(quote @ ('"' | '\'')) => {
+                if !source[end..].starts_with(quote) {
+                    return None;
+                }
+                (i - 1, end + 1)
+            }
+            // 別のキーの一部 (`pre-depends` の中の `depends` など) は除く
+            Some(c) if c.is_alphanumeric() || "_-.".contains(c) => return None,
+            _ => (i, end),
+        };
+        let rest = &source[end..];
+        let colon = end + (rest.len() - rest.trim_start().len());
+        source[colon..]
+            .starts_with(':')
+            .then_some((start..end, colon + 1))
+    })
+}
+
+/// JSON または YAML のソースの中で、パスの最後のキーの範囲と、その `:` の直後の位置を探します。
+///
+/// キーは `"name":`、`'name':` または `name:` の形で現れるものとし、配列の添字は
+/// それより前の要素に同じキーがいくつ現れるかを数えて読み飛ばします。
+fn find_in_text(source: &str, root: &Value, path: &[Segment]) -> Option<(Range<usize>, usize)> {
+    let mut position = 0;
+    let mut found_key = 0..0;
+    let mut value = root;
+    let mut skip = 0;
+    for (i, segment) in path.iter().enumerate() {
+        match *segment {
+            Segment::Index(index) => {
+                let elements = value.as_array()?;
+                // 次に探すキーが、前の要素にいくつ現れるか
+                if let Some(Segment::Key(key)) = path[i + 1..]
+                    .iter()
+                    .find(|segment| matches!(segment, Segment::Key(_)))
+                {
+                    skip += elements[..index.min(elements.len())]
+                        .iter()
+                        .map(|element| count_key(element, key))
+                        .sum::<usize>();
+                }
+                value = elements.get(index)?;
+            }
+            Segment::Key(key) => {
+                let (range, end) = key_positions(&source[position..], key).nth(skip)?;
+                found_key = position + range.start..position + range.end;
+                position += end;
+                skip = 0;
+                value = value.get(key)?;
+            }
+        }
+    }
+    Some((found_key, position))
+}
+
+/// JSON または YAML のソースの中で、パスが指す値の位置を探します。
+fn locate_in_text(source: &str, root: &Value, path: &[Segment]) -> Option<Range<usize>> {
+    let (_, position) = find_in_text(source, root, path)?;
+    // キーの後ろの値の範囲
+    let rest = &source[position..];
+    let start = position + (rest.len() - rest.trim_start().len());
+    let rest = &source[start..];
+    let length = match rest.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let mut escaped = false;
+            let close = rest[1..].char_indices().find(|&(_, c)| {
+                let found = c == quote && !escaped;
+                escaped = c == '\\' && !escaped;
+                found
+            });
+            close.map_or(rest.len(), |(i, _)| i + 2)
+        }
+        _ => rest
+            .find(['\n', ',', '}', ']'])
+            .map_or(rest.len(), |i| rest[..i].trim_end().len()),
+    };
+    Some(start..start + length)
+}
+
+/// 値の中にキーが何回現れるかを数えます。
+fn count_key(value: &Value, key: &str) -> usize {
+    match value {
+        Value::Object(map) => map
+            .iter()
+            .map(|(k, v)| usize::from(k == key) + count_key(v, key))
+            .sum(),
+        Value::Array(elements) => elements.iter().map(|v| count_key(v, key)).sum(),
+        _ => 0,
+    }
+}
+
+/// 値を調べながら診断を集めるもの。
+struct Checker<'a> {
+    source: &'a str,
+    locator: Locator<'a>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    /// 文字列の値の中の `inner` の範囲を、ソース全体の範囲にします。
+    fn inner_span(&self, path: &[Segment], inner: Range<usize>) -> Option<Range<usize>> {
+        let span = self.locator.locate(path)?;
+        let quoted = matches!(self.source.as_bytes().get(span.start), Some(b'"' | b'\''));
+        let start = span.start + usize::from(quoted);
+        let end = span.end - usize::from(quoted);
+        Some((start + inner.start).min(end)..(start + inner.end).min(end))
+    }
+
+    fn error(&mut self, path: &[Segment], message: String, label: impl Into<String>) {
+        let span = self.locator.locate(path);
+        self.diagnostics
+            .push(Diagnostic::error(message, span, label));
+    }
+
+    /// バージョンの解釈エラーを、値の中の問題のある部分を指す診断にします。
+    fn version_error(&mut self, path: &[Segment], what: &str, error: &VersionError) {
+        let span = self.inner_span(path, error.span.clone());
+        let diagnostic = match &error.kind {
+            VersionErrorKind::Unsatisfiable { conflicting } => Diagnostic::error(
+                format!("{} `{}` can never be satisfied", what, error.input),
+                span,
+                format!("conflicts with `{}`", conflicting),
+            ),
+            kind => Diagnostic::error(
+                format!("invalid {} `{}`", what, error.input),
+                span,
+                kind.to_string(),
+            ),
+        };
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// テーブルに使われないキーがないかを調べます。
+    fn check_keys(&mut self, path: &[Segment], table: &Value, known: &[&str]) {
+        let Some(map) = table.as_object() else {
+            return;
+        };
+        for key in map.keys().filter(|key| !known.contains(&key.as_str())) {
+            let path = [path, &[Segment::Key(key)]].concat();
+            let span = self.locator.locate_key(&path);
+            let message = match path_name(&path[..path.len() - 1]) {
+                Some(parent) => format!("unknown key `{}` in `{}`", key, parent),
+                None => format!("unknown key `{}`", key),
+            };
+            self.diagnostics
+                .push(Diagnostic::warning(message, span, "this key is ignored"));
+        }
+    }
+
+    /// パッケージ名がケバブケースかを調べます。
+    fn check_name(&mut self, path: &[Segment], name: &str) {
+        if !is_kebab_case(name) {
+            self.error(
+                path,
+                format!("package name `{}` is not kebab-case", name),
+                "use lowercase letters, digits and `-`",
+            );
+        }
+    }
+
+    fn check_package(&mut self, package: &Value) {
+        let path = [Segment::Key("about"), Segment::Key("package")];
+        let at = |key| [path[0], path[1], Segment::Key(key)];
+        self.check_keys(&path, package, PACKAGE_KEYS);
+        if let Some(name) = package.get("name").and_then(Value::as_str) {
+            self.check_name(&at("name"), name);
+        }
+        let scheme = match package.get("scheme").and_then(Value::as_str) {
+            Some(scheme) => match scheme.parse::<Scheme>() {
+                Ok(scheme) => Some(scheme),
+                Err(_) => {
+                    self.error(
+                        &at("scheme"),
+                        format!("unknown version scheme `{}`", scheme),
+                        "expected semver, dpkg, rpm, pep440 or calver",
+                    );
+                    None
+                }
+            },
+            None => Some(Scheme::default()),
+        };
+        if let (Some(scheme), Some(version)) =
+            (scheme, package.get("version").and_then(Value::as_str))
+            && let Err(error) = Version::parse_with(version, scheme)
+        {
+            self.version_error(&at("version"), "version", &error);
+        }
+        if let Some(license) = package.get("license").and_then(Value::as_str)
+            && let Err(error) = License::parse(license)
+        {
+            let span = self.inner_span(&at("license"), error.span.clone());
+            self.diagnostics.push(Diagnostic::error(
+                format!("invalid license expression `{}`", license),
+                span,
+                error.reason.to_string(),
+            ));
+        }
+    }
+
+    /// 作者と保守担当者のメールアドレスを調べます。
+    ///
+    /// それぞれ一人のテーブル (`author`) でも、配列 (`authors`) でも書けます。
+    fn check_people(&mut self, about: &Value) {
+        self.check_keys(&[Segment::Key("about")], about, ABOUT_KEYS);
+        let keys = [
+            ("author", "authors", "author"),
+            ("maintainer", "maintainers", "maintainer"),
+        ];
+        for (single, plural, what) in keys {
+            for key in [single, plural] {
+                match about.get(key) {
+                    Some(Value::Array(people)) => {
+                        for (i, person) in people.iter().enumerate() {
+                            let path =
+                                [Segment::Key("about"), Segment::Key(key), Segment::Index(i)];
+                            self.check_person(&path, person, what);
+                        }
+                    }
+                    Some(person) => {
+                        self.check_person(&[Segment::Key("about"), Segment::Key(key)], person, what)
+                    }
+                    None => {}
+                }
+            }
+        }
+    }
+
+    fn check_person(&mut self, path: &[Segment], person: &Value, what: &str) {
+        self.check_keys(path, person, PERSON_KEYS);
+        if let Some(email) = person.get("email").and_then(Value::as_str)
+            && !EMAIL_REGEX.is_match(email)
+        {
+            let path = [path, &[Segment::Key("email")]].concat();
+            self.error(
+                &path,
+                format!("{} email `{}` is not well-formed", what, email),
+                "expected an address like `name@example.com`",
+            );
+        }
+    }
+
+    /// 依存関係または競合の一つを調べ、名前を返します。
+    fn check_relation(&mut self, path: &[Segment], relation: &Value) -> Option<String> {
+        let at = |key| [path, &[Segment::Key(key)]].concat();
+        self.check_keys(path, relation, DEPEND_KEYS);
+        let name = relation.get("name").and_then(Value::as_str)?;
+        self.check_name(&at("name"), name);
+        if let Some(range) = relation.get("version").and_then(Value::as_str) {
+            match range.parse::<VersionRange>() {
+                Ok(parsed) if parsed.is_empty() => {
+                    let span = self.locator.locate(&at("version"));
+                    self.diagnostics.push(Diagnostic::warning(
+                        format!("version range of `{}` matches no version", name),
+                        span,
+                        "no version can be selected",
+                    ));
+                }
+                Ok(_) => {}
+                Err(error) => self.version_error(&at("version"), "version range", &error),
+            }
+        }
+        if let Some(policy) = relation.get("prerelease").and_then(Value::as_str)
+            && policy.parse::<PrereleasePolicy>().is_err()
+        {
+            self.error(
+                &at("prerelease"),
+                format!("unknown pre-release policy `{}`", policy),
+                "expected same-release, include or exclude",
+            );
+        }
+        Some(name.to_string())
+    }
+
+    /// `libfoo (>= 1.2) | libbar` の形の関係を調べ、名前を返します。
+    fn check_relation_string(&mut self, path: &[Segment], text: &str) -> Vec<String> {
+        match relation::parse_relations(text) {
+            Ok(groups) => groups.into_iter().flatten().map(|d| d.name).collect(),
+            Err(error) => {
+                let span = self.inner_span(path, error.span.clone());
+                self.diagnostics.push(Diagnostic::error(
+                    format!("invalid relation `{}`", text),
+                    span,
+                    error.kind.to_string(),
+                ));
+                Vec::new()
+            }
+        }
+    }
+
+    fn check_relations(&mut self, relation: &Value) {
+        let known = [RelationData::GROUP_KEYS.as_slice(), &["conflict"]].concat();
+        self.check_keys(&[Segment::Key("relation")], relation, &known);
+        // インストールに必要な依存関係の名前と、それを書いたキー
+        let mut depends = Vec::new();
+        for key in RelationData::GROUP_KEYS {
+            let path = [Segment::Key("relation"), Segment::Key(key)];
+            let mut names = Vec::new();
+            match relation.get(key) {
+                Some(Value::String(text)) => names = self.check_relation_string(&path, text),
+                Some(Value::Array(groups)) => {
+                    for (i, group) in groups.iter().enumerate() {
+                        let path = [path.as_slice(), &[Segment::Index(i)]].concat();
+                        match group {
+                            Value::Array(alternatives) => {
+                                for (j, alternative) in alternatives.iter().enumerate() {
+                                    let path = [path.as_slice(), &[Segment::Index(j)]].concat();
+                                    names.extend(self.check_relation(&path, alternative));
+                                }
+                            }
+                            Value::String(text) => {
+                                names.extend(self.check_relation_string(&path, text))
+                            }
+                            group => names.extend(self.check_relation(&path, group)),
+                        }
+                    }
+                }
+                _ => {}
+            }
+            if matches!(key, "pre-depends" | "depend") {
+                depends.extend(names.into_iter().map(|name| (name, key)));
+            }
+        }
+        let conflicts = relation.get("conflict").and_then(Value::as_array);
+        for (i, conflict) in conflicts.into_iter().flatten().enumerate() {
+            let path = [
+                Segment::Key("relation"),
+                Segment::Key("conflict"),
+                Segment::Index(i),
+            ];
+            if let Some(name) = self.check_relation(&path, conflict)
+                && let Some((_, key)) = depends.iter().find(|(depend, _)| *depend == name)
+            {
+                let path = [path.as_slice(), &[Segment::Key("name")]].concat();
+                self.error(
+                    &path,
+                    format!("`{}` is both a dependency and a conflict", name),
+                    format!("also listed in `relation.{}`", key),
+                );
+            }
+        }
+    }
+}
+
+/// パスを `relation.depend[0]` の形にします。空のパスなら `None` を返します。
+fn path_name(path: &[Segment]) -> Option<String> {
+    let mut name = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if name.is_empty() => name.push_str(key),
+            Segment::Key(key) => name.push_str(&format!(".{}", key)),
+            Segment::Index(index) => name.push_str(&format!("[{}]", index)),
+        }
+    }
+    (!name.is_empty()).then_some(name)
+}
+
+/// 構文エラーの位置を、行と列からバイト位置にします (どちらも1始まり)。
+fn offset_of(source: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = source
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    let column_offset = source[line_start..]
+        .char_indices()
+        .nth(column.saturating_sub(1))
+        .map_or(source.len() - line_start, |(i, _)| i);
+    line_start + column_offset
+}
+
+/// 構文や型の誤りを、位置付きの診断にします。
+fn syntax_error<T: serde::de::DeserializeOwned>(
+    source: &str,
+    format: ManifestFormat,
+) -> Result<T, Diagnostic> {
+    let (message, span) = match format {
+        ManifestFormat::Toml => match toml_edit::de::from_str(source) {
+            Ok(value) => return Ok(value),
+            Err(e) => (e.message().to_string(), e.span()),
+        },
+        ManifestFormat::Json => match serde_json::from_str(source) {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                let at = offset_of(source, e.line(), e.column());
+                (e.to_string(), Some(at..at))
+            }
+        },
+        ManifestFormat::Yaml => match serde_yaml::from_str(source) {
+            Ok(value) => return Ok(value),
+            Err(e) => (e.to_string(), e.location().map(|at| at.index()..at.index())),
+        },
+    };
+    // serde_json と serde_yaml のメッセージには位置が含まれるので取り除く
+    let message = message
+        .split(" at line ")
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    Err(Diagnostic::error(
+        "manifest could not be read".to_string(),
+        span,
+        message,
+    ))
+}
+
+/// マニフェストの内容を検証し、見つかった問題をすべて返します。
+///
+/// # 引数
+///
+/// * `source` - マニフェストの内容。
+/// * `format` - マニフェストの形式。
+///
+/// # 戻り値
+///
+/// ソースの中の順に並んだ診断の列。問題がなければ空です。
+pub fn check_manifest_str(source: &str, format: ManifestFormat) -> Vec<Diagnostic> {
+    let root: Value = match syntax_error(source, format) {
+        Ok(root) => root,
+        Err(diagnostic) => return vec![diagnostic],
+    };
+    let locator = match format {
+        ManifestFormat::Toml => match ImDocument::parse(source) {
+            Ok(document) => Locator::Toml(document),
+            Err(_) => Locator::Text {
+                source,
+                root: &root,
+            },
+        },
+        _ => Locator::Text {
+            source,
+            root: &root,
+        },
+    };
+    let mut checker = Checker {
+        source,
+        locator,
+        diagnostics: Vec::new(),
+    };
+    checker.check_keys(&[], &root, ROOT_KEYS);
+    if let Some(package) = root.pointer("/about/package") {
+        checker.check_package(package);
+    }
+    if let Some(about) = root.get("about") {
+        checker.check_people(about);
+    }
+    if let Some(relation) = root.get("relation") {
+        checker.check_relations(relation);
+    }
+    let mut diagnostics = checker.diagnostics;
+    // 値の誤りがなければ、欠けているキーや型の誤りを調べる
+    if !diagnostics.iter().any(|d| d.severity == Severity::Error)
+        && let Err(diagnostic) = syntax_error::<PackageData>(source, format)
+    {
+        diagnostics.push(diagnostic);
+    }
+    diagnostics.sort_by_key(|d| d.span.as_ref().map_or(usize::MAX, |span| span.start));
+    diagnostics
+}
+
+/// マニフェストのファイルを検証します。形式は拡張子で判定します。
+///
+/// # 戻り値
+///
+/// * `Ok((String, Vec<Diagnostic>))` - ファイルの内容と診断の列。
+/// * `Err(String)` - ファイルが読めないか、形式が分からない場合。
+pub fn check_manifest(path: &Path) -> Result<(String, Vec<Diagnostic>), String> {
+    let format = ManifestFormat::from_path(path)?;
+    let source = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let diagnostics = check_manifest_str(&source, format);
+    Ok((source, diagnostics))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"[about.package]
+name = "Hello_World"
+version = "1.2.3-rc.01"
+
+[about.author]
+name = "Infinitys"
+email = "infinitys at example.com"
+
+[relation]
+depend = [
+    { name = "libfoo", version = ">= 2.0, < 1.0" },
+    [{ name = "python3" }, { name = "python", version = ">= 3.0, ~> 4" }],
+]
+conflict = [{ name = "libfoo" }]
+"#;
+
+    fn render_all(diagnostics: &[Diagnostic], path: &str, source: &str) -> String {
+        colored::control::set_override(false);
+        diagnostics
+            .iter()
+            .map(|d| d.render(path, source))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn toml_diagnostics_point_at_values() {
+        let diagnostics = check_manifest_str(MANIFEST, ManifestFormat::Toml);
+        assert_eq!(
+            render_all(&diagnostics, "ipkg.toml", MANIFEST),
+            r#"error: package name `Hello_World` is not kebab-case
+ --> ipkg.toml:2:8
+  |
+2 | name = "Hello_World"
+  |        ^^^^^^^^^^^^^ use lowercase letters, digits and `-`
+
+error: invalid version `1.2.3-rc.01`
+ --> ipkg.toml:3:21
+  |
+3 | version = "1.2.3-rc.01"
+  |                     ^^ numeric identifier has a leading zero
+
+error: author email `infinitys at example.com` is not well-formed
+ --> ipkg.toml:7:9
+  |
+7 | email = "infinitys at example.com"
+  |         ^^^^^^^^^^^^^^^^^^^^^^^^^^ expected an address like `name@example.com`
+
+error: version range `>= 2.0, < 1.0` can never be satisfied
+  --> ipkg.toml:11:43
+   |
+11 |     { name = "libfoo", version = ">= 2.0, < 1.0" },
+   |                                           ^^^^^ conflicts with `>= 2.0`
+
+error: invalid version range `>= 3.0, ~> 4`
+  --> ipkg.toml:12:66
+   |
+12 |     [{ name = "python3" }, { name = "python", version = ">= 3.0, ~> 4" }],
+   |                                                                  ^^ unknown operator `~>`
+
+error: `libfoo` is both a dependency and a conflict
+  --> ipkg.toml:14:22
+   |
+14 | conflict = [{ name = "libfoo" }]
+   |                      ^^^^^^^^ also listed in `relation.depend`
+"#
+        );
+    }
+
+    #[test]
+    fn json_and_yaml_are_located_by_keys() {
+        let data = r#"{
+  "about": {
+    "package": { "name": "hello", "version": "1.0.0" },
+    "author": { "name": "Infinitys", "email": "infinitys@example.com" }
+  },
+  "relation": {
+    "depend": [
+      [{ "name": "a" }, { "name": "b", "version": ">= 2.0, < 1.0" }],
+      { "name": "Bad_Name" }
+    ]
+  }
+}"#;
+        let diagnostics = check_manifest_str(data, ManifestFormat::Json);
+        let spans: Vec<&str> = diagnostics
+            .iter()
+            .map(|d| &data[d.span.clone().unwrap()])
+            .collect();
+        assert_eq!(spans, ["< 1.0", r#""Bad_Name""#]);
+
+        let yaml = "about:\n  package:\n    name: hello\n    version: 1.0.0\n  author:\n    name: Infinitys\n    email: nobody\n  maintainers:\n    - name: A\n      email: a@example.com\n    - name: B\n      email: bad\nrelation:\n  conflict:\n    - name: ok\n    - name: Not_OK\n";
+        let diagnostics = check_manifest_str(yaml, ManifestFormat::Yaml);
+        let spans: Vec<&str> = diagnostics
+            .iter()
+            .map(|d| &yaml[d.span.clone().unwrap()])
+            .collect();
+        assert_eq!(spans, ["nobody", "bad", "Not_OK"]);
+    }
+
+    #[test]
+    fn all_relation_kinds_are_checked() {
+        let source = "[about.package]\nname = \"x\"\nversion = \"1.0.0\"\n\n[relation]\npre-depends = [{ name = \"libc\" }]\nsuggests = [{ name = \"Doc\" }]\nbreaks = [{ name = \"y\", version = \"~> 1\" }]\nconflict = [{ name = \"libc\" }]\n";
+        let diagnostics = check_manifest_str(source, ManifestFormat::Toml);
+        let spans: Vec<&str> = diagnostics
+            .iter()
+            .map(|d| &source[d.span.clone().unwrap()])
+            .collect();
+        assert_eq!(spans, ["\"Doc\"", "~>", "\"libc\""]);
+        assert_eq!(
+            diagnostics[2].label,
+            "also listed in `relation.pre-depends`"
+        );
+    }
+
+    #[test]
+    fn relation_strings_are_checked() {
+        let source = "[about.package]\nname = \"x\"\nversion = \"1.0.0\"\n\n[relation]\ndepend = \"libfoo (>= 1.2) | libbar, baz (<< 3)\"\nrecommends = [\"less |\"]\nsuggests = \"baz (~> 3)\"\nconflict = [{ name = \"libbar\" }]\n";
+        let diagnostics = check_manifest_str(source, ManifestFormat::Toml);
+        let spans: Vec<&str> = diagnostics
+            .iter()
+            .map(|d| &source[d.span.clone().unwrap()])
+            .collect();
+        assert_eq!(spans, ["", "~>", "\"libbar\""]);
+        assert_eq!(diagnostics[0].label, "empty alternative around `|`");
+        assert!(
+            diagnostics[1].label.contains("unknown operator"),
+            "{:?}",
+            diagnostics
+        );
+        assert_eq!(diagnostics[2].label, "also listed in `relation.depend`");
+    }
+
+    #[test]
+    fn unknown_keys_are_warned() {
+        let source = "[about.package]\nname = \"x\"\nversion = \"1.0.0\"\nhompage = \"https://example.com\"\n\n[relation]\ndepend = [{ name = \"libfoo\", verison = \">= 1.0\" }]\n\n[tool.ci]\nimage = \"debian\"\n";
+        let diagnostics = check_manifest_str(source, ManifestFormat::Toml);
+        let spans: Vec<&str> = diagnostics
+            .iter()
+            .map(|d| &source[d.span.clone().unwrap()])
+            .collect();
+        assert_eq!(spans, ["hompage", "verison", "tool"]);
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Warning));
+        assert_eq!(
+            diagnostics[0].message,
+            "unknown key `hompage` in `about.package`"
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "unknown key `verison` in `relation.depend[0]`"
+        );
+        assert_eq!(diagnostics[2].message, "unknown key `tool`");
+
+        let json = r#"{"about": {"package": {"name": "x", "version": "1.0.0", "hompage": ""}}}"#;
+        let diagnostics = check_manifest_str(json, ManifestFormat::Json);
+        let span = diagnostics[0].span.clone().unwrap();
+        assert_eq!(&json[span], r#""hompage""#);
+
+        let yaml = "about:\n  package:\n    name: x\n    version: 1.0.0\n    'hompage' : ''\nrelation:\n  pre-depends:\n    - name: libc\n  depends:\n    - name: libfoo\n";
+        let diagnostics = check_manifest_str(yaml, ManifestFormat::Yaml);
+        let spans: Vec<&str> = diagnostics
+            .iter()
+            .map(|d| &yaml[d.span.clone().unwrap()])
+            .collect();
+        // `pre-depends` の中の `depends` はキーとして扱わない
+        assert_eq!(spans, ["'hompage'", "depends"]);
+        let at = yaml.find("\n  depends:").unwrap() + 3;
+        assert_eq!(diagnostics[1].span, Some(at..at + "depends".len()));
+    }
+
+    #[test]
+    fn license_expressions_are_checked() {
+        let source =
+            "[about.package]\nname = \"x\"\nversion = \"1.0.0\"\nlicense = \"MIT OR Nope-1.0\"\n";
+        let diagnostics = check_manifest_str(source, ManifestFormat::Toml);
+        assert_eq!(diagnostics.len(), 1);
+        let span = diagnostics[0].span.clone().unwrap();
+        assert_eq!(&source[span], "Nope-1.0");
+        assert!(
+            diagnostics[0].label.contains("unknown"),
+            "{:?}",
+            diagnostics
+        );
+        let valid = source.replace("Nope-1.0", "Apache-2.0 WITH LLVM-exception");
+        assert_eq!(check_manifest_str(&valid, ManifestFormat::Toml), []);
+    }
+
+    #[test]
+    fn syntax_and_structure_errors() {
+        let diagnostics = check_manifest_str("[about\n", ManifestFormat::Toml);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span.as_ref().unwrap().start, 6);
+
+        let source = "[about.package]\nversion = \"1.0\"\n";
+        let diagnostics = check_manifest_str(source, ManifestFormat::Toml);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].label.contains("name"), "{:?}", diagnostics);
+
+        let diagnostics = check_manifest_str("{\n  \"about\": }", ManifestFormat::Json);
+        assert_eq!(diagnostics[0].span, Some(13..13));
+    }
+
+    #[test]
+    fn valid_manifest_has_no_diagnostics() {
+        let source = MANIFEST
+            .replace("Hello_World", "hello-world")
+            .replace("rc.01", "rc.1")
+            .replace(" at ", "@")
+            .replace(">= 2.0, < 1.0", ">= 1.0, < 2.0")
+            .replace(", ~> 4", "")
+            .replace("libfoo\" }]", "libbar\" }]");
+        assert_eq!(check_manifest_str(&source, ManifestFormat::Toml), []);
+        let warning = check_manifest_str(&source.replace(">= 3.0", "none"), ManifestFormat::Toml);
+        assert_eq!(warning.len(), 1);
+        assert_eq!(warning[0].severity, Severity::Warning);
+    }
+}
@@ -0,0 +1,93 @@
+//! 利用できるパッケージの一覧 (パッケージインデックス)。
+//!
+//! インデックスはマニフェストを集めたディレクトリです。直下のマニフェストのファイル
+//! (`libfoo-1.2.0.toml` など) と、マニフェストを持つサブディレクトリを読み込みます。
+use super::manifest::{ManifestFormat, find_manifest};
+use super::{DependPackageData, PackageData};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 利用できるパッケージの一覧。
+#[derive(Debug, Default)]
+pub struct PackageIndex {
+    packages: Vec<PackageData>,
+    /// パッケージごとの、読み込んだマニフェストのファイル
+    sources: Vec<Option<PathBuf>>,
+}
+
+impl PackageIndex {
+    /// パッケージの列からインデックスを作ります。パッケージの取得元はありません。
+    pub fn new(packages: Vec<PackageData>) -> Self {
+        let sources = vec![None; packages.len()];
+        PackageIndex { packages, sources }
+    }
+
+    /// 取得元のマニフェストのファイルとともに、パッケージを加えます。
+    pub fn push(&mut self, package: PackageData, source: PathBuf) {
+        self.packages.push(package);
+        self.sources.push(Some(source));
+    }
+
+    /// ディレクトリにあるマニフェストをすべて読み込みます。
+    ///
+    /// # 引数
+    ///
+    /// * `dir` - インデックスのディレクトリ。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(PackageIndex)` - 読み込んだインデックス。ファイル名の順に並びます。
+    /// * `Err(String)` - ディレクトリやマニフェストが読めない場合、エラーメッセージを含む。
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let mut index = PackageIndex::default();
+        index.extend_from_dir(dir)?;
+        Ok(index)
+    }
+
+    /// ディレクトリにあるマニフェストを、このインデックスに加えます。
+    pub fn extend_from_dir(&mut self, dir: &Path) -> Result<(), String> {
+        let entries = fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read index {}: {}", dir.display(), e))?;
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let manifest = if path.is_dir() {
+                find_manifest(&path)
+            } else {
+                ManifestFormat::from_path(&path).ok().map(|_| path)
+            };
+            if let Some(manifest) = manifest {
+                self.push(PackageData::load(&manifest)?, manifest);
+            }
+        }
+        Ok(())
+    }
+
+    /// すべてのパッケージを返します。
+    pub fn packages(&self) -> &[PackageData] {
+        &self.packages
+    }
+
+    /// パッケージを読み込んだマニフェストのファイルを返します。
+    ///
+    /// `package` はこのインデックスが返したものである必要があります。
+    pub fn source(&self, package: &PackageData) -> Option<&Path> {
+        let position = self
+            .packages
+            .iter()
+            .position(|candidate| std::ptr::eq(candidate, package))?;
+        self.sources[position].as_deref()
+    }
+
+    /// 依存関係を満たすパッケージを返します。仮想パッケージを提供するパッケージも含みます。
+    pub fn candidates<'a>(
+        &'a self,
+        depend: &'a DependPackageData,
+    ) -> impl Iterator<Item = &'a PackageData> {
+        self.packages
+            .iter()
+            .filter(|package| package.satisfies(depend))
+    }
+}
@@ -0,0 +1,345 @@
+//! SPDX のライセンス式と、許可リストによる検査。
+//!
+//! ライセンスは `MIT OR Apache-2.0 WITH LLVM-exception` のような SPDX の式で書き、
+//! 識別子は同梱の SPDX ライセンスリストで検証されます。
+use super::PackageData;
+use super::index::PackageIndex;
+use crate::modules::version::Version;
+use colored::Colorize;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use spdx::expression::ExpressionReq;
+use spdx::{Expression, Licensee, ParseError};
+use std::collections::{HashSet, VecDeque};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// SPDX のライセンス式。
+#[derive(Debug, Clone, PartialEq)]
+pub struct License(Expression);
+
+impl License {
+    /// ライセンス式を解釈します。エラーは式の中の問題のある範囲を含みます。
+    pub fn parse(s: &str) -> Result<Self, ParseError> {
+        Expression::parse(s).map(License)
+    }
+
+    /// 式に現れるライセンスの要求 (`Apache-2.0 WITH LLVM-exception` など) を返します。
+    pub fn requirements(&self) -> Vec<String> {
+        self.0
+            .requirements()
+            .map(|requirement| self.term(requirement))
+            .collect()
+    }
+
+    /// 要求を、式に書かれたとおりの文字列で返します。
+    fn term(&self, requirement: &ExpressionReq) -> String {
+        // 位置はライセンスの部分だけを指すので、例外は後から加える
+        let span = requirement.span.start as usize..requirement.span.end as usize;
+        let license = &self.0.as_ref()[span];
+        match requirement.req.exception {
+            Some(exception) => format!("{} WITH {}", license, exception.name),
+            None => license.to_string(),
+        }
+    }
+}
+
+impl FromStr for License {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        License::parse(s).map_err(|e| format!("Invalid license expression `{}`: {}", s, e.reason))
+    }
+}
+
+impl Display for License {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for License {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for License {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// 使ってよいライセンスの許可リスト。
+///
+/// 式は、許可されたライセンスだけで満たせるときに受け入れられます。
+/// 例えば `MIT OR GPL-3.0-only` は `MIT` が許可されていれば受け入れられます。
+/// 例外 (`WITH ...`) 付きの要求は、同じ例外付きで許可されている必要があります。
+#[derive(Debug, Clone, Default)]
+pub struct LicensePolicy {
+    allow: Vec<Licensee>,
+}
+
+impl LicensePolicy {
+    /// 許可するライセンスの列から許可リストを作ります。
+    ///
+    /// # 引数
+    ///
+    /// * `allow` - 許可するライセンス (`MIT`、`Apache-2.0 WITH LLVM-exception` など)。
+    ///   GNU のライセンスは `-only` や `-or-later` を付けずに `GPL-3.0` と書きます。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(LicensePolicy)` - 作った許可リスト。
+    /// * `Err(String)` - 解釈できないライセンスがある場合、エラーメッセージを含む。
+    pub fn new<S: AsRef<str>>(allow: &[S]) -> Result<Self, String> {
+        let allow = allow
+            .iter()
+            .map(|license| {
+                let license = license.as_ref();
+                Licensee::parse(license).map_err(|e| {
+                    format!("Invalid license in allow-list `{}`: {}", license, e.reason)
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(LicensePolicy { allow })
+    }
+
+    /// 許可するライセンスが一つもないかを返します。
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty()
+    }
+
+    /// ライセンスが許可されるかを判定し、満たせなかった要求を返します。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(())` - 許可される場合。
+    /// * `Err(Vec<String>)` - 許可されない場合、許可リストにない要求を含む。
+    pub fn evaluate(&self, license: &License) -> Result<(), Vec<String>> {
+        license
+            .0
+            .evaluate_with_failures(|requirement| {
+                self.allow
+                    .iter()
+                    .any(|licensee| licensee.satisfies(requirement))
+            })
+            .map_err(|failures| {
+                failures
+                    .into_iter()
+                    .map(|failure| license.term(failure))
+                    .collect()
+            })
+    }
+}
+
+/// 許可リストに反する依存パッケージ。
+#[derive(Debug)]
+pub struct LicenseViolation {
+    /// ルートのパッケージからこのパッケージの依存元までの経路
+    pub path: Vec<String>,
+    pub name: String,
+    pub version: Version,
+    /// 宣言されたライセンス。`None` なら宣言がない
+    pub license: Option<License>,
+    /// 許可リストにない要求
+    pub denied: Vec<String>,
+}
+
+impl Display for LicenseViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}: ", self.name.cyan(), self.version)?;
+        match &self.license {
+            Some(license) => write!(
+                f,
+                "license `{}` is not allowed ({} not in allow-list)",
+                license.to_string().yellow(),
+                self.denied.join(", ").red()
+            )?,
+            None => write!(f, "{}", "no license declared".red())?,
+        }
+        write!(f, " (required by {})", self.path.join(" -> "))
+    }
+}
+
+/// 依存パッケージのライセンスを調べた結果。
+#[derive(Debug, Default)]
+pub struct LicenseReport {
+    /// 調べたパッケージの数
+    pub checked: usize,
+    pub violations: Vec<LicenseViolation>,
+    /// インデックスに見つからなかった依存関係 (`名前 (範囲)`)
+    pub missing: Vec<String>,
+}
+
+/// 依存関係をたどり、依存パッケージのライセンスを許可リストで調べます。
+///
+/// インストールに必要な `pre-depends` と `depend` をたどります。代替のグループは
+/// どれが選ばれてもよいように、すべての候補とそのすべてのバージョンを調べます。
+/// ルートのパッケージ自身のライセンスは調べません。
+///
+/// # 引数
+///
+/// * `root` - 調べるプロジェクトのパッケージ。
+/// * `index` - 依存パッケージを探すインデックス。
+/// * `policy` - ライセンスの許可リスト。
+///
+/// # 戻り値
+///
+/// 調べたパッケージの数と、許可リストに反するパッケージ、見つからなかった依存関係。
+pub fn check_licenses(
+    root: &PackageData,
+    index: &PackageIndex,
+    policy: &LicensePolicy,
+) -> LicenseReport {
+    let mut report = LicenseReport::default();
+    let mut visited = HashSet::new();
+    let mut missing = HashSet::new();
+    // 幅優先でたどり、経路はルートから最短のものを示す
+    let mut queue = VecDeque::from([(root, vec![root.about.package.name.clone()])]);
+    while let Some((package, path)) = queue.pop_front() {
+        for depend in package.relation.required_groups().flatten() {
+            let mut found = false;
+            for candidate in index.candidates(depend) {
+                found = true;
+                let about = &candidate.about.package;
+                if !visited.insert((about.name.clone(), about.version.to_string())) {
+                    continue;
+                }
+                report.checked += 1;
+                let denied = match &about.license {
+                    Some(license) => policy.evaluate(license).err(),
+                    None => Some(Vec::new()),
+                };
+                if let Some(denied) = denied {
+                    report.violations.push(LicenseViolation {
+                        path: path.clone(),
+                        name: about.name.clone(),
+                        version: about.version.clone(),
+                        license: about.license.clone(),
+                        denied,
+                    });
+                }
+                let mut next = path.clone();
+                next.push(about.name.clone());
+                queue.push_back((candidate, next));
+            }
+            let described = format!("{} ({})", depend.name, depend.version);
+            if !found && missing.insert(described.clone()) {
+                report.missing.push(described);
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::pkg::DependPackageData;
+    use crate::modules::pkg::manifest::ManifestFormat;
+
+    #[test]
+    fn expressions_are_validated() {
+        let license: License = "MIT OR Apache-2.0 WITH LLVM-exception".parse().unwrap();
+        assert_eq!(license.to_string(), "MIT OR Apache-2.0 WITH LLVM-exception");
+        assert_eq!(
+            license.requirements(),
+            ["MIT", "Apache-2.0 WITH LLVM-exception"]
+        );
+        let error = "MIT OR Nope-1.0".parse::<License>().unwrap_err();
+        assert!(error.contains("Nope-1.0"), "{}", error);
+        let error = License::parse("(MIT OR Apache-2.0").unwrap_err();
+        assert_eq!(error.span.start, 0);
+        assert!(License::parse("MIT/Apache-2.0").is_err());
+    }
+
+    #[test]
+    fn policy_evaluates_expressions() {
+        let policy = LicensePolicy::new(&["MIT", "Apache-2.0 WITH LLVM-exception"]).unwrap();
+        let evaluate = |s: &str| policy.evaluate(&s.parse().unwrap());
+        assert_eq!(evaluate("MIT"), Ok(()));
+        assert_eq!(evaluate("MIT OR GPL-3.0-only"), Ok(()));
+        assert_eq!(
+            evaluate("MIT AND GPL-3.0-only"),
+            Err(vec!["GPL-3.0-only".to_string()])
+        );
+        assert_eq!(evaluate("Apache-2.0 WITH LLVM-exception"), Ok(()));
+        assert_eq!(evaluate("Apache-2.0"), Err(vec!["Apache-2.0".to_string()]));
+        assert!(LicensePolicy::new(&["GPL-3.0-or-later"]).is_err());
+        assert!(LicensePolicy::new::<&str>(&[]).unwrap().is_empty());
+    }
+
+    fn package(text: &str) -> PackageData {
+        PackageData::from_manifest_str(text, ManifestFormat::Toml).unwrap()
+    }
+
+    #[test]
+    fn dependencies_are_walked() {
+        let root = package(
+            r#"
+[about.package]
+name = "app"
+version = "1.0.0"
+license = "GPL-3.0-only"
+
+[relation]
+depend = [[{ name = "libfoo" }, { name = "libbar", version = ">= 2.0" }], { name = "libqux" }]
+"#,
+        );
+        let index = PackageIndex::new(vec![
+            package(
+                r#"
+[about.package]
+name = "libfoo"
+version = "1.0.0"
+license = "MIT"
+
+[relation]
+depend = [{ name = "libbaz" }]
+"#,
+            ),
+            package("[about.package]\nname = \"libbar\"\nversion = \"1.0.0\"\n"),
+            package(
+                "[about.package]\nname = \"libbar\"\nversion = \"2.0.0\"\nlicense = \"GPL-2.0-only\"\n",
+            ),
+            package(
+                "[about.package]\nname = \"libbaz\"\nversion = \"0.1.0\"\nlicense = \"MIT AND BSD-3-Clause\"\n",
+            ),
+        ]);
+        let policy = LicensePolicy::new(&["MIT"]).unwrap();
+        let report = check_licenses(&root, &index, &policy);
+        assert_eq!(report.checked, 3);
+        let found: Vec<(&str, &str, &[String])> = report
+            .violations
+            .iter()
+            .map(|v| (v.name.as_str(), v.version.as_str(), v.denied.as_slice()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("libbar", "2.0.0", &["GPL-2.0-only".to_string()][..]),
+                ("libbaz", "0.1.0", &["BSD-3-Clause".to_string()][..]),
+            ]
+        );
+        assert_eq!(report.violations[1].path, ["app", "libfoo"]);
+        assert_eq!(report.missing, ["libqux (*)"]);
+
+        let mut depend = DependPackageData {
+            name: "libbar".to_string(),
+            version: "< 2.0".parse().unwrap(),
+            prerelease: Default::default(),
+        };
+        let mut root = package("[about.package]\nname = \"app\"\nversion = \"1.0.0\"\n");
+        root.relation.depend.push(vec![depend.clone()]);
+        let report = check_licenses(&root, &index, &policy);
+        colored::control::set_override(false);
+        assert_eq!(
+            report.violations[0].to_string(),
+            "libbar 1.0.0: no license declared (required by app)"
+        );
+        depend.version = "*".parse().unwrap();
+        root.relation.depend = vec![vec![depend]];
+        assert_eq!(check_licenses(&root, &index, &policy).checked, 2);
+    }
+}
@@ -0,0 +1,430 @@
+//! ロックファイル (`ipkg.lock`)。
+//!
+//! 依存関係を解決した結果を、パッケージごとの名前、バージョン、取得元、チェックサムで記録します。
+//! 取得元はパッケージのマニフェストのファイルで、プロジェクトのディレクトリの中にあれば
+//! そこからの相対パスで書きます。チェックサムはそのファイルの SHA-256 です。
+//!
+//! ```toml
+//! # This file is generated by `ipkg lock`. Do not edit it by hand.
+//! version = 1
+//!
+//! [[package]]
+//! name = "libfoo"
+//! version = "1.2.0"
+//! source = "packages/libfoo-1.2.0.toml"
+//! checksum = "sha256:5d41402abc4b2a76b9719d911017c592..."
+//! ```
+use super::PackageData;
+use super::index::PackageIndex;
+use super::resolve::{self, InstallPlan, Strategy};
+use crate::modules::version::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// ロックファイルの名前
+pub const LOCK_FILE: &str = "ipkg.lock";
+
+/// ロックファイルの形式の版
+const LOCK_VERSION: u32 = 1;
+
+/// ロックファイルの先頭に書くコメント
+const HEADER: &str = "# This file is generated by `ipkg lock`. Do not edit it by hand.\n";
+
+/// ロックファイルの内容。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockFile {
+    /// 形式の版
+    pub version: u32,
+    /// ロックしたパッケージ。名前とバージョンの順に並びます
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+/// ロックしたパッケージ (`[[package]]`)。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockedPackage {
+    pub name: String,
+    /// パッケージの方式で書かれたバージョン
+    pub version: String,
+    /// マニフェストのファイル (プロジェクトのディレクトリからの相対パスか絶対パス)
+    pub source: String,
+    /// マニフェストのファイルの `sha256:` 付きのハッシュ
+    pub checksum: String,
+}
+
+/// ファイルの SHA-256 を `sha256:<16進数>` の形で返します。
+pub fn checksum(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let digest = Sha256::digest(&bytes);
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok(format!("sha256:{}", hex))
+}
+
+/// 取得元のパスを、プロジェクトのディレクトリの中ならそこからの相対パスで返します。
+fn source_string(source: &Path, project: &Path) -> String {
+    // `ipkg.toml` のような相対パスの親は空になる
+    let project = match project.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => project,
+    };
+    let source = fs::canonicalize(source).unwrap_or_else(|_| source.to_path_buf());
+    let project = fs::canonicalize(project).unwrap_or_else(|_| project.to_path_buf());
+    let path = source.strip_prefix(&project).unwrap_or(&source);
+    path.to_string_lossy().replace('\\', "/")
+}
+
+impl LockedPackage {
+    /// 取得元のマニフェストのファイルの場所を返します。
+    pub fn source_path(&self, project: &Path) -> PathBuf {
+        project.join(&self.source)
+    }
+}
+
+impl LockFile {
+    /// 依存関係を解決した結果からロックファイルを作ります。
+    ///
+    /// # 引数
+    ///
+    /// * `plan` - 解決した結果。
+    /// * `index` - 解決に使ったインデックス。パッケージの取得元を調べます。
+    /// * `project` - プロジェクトのディレクトリ。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(LockFile)` - 作ったロックファイル。
+    /// * `Err(String)` - 取得元のないパッケージがあるか、取得元のファイルが読めない場合。
+    pub fn from_plan(
+        plan: &InstallPlan,
+        index: &PackageIndex,
+        project: &Path,
+    ) -> Result<Self, String> {
+        let mut packages = plan
+            .packages()
+            .iter()
+            .map(|package| {
+                let about = &package.about.package;
+                let source = index.source(package).ok_or_else(|| {
+                    format!("{} {} has no source to lock", about.name, about.version)
+                })?;
+                Ok(LockedPackage {
+                    name: about.name.clone(),
+                    version: about.version.to_string(),
+                    source: source_string(source, project),
+                    checksum: checksum(source)?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        packages.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+        Ok(LockFile {
+            version: LOCK_VERSION,
+            packages,
+        })
+    }
+
+    /// ロックファイルを読み込みます。
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        text.parse()
+            .map_err(|e| format!("{} ({})", e, path.display()))
+    }
+
+    /// ロックファイルを書き込みます。
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_string())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// ロックしたパッケージを取得元から読み込み、それだけを含むインデックスを作ります。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(PackageIndex)` - ロックしたパッケージのインデックス。
+    /// * `Err(String)` - 取得元が読めないか、チェックサムや名前、バージョンが一致しない場合。
+    pub fn load_packages(&self, project: &Path) -> Result<PackageIndex, String> {
+        let mut index = PackageIndex::default();
+        for locked in &self.packages {
+            let path = locked.source_path(project);
+            if checksum(&path)? != locked.checksum {
+                return Err(format!(
+                    "checksum of {} {} ({}) does not match {}",
+                    locked.name, locked.version, locked.source, LOCK_FILE
+                ));
+            }
+            let package = PackageData::load(&path)?;
+            let about = &package.about.package;
+            if about.name != locked.name || about.version.as_str() != locked.version {
+                return Err(format!(
+                    "{} is {} {}, but {} expects {} {}",
+                    locked.source,
+                    about.name,
+                    about.version,
+                    LOCK_FILE,
+                    locked.name,
+                    locked.version
+                ));
+            }
+            index.push(package, path);
+        }
+        Ok(index)
+    }
+
+    /// ロックしたバージョンを、インデックスのパッケージの方式で解釈して返します。
+    ///
+    /// [`Strategy::PreferLocked`] に渡すのに使います。インデックスにないものは含みません。
+    pub fn versions(&self, index: &PackageIndex) -> HashMap<String, Version> {
+        self.packages
+            .iter()
+            .filter_map(|locked| {
+                index
+                    .packages()
+                    .iter()
+                    .map(|package| &package.about.package)
+                    .find(|about| {
+                        about.name == locked.name && about.version.as_str() == locked.version
+                    })
+                    .map(|about| (about.name.clone(), about.version.clone()))
+            })
+            .collect()
+    }
+}
+
+/// ロックしたパッケージだけで依存関係を解決します (`--frozen`)。
+///
+/// マニフェストの範囲がロックしたバージョンで満たせない場合や、ロックに不要なパッケージが
+/// 残っている場合はエラーになります。
+///
+/// # 引数
+///
+/// * `root` - プロジェクトのパッケージ。
+/// * `locked` - [`LockFile::load_packages`] で読み込んだ、ロックしたパッケージ。
+///
+/// # 戻り値
+///
+/// * `Ok(InstallPlan)` - ロックしたパッケージをインストールする順に並べたもの。
+/// * `Err(String)` - ロックがマニフェストと一致しない場合、その理由を含む。
+pub fn resolve_frozen<'a>(
+    root: &'a PackageData,
+    locked: &'a PackageIndex,
+) -> Result<InstallPlan<'a>, String> {
+    let plan = resolve::resolve_with(root, locked, &Strategy::default()).map_err(|error| {
+        format!(
+            "{} does not satisfy the manifest of {} (run `ipkg lock` to update it)\n\n{}",
+            LOCK_FILE,
+            error.root,
+            error.explanation.join("\n")
+        )
+    })?;
+    if let Some(unused) = locked.packages().iter().find(|package| {
+        !plan
+            .packages()
+            .iter()
+            .any(|planned| std::ptr::eq(*planned, *package))
+    }) {
+        let about = &unused.about.package;
+        return Err(format!(
+            "{} {} is locked but no longer required (run `ipkg lock` to update {})",
+            about.name, about.version, LOCK_FILE
+        ));
+    }
+    Ok(plan)
+}
+
+impl FromStr for LockFile {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lock: LockFile =
+            toml_edit::de::from_str(s).map_err(|e| format!("Invalid lock file: {}", e))?;
+        if lock.version != LOCK_VERSION {
+            return Err(format!(
+                "Unsupported lock file version {} (expected {})",
+                lock.version, LOCK_VERSION
+            ));
+        }
+        Ok(lock)
+    }
+}
+
+impl Display for LockFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let body = toml_edit::ser::to_string_pretty(self).map_err(|_| fmt::Error)?;
+        write!(f, "{}{}", HEADER, body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::pkg::manifest::ManifestFormat;
+
+    fn write(path: &Path, text: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    fn package(text: &str) -> PackageData {
+        PackageData::from_manifest_str(text, ManifestFormat::Toml).unwrap()
+    }
+
+    fn manifest(name: &str, version: &str, depend: &str) -> String {
+        format!(
+            "[about.package]\nname = \"{}\"\nversion = \"{}\"\n\n[relation]\ndepend = \"{}\"\n",
+            name, version, depend
+        )
+    }
+
+    /// `packages` に libfoo 1.0.0、libfoo 1.1.0 (libbar に依存)、libbar 0.1.0 を置いた
+    /// プロジェクトのディレクトリを作ります。
+    fn project(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ipkg-lock-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let packages = dir.join("packages");
+        write(
+            &packages.join("libfoo-1.0.0.toml"),
+            &manifest("libfoo", "1.0.0", ""),
+        );
+        write(
+            &packages.join("libfoo-1.1.0.toml"),
+            &manifest("libfoo", "1.1.0", "libbar"),
+        );
+        write(
+            &packages.join("libbar").join("ipkg.toml"),
+            &manifest("libbar", "0.1.0", ""),
+        );
+        dir
+    }
+
+    /// `root` の依存関係を `strategy` で解決し、ロックファイルを書き込みます。
+    fn lock(dir: &Path, root: &PackageData, strategy: &Strategy) -> LockFile {
+        let index = PackageIndex::load(&dir.join("packages")).unwrap();
+        let plan = resolve::resolve_with(root, &index, strategy).unwrap();
+        let lock = LockFile::from_plan(&plan, &index, dir).unwrap();
+        lock.save(&dir.join(LOCK_FILE)).unwrap();
+        lock
+    }
+
+    #[test]
+    fn lock_is_written_and_read_back() {
+        let dir = project("written");
+        let root = package(&manifest("app", "1.0.0", "libfoo (>= 1.0)"));
+        let lock = lock(&dir, &root, &Strategy::default());
+        let text = fs::read_to_string(dir.join(LOCK_FILE)).unwrap();
+        assert!(text.starts_with(HEADER), "{}", text);
+        assert!(
+            text.contains(
+                "[[package]]\nname = \"libbar\"\nversion = \"0.1.0\"\nsource = \"packages/libbar/ipkg.toml\"\nchecksum = \"sha256:"
+            ),
+            "{}",
+            text
+        );
+        assert_eq!(LockFile::load(&dir.join(LOCK_FILE)).unwrap(), lock);
+        assert!("version = 2\n".parse::<LockFile>().is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn frozen_resolution_uses_the_locked_packages() {
+        let dir = project("frozen");
+        let root = package(&manifest("app", "1.0.0", "libfoo (>= 1.0)"));
+        let lock = lock(&dir, &root, &Strategy::default());
+        let locked = lock.load_packages(&dir).unwrap();
+        let plan = resolve_frozen(&root, &locked).unwrap();
+        let names: Vec<String> = plan
+            .packages()
+            .iter()
+            .map(|package| {
+                let about = &package.about.package;
+                format!("{} {}", about.name, about.version)
+            })
+            .collect();
+        assert_eq!(names, ["libbar 0.1.0", "libfoo 1.1.0"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_lock_is_reported() {
+        let dir = project("missing");
+        let error = LockFile::load(&dir.join(LOCK_FILE)).unwrap_err();
+        assert!(error.starts_with("Failed to read"), "{}", error);
+        assert!(error.contains(LOCK_FILE), "{}", error);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn mismatched_lock_is_rejected() {
+        let dir = project("mismatched");
+        let root = package(&manifest("app", "1.0.0", "libfoo (>= 1.0)"));
+        let mut lock = lock(&dir, &root, &Strategy::default());
+        let locked = lock.load_packages(&dir).unwrap();
+
+        // 範囲がロックしたバージョンを外れた
+        let moved = package(&manifest("app", "1.0.0", "libfoo (>= 2.0)"));
+        let error = resolve_frozen(&moved, &locked).unwrap_err();
+        assert!(error.starts_with("ipkg.lock does not satisfy"), "{}", error);
+
+        // ロックのバージョンが取得元と一致しない
+        let libbar = lock
+            .packages
+            .iter()
+            .position(|p| p.name == "libbar")
+            .unwrap();
+        lock.packages[libbar].version = "0.2.0".to_string();
+        let error = lock.load_packages(&dir).unwrap_err();
+        assert_eq!(
+            error,
+            "packages/libbar/ipkg.toml is libbar 0.1.0, but ipkg.lock expects libbar 0.2.0"
+        );
+
+        // 取得元が変わった
+        write(
+            &dir.join("packages").join("libbar").join("ipkg.toml"),
+            &manifest("libbar", "0.1.0", "libfoo"),
+        );
+        let error = lock.load_packages(&dir).unwrap_err();
+        assert!(error.starts_with("checksum of libbar 0.2.0"), "{}", error);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unused_locked_packages_are_rejected() {
+        let dir = project("unused");
+        let root = package(&manifest("app", "1.0.0", "libfoo (>= 1.0)"));
+        let locked = lock(&dir, &root, &Strategy::default())
+            .load_packages(&dir)
+            .unwrap();
+        // 依存関係が減った
+        let fewer = package(&manifest("app", "1.0.0", "libbar"));
+        let error = resolve_frozen(&fewer, &locked).unwrap_err();
+        assert_eq!(
+            error,
+            "libfoo 1.1.0 is locked but no longer required (run `ipkg lock` to update ipkg.lock)"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn locked_strategy_round_trips() {
+        let dir = project("strategy");
+        let root = package(&manifest("app", "1.0.0", "libfoo (>= 1.0)"));
+        // 古いバージョンでロックしておけば、`--strategy locked` は新しい libfoo を選ばない
+        let lock = lock(&dir, &root, &Strategy::Lowest);
+        let index = PackageIndex::load(&dir.join("packages")).unwrap();
+        let versions = LockFile::load(&dir.join(LOCK_FILE))
+            .unwrap()
+            .versions(&index);
+        assert_eq!(
+            versions,
+            HashMap::from([("libfoo".to_string(), "1.0.0".parse().unwrap())])
+        );
+        let plan = resolve::resolve_with(&root, &index, &Strategy::PreferLocked(versions)).unwrap();
+        assert_eq!(LockFile::from_plan(&plan, &index, &dir).unwrap(), lock);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
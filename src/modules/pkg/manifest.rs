@@ -0,0 +1,1070 @@
+//! プロジェクトのマニフェスト (`ipkg.toml`)。
+//!
+//! 編集しても、手で書かれたコメントやキーの順序、書式はそのまま残ります。
+//!
+//! ```toml
+//! [about.package]
+//! name = "hello"
+//! version = "1.2.3"
+//! scheme = "semver"          # 省略すると semver
+//!
+//! description = "A friendly greeting"
+//! license = "MIT"
+//! keywords = ["hello", "example"]
+//! architecture = ["amd64", "arm64"]   # 省略するとすべて
+//!
+//! [about.author]                 # 複数なら [[about.authors]] を繰り返す
+//! name = "Infinitys"
+//! email = "infinitys@example.com"
+//!
+//! [[about.maintainers]]
+//! name = "Someone"
+//! email = "someone@example.com"
+//!
+//! [relation]
+//! depend = [
+//!     { name = "libfoo", version = ">= 1.2, < 2.0" },
+//!     # 配列は代替のグループ (どれか一つがあればよい)
+//!     [{ name = "python3" }, { name = "python", version = ">= 3.0" }],
+//!     { name = "libbar", version = ">= 2.0.0-rc.1", prerelease = "include" },
+//! ]
+//! conflict = [{ name = "libbaz", version = "< 0.5" }]
+//! pre-depends = [{ name = "libc", version = ">= 2.0" }]
+//! provides = [{ name = "greeter" }]  # 仮想パッケージ
+//! ```
+//!
+//! `relation` には `depend` と同じ形で `pre-depends`、`recommends`、`suggests`、
+//! `build-depends`、`provides`、`replaces`、`breaks` も書けます。
+//! どの関係も Debian の control ファイルと同じ書き方の文字列
+//! (`depend = "libfoo (>= 1.2) | libbar, baz (<< 3)"`) で書くこともでき、
+//! 配列の要素としてグループ一つ分 (`"python3 | python"`) を書くこともできます。
+//!
+//! `[about.package]` には他に `long_description`、`homepage`、`repository`、`categories` を書けます。
+//! 依存関係の `version` は省略すると `*`、`prerelease` は省略すると `same-release` です。
+//!
+//! 同じ内容を JSON (`ipkg.json`) や YAML (`ipkg.yaml`) でも書けます。
+//! 形式は拡張子で見分け、どの形式でもキーと値の意味は同じです。
+//! ただしコメントや書式が保たれるのは TOML だけです。
+use super::relation::{format_relations, parse_relations};
+use super::{DependPackageData, PackageData, RelationData};
+use crate::modules::version::{Scheme, Version};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use toml_edit::{Array, DocumentMut, Item, Table, TableLike, Value};
+
+/// マニフェストのファイル名
+pub const MANIFEST_FILE: &str = "ipkg.toml";
+
+/// マニフェストを探すときの拡張子なしのファイル名
+const MANIFEST_STEM: &str = "ipkg";
+
+/// マニフェストの形式。
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ManifestFormat {
+    #[default]
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ManifestFormat {
+    /// すべての形式 (マニフェストを探す順)
+    pub const ALL: [ManifestFormat; 3] = [
+        ManifestFormat::Toml,
+        ManifestFormat::Json,
+        ManifestFormat::Yaml,
+    ];
+
+    /// ファイルの拡張子から形式を判定します。
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        extension
+            .parse()
+            .map_err(|_| format!("Unknown manifest format: {}", path.display()))
+    }
+
+    /// この形式のファイルの拡張子を返します。
+    pub fn extension(self) -> &'static str {
+        match self {
+            ManifestFormat::Toml => "toml",
+            ManifestFormat::Json => "json",
+            ManifestFormat::Yaml => "yaml",
+        }
+    }
+}
+
+impl FromStr for ManifestFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "toml" => Ok(ManifestFormat::Toml),
+            "json" => Ok(ManifestFormat::Json),
+            "yaml" | "yml" => Ok(ManifestFormat::Yaml),
+            _ => Err(format!(
+                "Unknown manifest format: {} (expected toml, json or yaml)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ManifestFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+/// ディレクトリにあるマニフェストを探します。
+///
+/// `ipkg.toml`、`ipkg.json`、`ipkg.yaml`、`ipkg.yml` の順に調べ、最初に見つかったものを返します。
+pub fn find_manifest(dir: &Path) -> Option<PathBuf> {
+    ManifestFormat::ALL
+        .iter()
+        .map(|format| format.extension())
+        .chain(["yml"])
+        .map(|extension| dir.join(format!("{}.{}", MANIFEST_STEM, extension)))
+        .find(|path| path.is_file())
+}
+
+/// 書式を保ったまま編集できるマニフェスト。
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    document: DocumentMut,
+}
+
+impl FromStr for Manifest {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<DocumentMut>()
+            .map(|document| Manifest { document })
+            .map_err(|e| format!("Invalid manifest: {}", e))
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.document)
+    }
+}
+
+impl Manifest {
+    /// マニフェストをファイルから読み込みます。
+    pub fn load(path: &Path) -> Result<Self, String> {
+        fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+            .parse()
+    }
+
+    /// マニフェストをファイルに書き込みます。
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_string())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// パッケージの情報全体を読み取ります。
+    pub fn package_data(&self) -> Result<PackageData, String> {
+        toml_edit::de::from_document(self.document.clone())
+            .map_err(|e| format!("Invalid manifest: {}", e))
+    }
+
+    /// パッケージの情報全体を書き込みます。
+    ///
+    /// 値が変わらない部分はそのまま残り、変わった値もコメントや前後の空白を保ちます。
+    /// 新しいキーは各テーブルの末尾に加えられ、空になった項目のキーは取り除かれます。
+    /// [`PackageData`] の表さないキーやテーブル、既定値を明示したキーは残ります。
+    pub fn set_package_data(&mut self, data: &PackageData) -> Result<(), String> {
+        let mut new = toml_edit::ser::to_document(data)
+            .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+        layout(new.as_table_mut());
+        if let Some(about) = new.get_mut("about").and_then(Item::as_table_mut) {
+            let old = self
+                .document
+                .get_mut("about")
+                .and_then(Item::as_table_like_mut);
+            layout_people(about, old);
+        }
+        if let Some(relation) = new.get_mut("relation").and_then(Item::as_table_mut) {
+            let old = self.document.get("relation").and_then(Item::as_table_like);
+            layout_relation_strings(relation, old, &data.relation);
+        }
+        let mut merge = Merge::default();
+        let old = self.document.as_table_mut();
+        merge.table(old, new.as_table().clone().into_iter(), false);
+        // 取り除くと読み取る内容が変わるキーだけを消す。表さないキーや既定値はそのまま残る
+        for path in merge.stale {
+            let mut removed = self.document.clone();
+            remove_at(removed.as_table_mut(), &path);
+            if canonical(&removed) != canonical(&self.document) {
+                self.document = removed;
+            }
+        }
+        Ok(())
+    }
+
+    /// `[about.package]` の文字列の値を返します。
+    fn package_str(&self, key: &str) -> Option<&str> {
+        self.document
+            .get("about")
+            .and_then(|about| about.get("package"))
+            .and_then(|package| package.get(key))
+            .and_then(Item::as_str)
+    }
+
+    /// パッケージのバージョンの方式を返します。省略された場合は SemVer です。
+    pub fn scheme(&self) -> Result<Scheme, String> {
+        self.package_str("scheme")
+            .map_or(Ok(Scheme::default()), str::parse)
+    }
+
+    /// パッケージのバージョン (`about.package.version`) を返します。
+    pub fn version(&self) -> Result<Version, String> {
+        let version = self
+            .package_str("version")
+            .ok_or_else(|| "Manifest has no about.package.version".to_string())?;
+        Version::parse_with(version, self.scheme()?).map_err(|e| e.to_string())
+    }
+
+    /// パッケージのバージョンを書き換えます。
+    ///
+    /// 値の前後の空白や行末のコメントは元のまま残ります。
+    pub fn set_version(&mut self, version: &Version) -> Result<(), String> {
+        let package = self
+            .document
+            .get_mut("about")
+            .and_then(|about| about.get_mut("package"))
+            .and_then(Item::as_table_like_mut)
+            .ok_or_else(|| "Manifest has no [about.package] table".to_string())?;
+        let mut value = Value::from(version.as_str());
+        if let Some(old) = package.get("version").and_then(Item::as_value) {
+            *value.decor_mut() = old.decor().clone();
+        }
+        package.insert("version", Item::Value(value));
+        Ok(())
+    }
+}
+
+impl From<&PackageData> for Manifest {
+    fn from(data: &PackageData) -> Self {
+        let mut manifest = Manifest {
+            document: DocumentMut::new(),
+        };
+        manifest
+            .set_package_data(data)
+            .expect("package data is always representable in TOML");
+        manifest
+    }
+}
+
+impl PackageData {
+    /// 指定した形式のマニフェストの文字列を解釈します。
+    ///
+    /// # 引数
+    ///
+    /// * `text` - マニフェストの内容。
+    /// * `format` - マニフェストの形式。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(PackageData)` - 解釈したパッケージの情報。
+    /// * `Err(String)` - 構文や値が不正な場合、エラーメッセージを含む。
+    pub fn from_manifest_str(text: &str, format: ManifestFormat) -> Result<Self, String> {
+        match format {
+            ManifestFormat::Toml => text.parse::<Manifest>()?.package_data(),
+            ManifestFormat::Json => {
+                serde_json::from_str(text).map_err(|e| format!("Invalid manifest: {}", e))
+            }
+            ManifestFormat::Yaml => {
+                serde_yaml::from_str(text).map_err(|e| format!("Invalid manifest: {}", e))
+            }
+        }
+    }
+
+    /// 指定した形式のマニフェストの文字列にします。
+    pub fn to_manifest_string(&self, format: ManifestFormat) -> Result<String, String> {
+        let error = |e: &dyn fmt::Display| format!("Failed to serialize manifest: {}", e);
+        match format {
+            ManifestFormat::Toml => Ok(Manifest::from(self).to_string()),
+            ManifestFormat::Json => serde_json::to_string_pretty(self)
+                .map(|json| json + "\n")
+                .map_err(|e| error(&e)),
+            ManifestFormat::Yaml => serde_yaml::to_string(self).map_err(|e| error(&e)),
+        }
+    }
+
+    /// マニフェストのファイルからパッケージの情報を読み込みます。形式は拡張子で判定します。
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let format = ManifestFormat::from_path(path)?;
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        PackageData::from_manifest_str(&text, format)
+    }
+
+    /// パッケージの情報をマニフェストのファイルに書き込みます。形式は拡張子で判定します。
+    ///
+    /// TOML のファイルがすでにあれば、そのコメントやキーの順序を保って書き換えます。
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = match ManifestFormat::from_path(path)? {
+            ManifestFormat::Toml if path.exists() => {
+                let mut manifest = Manifest::load(path)?;
+                manifest.set_package_data(self)?;
+                manifest.to_string()
+            }
+            format => self.to_manifest_string(format)?,
+        };
+        fs::write(path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+/// 書き出した値を、手で書くときと同じ形に整えます。
+///
+/// テーブルの中のテーブルは `[a.b]` の見出しに、要素が複数ある配列は一行に一要素にします。
+fn layout(table: &mut Table) {
+    for (_, item) in table.iter_mut() {
+        if let Some(inline) = item.as_inline_table() {
+            let mut nested = inline.clone().into_table();
+            layout(&mut nested);
+            nested.set_implicit(true);
+            *item = Item::Table(nested);
+        } else if let Some(array) = item.as_array_mut()
+            && array.len() > 1
+        {
+            for element in array.iter_mut() {
+                element.decor_mut().set_prefix("\n    ");
+            }
+            array.set_trailing("\n");
+            array.set_trailing_comma(true);
+        }
+    }
+}
+
+/// 作者と保守担当者を、手で書くときと同じ形に整えます。
+///
+/// 一人だけなら `[about.author]` のテーブルに、複数なら `[[about.authors]]` にします。
+/// 既存のマニフェストが複数形のキーを使っていれば、一人でもそちらに合わせます。
+/// 形が変わる場合は既存のキーをその場で付け替え、元の位置とコメントを保ちます。
+fn layout_people(about: &mut Table, mut old: Option<&mut dyn TableLike>) {
+    for (single, plural) in [("author", "authors"), ("maintainer", "maintainers")] {
+        let Some(item) = about.remove(plural) else {
+            continue;
+        };
+        let mut tables: Vec<Table> = item
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_inline_table)
+            .map(|table| table.clone().into_table())
+            .collect();
+        let keep_plural = old.as_ref().is_some_and(|old| old.contains_key(plural));
+        let (key, item) = if tables.len() == 1 && !keep_plural {
+            (single, Item::Table(tables.remove(0)))
+        } else {
+            (plural, Item::ArrayOfTables(tables.into_iter().collect()))
+        };
+        if let Some(old) = old.as_deref_mut() {
+            let other = if key == single { plural } else { single };
+            rename_key(old, other, key);
+        }
+        about.insert(key, item);
+    }
+}
+
+/// 既存のマニフェストで `"libfoo (>= 1.2) | libbar"` の形の文字列で書かれた関係は、同じ形で書きます。
+///
+/// 関係全体が文字列の場合と、配列の要素のグループが文字列の場合があります。
+/// 意味が変わらなければ元の文字列をそのまま残します。文字列の形ではプレリリースの方針を
+/// 表せないので、既定でない方針を含む場合はテーブルの形になります。
+fn layout_relation_strings(relation: &mut Table, old: Option<&dyn TableLike>, data: &RelationData) {
+    for (key, groups) in data.groups() {
+        let Some(old) = old.and_then(|old| old.get(key)) else {
+            continue;
+        };
+        if let Some(old) = old.as_str() {
+            if let Some(text) = relation_string(old, groups) {
+                relation.insert(key, Item::Value(Value::from(text)));
+            }
+            continue;
+        }
+        let (Some(old), Some(new)) = (
+            old.as_array(),
+            relation.get_mut(key).and_then(Item::as_array_mut),
+        ) else {
+            continue;
+        };
+        for (i, group) in groups.iter().enumerate() {
+            let (Some(old), Some(element)) = (old.get(i).and_then(Value::as_str), new.get_mut(i))
+            else {
+                continue;
+            };
+            if let Some(text) = relation_string(old, std::slice::from_ref(group)) {
+                let decor = element.decor().clone();
+                *element = Value::from(text);
+                *element.decor_mut() = decor;
+            }
+        }
+    }
+}
+
+/// グループの列を文字列の形で返します。`old` と同じ意味なら `old` をそのまま返します。
+///
+/// 空の場合や、文字列の形で表せない場合は `None` を返します。
+fn relation_string(old: &str, groups: &[Vec<DependPackageData>]) -> Option<String> {
+    if groups.is_empty() {
+        return None;
+    }
+    let text = format_relations(groups)?;
+    let unchanged = parse_relations(old)
+        .ok()
+        .and_then(|parsed| format_relations(&parsed))
+        .is_some_and(|parsed| parsed == text);
+    Some(if unchanged { old.to_string() } else { text })
+}
+
+/// テーブルのキーを、順序を保ったまま付け替えます。
+///
+/// 一つのテーブルと一要素の配列は、新しいキーの単数形・複数形に合わせて互いに変換します。
+fn rename_key(table: &mut dyn TableLike, from: &str, to: &str) {
+    if !table.contains_key(from) {
+        return;
+    }
+    let keys: Vec<String> = table.iter().map(|(key, _)| key.to_string()).collect();
+    for key in keys {
+        let Some(item) = table.remove(&key) else {
+            continue;
+        };
+        if key != from {
+            table.insert(&key, item);
+            continue;
+        }
+        let item = match item {
+            Item::Table(single) => Item::ArrayOfTables([single].into_iter().collect()),
+            Item::Value(Value::InlineTable(single)) => {
+                Item::Value(Value::Array([single].into_iter().collect()))
+            }
+            Item::ArrayOfTables(tables) if tables.len() == 1 => {
+                tables.into_iter().next().map_or(Item::None, Item::Table)
+            }
+            Item::Value(Value::Array(array)) if array.len() == 1 => {
+                array.into_iter().next().map_or(Item::None, Item::Value)
+            }
+            item => item,
+        };
+        table.insert(to, item);
+    }
+}
+
+/// 文書の中の位置を表す、キーか配列の添字の一歩。
+#[derive(Debug, Clone)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+/// 既存の文書を新しい内容で書き換える処理。
+///
+/// 新しい内容にないキーはその場では消さず、位置を `stale` に集めておきます。
+/// [`PackageData`] の表さないキーや既定値を明示したキーは新しい内容に現れないためです。
+#[derive(Default)]
+struct Merge {
+    path: Vec<Step>,
+    stale: Vec<Vec<Step>>,
+}
+
+impl Merge {
+    /// `new` の内容で `old` を書き換えます。
+    ///
+    /// `inline` は `old` がインラインテーブルかどうかで、加える値の表現を合わせるのに使います。
+    fn table(
+        &mut self,
+        old: &mut dyn TableLike,
+        new: impl Iterator<Item = (impl AsRef<str>, Item)>,
+        inline: bool,
+    ) {
+        let mut keys = Vec::new();
+        for (key, item) in new {
+            let key = key.as_ref().to_string();
+            match old.get_mut(&key) {
+                Some(existing) => {
+                    self.path.push(Step::Key(key.clone()));
+                    self.item(existing, item);
+                    self.path.pop();
+                }
+                None if inline => {
+                    if let Ok(value) = item.into_value() {
+                        old.insert(&key, Item::Value(value));
+                    }
+                }
+                None => {
+                    old.insert(&key, item);
+                }
+            }
+            keys.push(key);
+        }
+        for (key, _) in old.iter() {
+            if !keys.iter().any(|k| k == key) {
+                let mut path = self.path.clone();
+                path.push(Step::Key(key.to_string()));
+                self.stale.push(path);
+            }
+        }
+    }
+
+    /// `new` の内容で `old` を書き換えます。表現 (テーブルかインラインか) は `old` に合わせます。
+    fn item(&mut self, old: &mut Item, new: Item) {
+        let new = match (&*old, new) {
+            (Item::Value(_), new @ (Item::Table(_) | Item::ArrayOfTables(_))) => {
+                new.into_value().map_or(Item::None, Item::Value)
+            }
+            (Item::Table(_), Item::Value(Value::InlineTable(table))) => {
+                Item::Table(table.into_table())
+            }
+            (Item::ArrayOfTables(_), Item::Value(Value::Array(array)))
+                if array.iter().all(Value::is_inline_table) =>
+            {
+                let mut tables = toml_edit::ArrayOfTables::new();
+                for value in array {
+                    if let Value::InlineTable(table) = value {
+                        tables.push(table.into_table());
+                    }
+                }
+                Item::ArrayOfTables(tables)
+            }
+            (_, new) => new,
+        };
+        match (old, new) {
+            (Item::Table(old), Item::Table(new)) => self.table(old, new.into_iter(), false),
+            (Item::Value(old), Item::Value(new)) => self.value(old, new),
+            (Item::ArrayOfTables(old), Item::ArrayOfTables(new)) => {
+                let len = new.len();
+                for (i, table) in new.into_iter().enumerate() {
+                    match old.get_mut(i) {
+                        Some(existing) => {
+                            self.path.push(Step::Index(i));
+                            self.table(existing, table.into_iter(), false);
+                            self.path.pop();
+                        }
+                        None => old.push(table),
+                    }
+                }
+                while old.len() > len {
+                    old.remove(old.len() - 1);
+                }
+            }
+            (old, new) => *old = new,
+        }
+    }
+
+    /// `new` の内容で `old` を書き換えます。値が同じなら元の書き方のまま残します。
+    fn value(&mut self, old: &mut Value, new: Value) {
+        match (old, new) {
+            (Value::InlineTable(old), Value::InlineTable(new)) => {
+                let new = new
+                    .into_iter()
+                    .map(|(key, value)| (key, Item::Value(value)));
+                self.table(old, new, true);
+            }
+            (Value::Array(old), Value::Array(new)) => self.array(old, new),
+            (old, new) => {
+                let bare = |value: &Value| value.clone().decorated("", "").to_string();
+                if bare(old) != bare(&new) {
+                    let decor = old.decor().clone();
+                    *old = new;
+                    *old.decor_mut() = decor;
+                }
+            }
+        }
+    }
+
+    /// 配列を要素ごとに書き換えます。加える要素は直前の要素と同じ字下げにします。
+    fn array(&mut self, old: &mut Array, new: Array) {
+        let len = new.len();
+        for (i, value) in new.into_iter().enumerate() {
+            match old.get_mut(i) {
+                Some(existing) => {
+                    self.path.push(Step::Index(i));
+                    self.value(existing, value);
+                    self.path.pop();
+                }
+                None => {
+                    let prefix = old
+                        .iter()
+                        .last()
+                        .and_then(|last| last.decor().prefix())
+                        .and_then(|prefix| prefix.as_str())
+                        .map(indentation);
+                    match prefix {
+                        Some(prefix) => old.push_formatted(value.decorated(prefix, "")),
+                        None => old.push(value),
+                    }
+                }
+            }
+        }
+        while old.len() > len {
+            old.remove(old.len() - 1);
+        }
+    }
+}
+
+/// 文書から読み取ったパッケージの情報を、正規化した TOML にして返します。
+fn canonical(document: &DocumentMut) -> Option<String> {
+    let data: PackageData = toml_edit::de::from_document(document.clone()).ok()?;
+    toml_edit::ser::to_document(&data)
+        .ok()
+        .map(|document| document.to_string())
+}
+
+/// 文書の `path` の位置のキーを取り除きます。
+fn remove_at(table: &mut dyn TableLike, path: &[Step]) {
+    match path {
+        [Step::Key(key)] => {
+            table.remove(key);
+        }
+        [Step::Key(key), rest @ ..] => match table.get_mut(key) {
+            Some(Item::Table(table)) => remove_at(table, rest),
+            Some(Item::ArrayOfTables(tables)) => {
+                if let [Step::Index(i), rest @ ..] = rest
+                    && let Some(table) = tables.get_mut(*i)
+                {
+                    remove_at(table, rest);
+                }
+            }
+            Some(Item::Value(value)) => remove_in_value(value, rest),
+            _ => {}
+        },
+        _ => {}
+    }
+}
+
+/// インラインテーブルや配列の値の中で、`path` の位置のキーを取り除きます。
+fn remove_in_value(value: &mut Value, path: &[Step]) {
+    match (value, path) {
+        (Value::Array(array), [Step::Index(i), rest @ ..]) => {
+            if let Some(value) = array.get_mut(*i) {
+                remove_in_value(value, rest);
+            }
+        }
+        (Value::InlineTable(table), [Step::Key(key)]) => {
+            let last = table.iter().last().is_some_and(|(last, _)| last == key);
+            let Some(removed) = table.remove(key) else {
+                return;
+            };
+            // `}` の前の空白は最後の値の後置きにあるので、新しい最後の値に移す
+            if last && let Some((_, value)) = table.iter_mut().last() {
+                let suffix = removed.decor().suffix().cloned().unwrap_or_default();
+                value.decor_mut().set_suffix(suffix);
+            }
+        }
+        (Value::InlineTable(table), path) => remove_at(table, path),
+        _ => {}
+    }
+}
+
+/// 要素の前置きから、コメントを除いた改行と字下げを取り出します。
+fn indentation(prefix: &str) -> String {
+    match prefix.rsplit_once('\n') {
+        Some((_, indent)) => format!("\n{}", indent),
+        None => prefix.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::pkg::AuthorAboutData;
+    use crate::modules::version::{PrereleasePolicy, VersionRange};
+
+    const MANIFEST: &str = r#"# パッケージの情報
+[about.package]
+name = "hello"
+version = "1.2.3" # リリースごとに更新する
+scheme = "semver"
+
+[about.author]
+name = "Infinitys"
+"#;
+
+    #[test]
+    fn set_version_keeps_formatting() {
+        let mut manifest: Manifest = MANIFEST.parse().unwrap();
+        let next = manifest.version().unwrap().bump_minor().unwrap();
+        manifest.set_version(&next).unwrap();
+        assert_eq!(
+            manifest.to_string(),
+            MANIFEST.replace(r#""1.2.3""#, r#""1.3.0""#)
+        );
+    }
+
+    #[test]
+    fn version_uses_manifest_scheme() {
+        let manifest: Manifest = "[about.package]\nversion = \"1.0~rc1\"\nscheme = \"dpkg\"\n"
+            .parse()
+            .unwrap();
+        assert_eq!(manifest.version().unwrap().scheme(), Scheme::Dpkg);
+        let manifest: Manifest = "[about.package]\nname = \"x\"\n".parse().unwrap();
+        assert!(manifest.version().is_err());
+        assert!(Manifest::from_str("[about").is_err());
+    }
+
+    const FULL: &str = r#"# パッケージの情報
+[about.package]
+name = "hello"
+version = "1.2.3" # リリースごとに更新する
+
+[about.author]
+name = "Infinitys"
+email = "infinitys@example.com"
+
+[relation]
+depend = [
+    { name = "libfoo", version = ">= 1.2, < 2.0" },
+    # どちらか一方があればよい
+    [{ name = "python3" }, { name = "python", version = ">= 3.0" }],
+]
+conflict = [{ name = "libbaz", version = "< 0.5", prerelease = "include" }]
+"#;
+
+    #[test]
+    fn package_data_is_read() {
+        let data = FULL.parse::<Manifest>().unwrap().package_data().unwrap();
+        assert_eq!(data.about.package.name, "hello");
+        assert_eq!(data.about.package.version.as_str(), "1.2.3");
+        assert_eq!(data.about.package.scheme, Scheme::SemVer);
+        assert_eq!(data.about.authors[0].email, "infinitys@example.com");
+        let depend = &data.relation.depend;
+        assert_eq!(depend.len(), 2);
+        assert_eq!(depend[0][0].version.to_string(), ">= 1.2, < 2.0");
+        let alternatives: Vec<&str> = depend[1].iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(alternatives, ["python3", "python"]);
+        assert_eq!(depend[1][0].version, VersionRange::default());
+        assert_eq!(
+            data.relation.conflict[0].prerelease,
+            PrereleasePolicy::Include
+        );
+    }
+
+    #[test]
+    fn unchanged_data_is_written_back_verbatim() {
+        let mut manifest: Manifest = FULL.parse().unwrap();
+        let data = manifest.package_data().unwrap();
+        manifest.set_package_data(&data).unwrap();
+        assert_eq!(manifest.to_string(), FULL);
+    }
+
+    #[test]
+    fn unmodeled_keys_and_defaults_are_kept() {
+        let text = r#"[about.package]
+name = "hello"
+version = "1.2.3"
+scheme = "semver" # explicit
+hompage = "https://example.com"
+
+[relation]
+depend = [{ name = "a", version = "*" }, { name = "b", version = ">= 1.0" }]
+
+[tool.ci]
+matrix = ["stable", "nightly"]
+"#;
+        let mut manifest: Manifest = text.parse().unwrap();
+        let data = manifest.package_data().unwrap();
+        manifest.set_package_data(&data).unwrap();
+        assert_eq!(manifest.to_string(), text);
+
+        // 空になった項目だけが消え、インラインテーブルの書式は保たれる
+        let mut data = manifest.package_data().unwrap();
+        data.relation.depend[1][0].version = VersionRange::default();
+        manifest.set_package_data(&data).unwrap();
+        assert_eq!(
+            manifest.to_string(),
+            text.replace(r#"{ name = "b", version = ">= 1.0" }"#, r#"{ name = "b" }"#)
+        );
+    }
+
+    #[test]
+    fn edits_keep_comments_and_key_order() {
+        let mut manifest: Manifest = FULL.parse().unwrap();
+        let mut data = manifest.package_data().unwrap();
+        data.about.package.version = "1.3.0".parse().unwrap();
+        data.relation.depend.push(vec![DependPackageData {
+            name: "libqux".to_string(),
+            version: "^0.4".parse().unwrap(),
+            prerelease: PrereleasePolicy::default(),
+        }]);
+        data.relation.conflict.clear();
+        manifest.set_package_data(&data).unwrap();
+        let expected = FULL
+            .replace(r#""1.2.3""#, r#""1.3.0""#)
+            .replace(
+                "    [{ name = \"python3\" }, { name = \"python\", version = \">= 3.0\" }],\n",
+                "    [{ name = \"python3\" }, { name = \"python\", version = \">= 3.0\" }],\n    { name = \"libqux\", version = \">= 0.4, < 0.5.0\" },\n",
+            )
+            .replace(
+                "conflict = [{ name = \"libbaz\", version = \"< 0.5\", prerelease = \"include\" }]\n",
+                "",
+            );
+        assert_eq!(manifest.to_string(), expected);
+    }
+
+    #[test]
+    fn array_of_tables_stays_in_place() {
+        let text = r#"[about.package]
+name = "hello"
+version = "1.0.0"
+
+[about.author]
+name = "Infinitys"
+email = "infinitys@example.com"
+
+# 古い実装とは共存できない
+[[relation.conflict]]
+name = "hello-legacy"
+
+[[relation.conflict]]
+name = "hello-ng"
+version = "< 2.0"
+"#;
+        let mut manifest: Manifest = text.parse().unwrap();
+        let mut data = manifest.package_data().unwrap();
+        data.relation.conflict[1].version = "< 3.0".parse().unwrap();
+        manifest.set_package_data(&data).unwrap();
+        assert_eq!(manifest.to_string(), text.replace("< 2.0", "< 3.0"));
+    }
+
+    #[test]
+    fn formats_share_semantics() {
+        let data = FULL.parse::<Manifest>().unwrap().package_data().unwrap();
+        for format in ManifestFormat::ALL {
+            let text = data.to_manifest_string(format).unwrap();
+            let read = PackageData::from_manifest_str(&text, format).unwrap();
+            assert_eq!(
+                read.to_manifest_string(ManifestFormat::Toml).unwrap(),
+                data.to_manifest_string(ManifestFormat::Toml).unwrap(),
+                "{}",
+                format
+            );
+        }
+        let json = data.to_manifest_string(ManifestFormat::Json).unwrap();
+        assert!(json.contains(r#""version": ">= 1.2, < 2.0""#), "{}", json);
+        let yaml = r#"about:
+  package:
+    name: hello
+    version: 1.0.0-rc.1
+  author:
+    name: Infinitys
+    email: infinitys@example.com
+relation:
+  depend:
+    - name: libfoo
+      version: ">= 1.2"
+    - - name: python3
+      - name: python
+"#;
+        let read = PackageData::from_manifest_str(yaml, ManifestFormat::Yaml).unwrap();
+        assert_eq!(read.about.package.version.as_str(), "1.0.0-rc.1");
+        assert_eq!(read.relation.depend[1].len(), 2);
+        let error =
+            PackageData::from_manifest_str(&yaml.replace(">= 1.2", "~> 1.2"), ManifestFormat::Yaml)
+                .unwrap_err();
+        assert!(error.contains("~>"), "{}", error);
+    }
+
+    #[test]
+    fn format_follows_extension() {
+        let format = |path: &str| ManifestFormat::from_path(Path::new(path));
+        assert_eq!(format("ipkg.toml"), Ok(ManifestFormat::Toml));
+        assert_eq!(format("dir/ipkg.JSON"), Ok(ManifestFormat::Json));
+        assert_eq!(format("ipkg.yml"), Ok(ManifestFormat::Yaml));
+        assert!(format("ipkg").is_err());
+        assert!(format("ipkg.ini").is_err());
+    }
+
+    #[test]
+    fn load_and_save_by_extension() {
+        let dir = std::env::temp_dir().join(format!("ipkg-manifest-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert_eq!(find_manifest(&dir), None);
+        let data = FULL.parse::<Manifest>().unwrap().package_data().unwrap();
+        let yaml = dir.join("ipkg.yml");
+        data.save(&yaml).unwrap();
+        assert_eq!(find_manifest(&dir), Some(yaml.clone()));
+        let read = PackageData::load(&yaml).unwrap();
+        assert_eq!(read.relation.depend.len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn new_manifest_layout() {
+        let mut data = PackageData::default();
+        data.relation.depend.push(vec![DependPackageData {
+            name: "libfoo".to_string(),
+            version: ">= 1.0".parse().unwrap(),
+            prerelease: PrereleasePolicy::default(),
+        }]);
+        let manifest = Manifest::from(&data);
+        assert_eq!(
+            manifest.to_string(),
+            r#"[about.package]
+name = "default-package"
+version = "1.0.0"
+
+[about.author]
+name = "default"
+email = "default@default.com"
+
+[relation]
+depend = [{ name = "libfoo", version = ">= 1.0" }]
+"#
+        );
+        let read = manifest.package_data().unwrap();
+        assert_eq!(read.about.package.name, data.about.package.name);
+        assert_eq!(read.relation.depend[0][0].version.to_string(), ">= 1.0");
+    }
+
+    #[test]
+    fn metadata_and_people() {
+        let mut manifest: Manifest = FULL.parse().unwrap();
+        let mut data = manifest.package_data().unwrap();
+        assert_eq!(data.about.maintainers.len(), 0);
+        data.about.package.description = Some("A friendly greeting".to_string());
+        data.about.package.license = Some("MIT".parse().unwrap());
+        data.about.package.keywords = vec!["hello".to_string(), "example".to_string()];
+        data.about.package.architecture = vec!["amd64".to_string()];
+        data.about.maintainers.push(AuthorAboutData {
+            name: "Someone".to_string(),
+            email: "someone@example.com".to_string(),
+        });
+        manifest.set_package_data(&data).unwrap();
+        let text = manifest.to_string();
+        assert!(
+            text.contains(
+                "version = \"1.2.3\" # リリースごとに更新する\ndescription = \"A friendly greeting\"\nlicense = \"MIT\"\nkeywords = [\n    \"hello\",\n    \"example\",\n]\narchitecture = [\"amd64\"]\n"
+            ),
+            "{}",
+            text
+        );
+        // 一人だけなら単数形のテーブルになる
+        assert!(
+            text.contains("\n[about.maintainer]\nname = \"Someone\"\n"),
+            "{}",
+            text
+        );
+
+        // 二人目を加えると配列のテーブルになる
+        data.about.authors.push(data.about.maintainers[0].clone());
+        manifest.set_package_data(&data).unwrap();
+        let text = manifest.to_string();
+        assert!(!text.contains("[about.author]"), "{}", text);
+        assert!(
+            text.find("[[about.authors]]") < text.find("[about.maintainer]"),
+            "{}",
+            text
+        );
+        assert_eq!(text.matches("[[about.authors]]").count(), 2, "{}", text);
+        for format in ManifestFormat::ALL {
+            let read =
+                PackageData::from_manifest_str(&data.to_manifest_string(format).unwrap(), format)
+                    .unwrap();
+            assert_eq!(read.about.authors.len(), 2, "{}", format);
+            assert_eq!(read.about.maintainers.len(), 1, "{}", format);
+            assert_eq!(read.about.package.keywords, ["hello", "example"]);
+            assert_eq!(read.about.package.license, data.about.package.license);
+            assert_eq!(read.about.package.homepage, None);
+        }
+    }
+
+    #[test]
+    fn relation_kinds_round_trip() {
+        let text = r#"[about.package]
+name = "hello"
+version = "1.0.0"
+
+[relation]
+pre-depends = [{ name = "libc", version = ">= 2.0" }]
+build-depends = [[{ name = "gcc" }, { name = "clang" }]]
+provides = [{ name = "greeter" }]
+breaks = [{ name = "hello-plugin", version = "< 2.0" }]
+"#;
+        let mut manifest: Manifest = text.parse().unwrap();
+        let mut data = manifest.package_data().unwrap();
+        assert_eq!(data.relation.pre_depends[0][0].name, "libc");
+        assert_eq!(data.relation.build_depends[0].len(), 2);
+        assert_eq!(data.relation.provides[0][0].name, "greeter");
+        manifest.set_package_data(&data).unwrap();
+        assert_eq!(manifest.to_string(), text);
+
+        data.relation.suggests.push(vec![DependPackageData {
+            name: "hello-doc".to_string(),
+            version: VersionRange::default(),
+            prerelease: PrereleasePolicy::default(),
+        }]);
+        for format in ManifestFormat::ALL {
+            let read =
+                PackageData::from_manifest_str(&data.to_manifest_string(format).unwrap(), format)
+                    .unwrap();
+            assert_eq!(read.relation.suggests[0][0].name, "hello-doc", "{}", format);
+            assert_eq!(read.relation.breaks[0][0].version.to_string(), "< 2.0");
+        }
+    }
+
+    #[test]
+    fn relation_strings_are_kept() {
+        let text = r#"[about.package]
+name = "hello"
+version = "1.0.0"
+
+[relation]
+depend = "libfoo(>= 1.2) | libbar,  baz (<< 3)" # Debian と同じ書き方
+recommends = ["less | more", { name = "hello-doc" }]
+"#;
+        let mut manifest: Manifest = text.parse().unwrap();
+        let mut data = manifest.package_data().unwrap();
+        assert_eq!(data.relation.depend.len(), 2);
+        assert_eq!(data.relation.depend[0][1].name, "libbar");
+        assert_eq!(data.relation.recommends[0].len(), 2);
+        manifest.set_package_data(&data).unwrap();
+        assert_eq!(manifest.to_string(), text);
+
+        data.relation.depend[1][0].version = "<< 4".parse().unwrap();
+        manifest.set_package_data(&data).unwrap();
+        assert_eq!(
+            manifest.to_string(),
+            text.replace(
+                r#""libfoo(>= 1.2) | libbar,  baz (<< 3)""#,
+                r#""libfoo (>= 1.2) | libbar, baz (<< 4)""#
+            )
+        );
+
+        // 文字列で表せない方針はテーブルの形で書く
+        data.relation.depend[1][0].prerelease = PrereleasePolicy::Include;
+        manifest.set_package_data(&data).unwrap();
+        assert!(
+            manifest
+                .to_string()
+                .contains(r#"{ name = "baz", version = "<< 4", prerelease = "include" }"#),
+            "{}",
+            manifest
+        );
+
+        let error = Manifest::from_str(&text.replace("<< 3)", "<< 3) [amd64]"))
+            .unwrap()
+            .package_data()
+            .unwrap_err();
+        assert!(error.contains("unexpected character"), "{}", error);
+        let error = Manifest::from_str(&text.replace("less | more", "less, more"))
+            .unwrap()
+            .package_data()
+            .unwrap_err();
+        assert!(error.contains("single group"), "{}", error);
+    }
+
+    #[test]
+    fn invalid_fields_are_reported() {
+        let manifest: Manifest = FULL.replace(">= 3.0", ">= 3.0, ~> 4").parse().unwrap();
+        let error = manifest.package_data().unwrap_err();
+        assert!(error.contains("~>"), "{}", error);
+    }
+}
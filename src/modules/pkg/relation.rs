@@ -0,0 +1,453 @@
+//! Debian の control ファイルと同じ書き方の関係 (`libfoo (>= 1.2) | libbar, baz (<< 3)`)。
+//!
+//! `,` で区切った一つ一つがグループで、グループの中の `|` は代替です。
+//! 括弧の中の演算子は Debian と同じ意味で、古い `<` と `>` は `<=` と `>=` と同じです
+//! (厳密な比較は `<<` と `>>`)。`(>= 1.2, << 2.0)` のように複数の条件を一つの括弧に
+//! 書くこともできますが、`||` の和集合は書けません。
+//! プレリリースの方針もこの書き方では表せず、常に既定の方針になります。
+use super::DependPackageData;
+use crate::modules::version::{
+    PrereleasePolicy, VersionErrorKind, VersionRange,
+    ecosystem::{debian_conditions, debian_operator},
+};
+use std::{fmt, ops::Range, str::FromStr};
+
+/// 関係の解釈に失敗した理由の種類。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelationErrorKind {
+    /// `,` の間にパッケージがない
+    EmptyGroup,
+    /// `|` の前後にパッケージがない
+    EmptyAlternative,
+    /// パッケージ名に使えない文字がある、または短すぎる
+    InvalidName(String),
+    /// バージョン条件の `(` が閉じられていない
+    UnclosedParenthesis,
+    /// その位置に置けない文字
+    UnexpectedCharacter(char),
+    /// 括弧の中のバージョン条件が不正
+    Version(VersionErrorKind),
+}
+
+impl fmt::Display for RelationErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RelationErrorKind::EmptyGroup => write!(f, "empty relation between commas"),
+            RelationErrorKind::EmptyAlternative => write!(f, "empty alternative around `|`"),
+            RelationErrorKind::InvalidName(name) => write!(f, "invalid package name `{}`", name),
+            RelationErrorKind::UnclosedParenthesis => {
+                write!(f, "version constraint is missing `)`")
+            }
+            RelationErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character {:?}", c),
+            RelationErrorKind::Version(kind) => write!(f, "invalid version constraint: {}", kind),
+        }
+    }
+}
+
+/// 関係の解釈エラー。
+///
+/// `span` は `input` の中で問題のある部分のバイト範囲です。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationError {
+    pub kind: RelationErrorKind,
+    pub input: String,
+    pub span: Range<usize>,
+}
+
+impl RelationError {
+    fn new(kind: RelationErrorKind, input: &str, span: Range<usize>) -> Self {
+        RelationError {
+            kind,
+            input: input.to_string(),
+            span,
+        }
+    }
+
+    /// 問題のある部分の文字列を返します。
+    pub fn fragment(&self) -> &str {
+        &self.input[self.span.clone()]
+    }
+}
+
+impl fmt::Display for RelationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}..{} in `{}`",
+            self.kind, self.span.start, self.span.end, self.input
+        )
+    }
+}
+
+impl std::error::Error for RelationError {}
+
+/// 括弧の外にある区切り文字で分け、前後の空白を除いた部分とその開始位置を返します。
+fn split_outside_parens(s: &str, sep: char) -> Vec<(usize, &str)> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if c == sep && depth == 0 => {
+                parts.push((start, &s[start..i]));
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push((start, &s[start..]));
+    parts
+        .into_iter()
+        .map(|(start, part)| {
+            let offset = part.len() - part.trim_start().len();
+            (start + offset, part.trim())
+        })
+        .collect()
+}
+
+/// Debian のパッケージ名の規則 (小文字・数字・`+`・`-`・`.` で、英数字で始まる2文字以上) に従うかを判定します。
+fn is_package_name(name: &str) -> bool {
+    name.len() >= 2
+        && name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "+-.".contains(c))
+}
+
+/// 括弧の中の条件 (`>= 1.2, << 2.0`) を範囲に変換します。`start` は `input` の中での位置です。
+fn parse_conditions(input: &str, start: usize, text: &str) -> Result<VersionRange, RelationError> {
+    let version_error = |kind, span: Range<usize>| {
+        RelationError::new(RelationErrorKind::Version(kind), input, span)
+    };
+    // Debian の書き方には和集合がない
+    if let Some(i) = text.find('|') {
+        return Err(RelationError::new(
+            RelationErrorKind::UnexpectedCharacter('|'),
+            input,
+            start + i..start + i + 1,
+        ));
+    }
+    let mut conditions = Vec::new();
+    for (offset, condition) in split_outside_parens(text, ',') {
+        let at = start + offset;
+        let op_len = condition
+            .find(|c: char| !"<>=!^~".contains(c))
+            .unwrap_or(condition.len());
+        let (op, version) = condition.split_at(op_len);
+        let Some(ours) = debian_operator(op) else {
+            return Err(version_error(
+                VersionErrorKind::UnknownOperator(op.to_string()),
+                at..at + op_len,
+            ));
+        };
+        let ours = format!("{} {}", ours, version.trim());
+        if let Err(e) = ours.parse::<VersionRange>() {
+            return Err(version_error(e.kind, at..at + condition.len()));
+        }
+        conditions.push(ours);
+    }
+    conditions
+        .join(", ")
+        .parse::<VersionRange>()
+        .map_err(|e| version_error(e.kind, start..start + text.len()))
+}
+
+/// 代替一つ分 (`libfoo (>= 1.2)`) を解釈します。`start` は `input` の中での位置です。
+fn parse_alternative(
+    input: &str,
+    start: usize,
+    text: &str,
+) -> Result<DependPackageData, RelationError> {
+    let error = |kind, span: Range<usize>| {
+        RelationError::new(kind, input, start + span.start..start + span.end)
+    };
+    let name_len = text
+        .find(|c: char| c.is_whitespace() || c == '(')
+        .unwrap_or(text.len());
+    let name = &text[..name_len];
+    if !is_package_name(name) {
+        return Err(error(
+            RelationErrorKind::InvalidName(name.to_string()),
+            0..name_len,
+        ));
+    }
+    let rest = text[name_len..].trim_start();
+    let rest_start = text.len() - rest.len();
+    let version = match rest.chars().next() {
+        None => VersionRange::default(),
+        Some('(') => {
+            let Some(close) = rest.find(')') else {
+                return Err(error(
+                    RelationErrorKind::UnclosedParenthesis,
+                    rest_start..text.len(),
+                ));
+            };
+            let after = &rest[close + 1..];
+            if let Some((i, c)) = after.char_indices().find(|(_, c)| !c.is_whitespace()) {
+                let i = rest_start + close + 1 + i;
+                return Err(error(
+                    RelationErrorKind::UnexpectedCharacter(c),
+                    i..i + c.len_utf8(),
+                ));
+            }
+            parse_conditions(input, start + rest_start + 1, &rest[1..close])?
+        }
+        Some(c) => {
+            return Err(error(
+                RelationErrorKind::UnexpectedCharacter(c),
+                rest_start..rest_start + c.len_utf8(),
+            ));
+        }
+    };
+    Ok(DependPackageData {
+        name: name.to_string(),
+        version,
+        prerelease: PrereleasePolicy::default(),
+    })
+}
+
+/// 代替のグループ一つ分 (`libfoo (>= 1.2) | libbar`) を解釈します。
+fn parse_group(
+    input: &str,
+    start: usize,
+    text: &str,
+) -> Result<Vec<DependPackageData>, RelationError> {
+    if text.is_empty() {
+        return Err(RelationError::new(
+            RelationErrorKind::EmptyGroup,
+            input,
+            start..start,
+        ));
+    }
+    split_outside_parens(text, '|')
+        .into_iter()
+        .map(|(offset, alternative)| {
+            if alternative.is_empty() {
+                let at = start + offset;
+                return Err(RelationError::new(
+                    RelationErrorKind::EmptyAlternative,
+                    input,
+                    at..at,
+                ));
+            }
+            parse_alternative(input, start + offset, alternative)
+        })
+        .collect()
+}
+
+/// Debian の書き方の関係を、代替のグループの列に変換します。
+///
+/// # 引数
+///
+/// * `s` - 関係 (例: `libfoo (>= 1.2) | libbar, baz (<< 3)`)。空白だけなら関係なしです。
+///
+/// # 戻り値
+///
+/// * `Ok(Vec<Vec<DependPackageData>>)` - グループの列。
+/// * `Err(RelationError)` - 構文が不正な場合、問題のある部分の位置を含む。
+pub fn parse_relations(s: &str) -> Result<Vec<Vec<DependPackageData>>, RelationError> {
+    if s.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    split_outside_parens(s, ',')
+        .into_iter()
+        .map(|(start, group)| parse_group(s, start, group))
+        .collect()
+}
+
+/// 代替一つ分を Debian の書き方 (`libfoo (>= 1.2, << 2.0)`) にします。
+///
+/// 読み戻したときに同じ関係にならない場合は `None` を返します。
+fn format_alternative(depend: &DependPackageData) -> Option<String> {
+    if depend.prerelease != PrereleasePolicy::default() {
+        return None;
+    }
+    let conditions = debian_conditions(&depend.version)?;
+    let text = if conditions.is_empty() {
+        depend.name.clone()
+    } else {
+        format!("{} ({})", depend.name, conditions.join(", "))
+    };
+    // 方式の違う範囲 (`pep440: >= 1.0` など) は同じ条件で書いても意味が変わる
+    let same = text
+        .parse::<DependPackageData>()
+        .is_ok_and(|parsed| parsed.version == depend.version);
+    same.then_some(text)
+}
+
+/// グループの列を Debian の書き方にします。[`parse_relations`] で読み戻せます。
+///
+/// # 戻り値
+///
+/// * `Some(String)` - 関係 (例: `libfoo (>= 1.2) | libbar, baz (<< 3)`)。
+/// * `None` - 和集合の範囲やプレリリースの方針のように、この書き方で表せない関係がある場合。
+pub fn format_relations(groups: &[Vec<DependPackageData>]) -> Option<String> {
+    let groups = groups
+        .iter()
+        .map(|group| {
+            let alternatives = group
+                .iter()
+                .map(format_alternative)
+                .collect::<Option<Vec<_>>>()?;
+            Some(alternatives.join(" | "))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(groups.join(", "))
+}
+
+impl FromStr for DependPackageData {
+    type Err = RelationError;
+    /// 代替のない関係一つ (`libfoo (>= 1.2)`) を解釈します。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let start = s.len() - s.trim_start().len();
+        let text = s.trim();
+        if text.is_empty() {
+            return Err(RelationError::new(RelationErrorKind::EmptyGroup, s, 0..0));
+        }
+        // 括弧の外の区切り文字だけを探す
+        let mut depth = 0usize;
+        let separator = text.char_indices().find(|&(_, c)| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            depth == 0 && (c == ',' || c == '|')
+        });
+        if let Some((i, c)) = separator {
+            let i = start + i;
+            return Err(RelationError::new(
+                RelationErrorKind::UnexpectedCharacter(c),
+                s,
+                i..i + 1,
+            ));
+        }
+        parse_alternative(s, start, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(groups: &[Vec<DependPackageData>]) -> Vec<Vec<&str>> {
+        groups
+            .iter()
+            .map(|group| group.iter().map(|d| d.name.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn debian_relations_are_parsed() {
+        let groups = parse_relations("libfoo (>= 1.2) | libbar, baz (<< 3)").unwrap();
+        assert_eq!(names(&groups), [vec!["libfoo", "libbar"], vec!["baz"]]);
+        assert_eq!(groups[0][0].version.to_string(), ">= 1.2");
+        assert_eq!(groups[0][1].version, VersionRange::default());
+        assert!(groups[1][0].version.compare(&"2.9".parse().unwrap()));
+        assert!(!groups[1][0].version.compare(&"3".parse().unwrap()));
+
+        let groups =
+            parse_relations("  libc6(>= 2.34),python3.12 |python3 ( >= 3.0, < 4.0 )  ").unwrap();
+        assert_eq!(
+            names(&groups),
+            [vec!["libc6"], vec!["python3.12", "python3"]]
+        );
+        // 古い `<` は `<=` の意味
+        assert_eq!(groups[1][1].version.to_string(), ">= 3.0, <= 4.0");
+        assert!(groups[1][1].version.compare(&"4.0".parse().unwrap()));
+        assert_eq!(parse_relations(" ").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn relations_round_trip() {
+        let input = "libfoo (>= 1.2) | libbar, baz (<< 3), qux (>= 3.0, << 4.0), gcc (= 12)";
+        let groups = parse_relations(input).unwrap();
+        let printed = format_relations(&groups).unwrap();
+        assert_eq!(printed, input);
+        assert_eq!(
+            format_relations(&parse_relations(&printed).unwrap()).as_deref(),
+            Some(input)
+        );
+        let single: DependPackageData = "gcc (> 12)".parse().unwrap();
+        assert_eq!(
+            format_relations(&[vec![single]]).as_deref(),
+            Some("gcc (>= 12)")
+        );
+    }
+
+    #[test]
+    fn inexpressible_relations_are_refused() {
+        let depend = |version: &str| {
+            vec![vec![DependPackageData {
+                name: "libfoo".to_string(),
+                version: version.parse().unwrap(),
+                prerelease: PrereleasePolicy::default(),
+            }]]
+        };
+        assert_eq!(format_relations(&depend("< 1.0 || >= 3.0")), None);
+        assert_eq!(format_relations(&depend("none")), None);
+        assert_eq!(format_relations(&depend("pep440: >= 1.0rc1")), None);
+        assert_eq!(
+            format_relations(&depend("== 1.0")).as_deref(),
+            Some("libfoo (= 1.0)")
+        );
+        let mut groups = depend(">= 1.0");
+        groups[0][0].prerelease = PrereleasePolicy::Include;
+        assert_eq!(format_relations(&groups), None);
+    }
+
+    #[test]
+    fn malformed_relations_are_located() {
+        let error = |s: &'static str| parse_relations(s).unwrap_err();
+
+        let e = error("libfoo, , libbar");
+        assert_eq!(e.kind, RelationErrorKind::EmptyGroup);
+        assert_eq!(e.span, 8..8);
+        let e = error("libfoo |, libbar");
+        assert_eq!(e.kind, RelationErrorKind::EmptyAlternative);
+        assert_eq!(e.span, 8..8);
+        let e = error("libfoo, Lib_Bar (>= 1)");
+        assert_eq!(
+            e.kind,
+            RelationErrorKind::InvalidName("Lib_Bar".to_string())
+        );
+        assert_eq!(e.fragment(), "Lib_Bar");
+        let e = error("libfoo (>= 1.2 | libbar");
+        assert_eq!(e.kind, RelationErrorKind::UnclosedParenthesis);
+        assert_eq!(e.fragment(), "(>= 1.2 | libbar");
+        let e = error("libfoo (>= 1.2) [amd64]");
+        assert_eq!(e.kind, RelationErrorKind::UnexpectedCharacter('['));
+        assert_eq!(e.span, 16..17);
+        let e = error("libfoo >= 1.2");
+        assert_eq!(e.kind, RelationErrorKind::UnexpectedCharacter('>'));
+        let e = error("libfoo, libbar (~> 1.2)");
+        assert_eq!(
+            e.kind,
+            RelationErrorKind::Version(VersionErrorKind::UnknownOperator("~>".to_string()))
+        );
+        assert_eq!(e.fragment(), "~>");
+        let e = error("libfoo (< 1.0 || >= 3.0)");
+        assert_eq!(e.kind, RelationErrorKind::UnexpectedCharacter('|'));
+        assert_eq!(e.span, 14..15);
+        let e = error("libfoo (>= 1.0, == 2.0)");
+        assert_eq!(
+            e.kind,
+            RelationErrorKind::Version(VersionErrorKind::UnknownOperator("==".to_string()))
+        );
+        assert_eq!(e.fragment(), "==");
+        let e = error("libbar (>= 1.x.2)");
+        assert!(matches!(e.kind, RelationErrorKind::Version(_)), "{}", e);
+        assert!(e.to_string().contains("in `libbar (>= 1.x.2)`"), "{}", e);
+
+        assert!("libfoo | libbar".parse::<DependPackageData>().is_err());
+        assert!(
+            "libfoo (>= 1), libbar"
+                .parse::<DependPackageData>()
+                .is_err()
+        );
+        assert!("libfoo (>= 1, << 2)".parse::<DependPackageData>().is_ok());
+        assert!("".parse::<DependPackageData>().is_err());
+    }
+}
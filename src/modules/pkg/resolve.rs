@@ -0,0 +1,754 @@
+//! 依存関係の解決。
+//!
+//! ルートのパッケージから `pre-depends` と `depend` をたどり、インデックスの中から
+//! 一緒にインストールできるパッケージの組を選びます。問題は充足可能性問題 (SAT) として
+//! 表し、矛盾から節を学習する探索 (CDCL) で解くので、解があれば必ず見つかります。
+//!
+//! 変数はパッケージ (名前とバージョンの組) ごとに一つで、次の節を作ります。
+//!
+//! * ルートのパッケージはインストールする。
+//! * パッケージをインストールするなら、依存関係のグループごとに候補のどれかをインストールする。
+//! * 同じ名前のパッケージは一つのバージョンしかインストールしない。
+//! * `conflict` や `breaks` で拒まれるパッケージは一緒にインストールしない。
+//!
+//! 候補は代替の順に、同じ代替の中では [`Strategy`] の順 (既定では新しいバージョンから) で試します。
+//!
+//! `recommends` は節にせず、必要な依存関係を選び終えた後で、インストールするパッケージの
+//! 推奨をまだ満たしていなければその候補を試します。矛盾すれば学習した節によって外れるので、
+//! 推奨のために解がなくなることはありません。`suggests`、`build-depends`、`replaces` は
+//! マニフェストに記録するだけの情報で、解決には使いません。
+use super::index::PackageIndex;
+use super::{DependPackageData, PackageAboutData, PackageData};
+use crate::modules::version::Version;
+use colored::Colorize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+mod explain;
+
+/// 変数 (パッケージ) をインストールするか、しないかを表すリテラル。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Literal {
+    var: usize,
+    positive: bool,
+}
+
+impl Literal {
+    fn install(var: usize) -> Self {
+        Literal {
+            var,
+            positive: true,
+        }
+    }
+
+    fn omit(var: usize) -> Self {
+        Literal {
+            var,
+            positive: false,
+        }
+    }
+}
+
+/// 節が成り立つ理由。
+#[derive(Debug, Clone, PartialEq)]
+enum Cause {
+    /// ルートのパッケージはインストールする
+    Root,
+    /// `package` の依存関係のグループ (`required_groups` での位置)
+    Depends { package: usize, group: usize },
+    /// `package` が `conflict` か `breaks` で `other` を拒む
+    Conflict { package: usize, other: usize },
+    /// 同じ名前のパッケージは一つしかインストールできない
+    SameName,
+    /// ほかの節から導いた節。導出に使った節を、使った順に並べる
+    Derived(Vec<usize>),
+}
+
+/// リテラルのいずれかが成り立つことを表す節。
+#[derive(Debug)]
+struct Clause {
+    literals: Vec<Literal>,
+    cause: Cause,
+}
+
+/// 依存関係を解決した結果。
+#[derive(Debug)]
+pub struct InstallPlan<'a> {
+    packages: Vec<&'a PackageData>,
+}
+
+impl<'a> InstallPlan<'a> {
+    /// インストールするパッケージを、インストールする順に返します。
+    ///
+    /// 依存されるパッケージは依存するパッケージより先に並びます (循環している場合を除く)。
+    /// ルートのパッケージは含みません。
+    pub fn packages(&self) -> &[&'a PackageData] {
+        &self.packages
+    }
+}
+
+impl Display for InstallPlan<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for package in &self.packages {
+            let about = &package.about.package;
+            writeln!(f, "{} {}", about.name, about.version)?;
+        }
+        Ok(())
+    }
+}
+
+/// 依存関係を満たすパッケージの組がないことを表すエラー。
+#[derive(Debug)]
+pub struct ResolveError {
+    /// ルートのパッケージ (`名前 バージョン`)
+    pub root: String,
+    /// 解がないことの説明。一行ずつ、前の行までの結論を使って導きます
+    pub explanation: Vec<String>,
+}
+
+impl Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "cannot resolve the dependencies of {}", self.root.cyan())?;
+        for line in &self.explanation {
+            write!(f, "\n{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+/// 同じ依存関係を満たすバージョンのうち、どれを先に試すか。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Strategy {
+    /// 新しいバージョンから試します。
+    #[default]
+    Highest,
+    /// 古いバージョンから試します。範囲の下限のバージョンで動くかを確かめるのに使います。
+    Lowest,
+    /// インストール済みのバージョン (名前からバージョンへの対応) を先に試し、
+    /// ほかは新しいバージョンから試します。
+    PreferInstalled(HashMap<String, Version>),
+    /// ロックされたバージョン (名前からバージョンへの対応) を先に試し、
+    /// ほかは新しいバージョンから試します。
+    PreferLocked(HashMap<String, Version>),
+}
+
+impl Strategy {
+    /// 同じ依存関係の候補の、試す順序を比べます。
+    ///
+    /// 名前が一致するパッケージを仮想パッケージの提供より先にし、同じ種類の中では方針に従います。
+    fn compare(&self, a: &PackageData, b: &PackageData, depend: &DependPackageData) -> Ordering {
+        let (a, b) = (&a.about.package, &b.about.package);
+        let provides = |about: &PackageAboutData| about.name != depend.name;
+        let newest = || b.version.cmp(&a.version);
+        provides(a).cmp(&provides(b)).then_with(|| match self {
+            Strategy::Highest => newest(),
+            Strategy::Lowest => a.version.cmp(&b.version),
+            Strategy::PreferInstalled(versions) | Strategy::PreferLocked(versions) => {
+                let preferred =
+                    |about: &PackageAboutData| versions.get(&about.name) == Some(&about.version);
+                preferred(b).cmp(&preferred(a)).then_with(newest)
+            }
+        })
+    }
+}
+
+/// 依存関係を解決し、インストールするパッケージを選びます。新しいバージョンを優先します。
+///
+/// # 引数
+///
+/// * `root` - インストールするパッケージ (プロジェクト)。
+/// * `index` - 依存パッケージを探すインデックス。ルートと同じ名前のパッケージは使いません。
+///
+/// # 戻り値
+///
+/// * `Ok(InstallPlan)` - 選んだパッケージをインストールする順に並べたもの。
+/// * `Err(ResolveError)` - 依存関係を満たす組がない場合、その理由を含む。
+pub fn resolve<'a>(
+    root: &'a PackageData,
+    index: &'a PackageIndex,
+) -> Result<InstallPlan<'a>, ResolveError> {
+    resolve_with(root, index, &Strategy::default())
+}
+
+/// 指定した方針で依存関係を解決します。
+///
+/// 方針は候補を試す順序だけを決めるので、解があれば方針によらず見つかります。
+///
+/// # 引数
+///
+/// * `root` - インストールするパッケージ (プロジェクト)。
+/// * `index` - 依存パッケージを探すインデックス。
+/// * `strategy` - どのバージョンを先に試すか。
+///
+/// # 戻り値
+///
+/// * `Ok(InstallPlan)` - 選んだパッケージをインストールする順に並べたもの。
+/// * `Err(ResolveError)` - 依存関係を満たす組がない場合、その理由を含む。
+pub fn resolve_with<'a>(
+    root: &'a PackageData,
+    index: &'a PackageIndex,
+    strategy: &Strategy,
+) -> Result<InstallPlan<'a>, ResolveError> {
+    let mut solver = Solver::new(root, index, strategy);
+    match solver.solve() {
+        Ok(()) => Ok(InstallPlan {
+            packages: solver.plan(),
+        }),
+        Err(refutation) => Err(solver.error(refutation)),
+    }
+}
+
+/// 依存関係のグループを満たす候補を、試す順に変数として返します。
+///
+/// 新しく見つかったパッケージは `packages` に加え、`vars` にインデックスでの位置を記録します。
+fn candidates<'a>(
+    group: &[DependPackageData],
+    root: &'a PackageData,
+    index: &'a PackageIndex,
+    strategy: &Strategy,
+    packages: &mut Vec<&'a PackageData>,
+    vars: &mut HashMap<usize, usize>,
+) -> Vec<usize> {
+    let mut candidates = Vec::new();
+    for depend in group {
+        let mut found = Vec::new();
+        if root.satisfies(depend) {
+            found.push(0);
+        }
+        for (position, candidate) in index.packages().iter().enumerate() {
+            if candidate.about.package.name == root.about.package.name
+                || !candidate.satisfies(depend)
+            {
+                continue;
+            }
+            found.push(*vars.entry(position).or_insert_with(|| {
+                packages.push(candidate);
+                packages.len() - 1
+            }));
+        }
+        found.sort_by(|&a, &b| strategy.compare(packages[a], packages[b], depend));
+        for var in found {
+            if !candidates.contains(&var) {
+                candidates.push(var);
+            }
+        }
+    }
+    candidates
+}
+
+struct Solver<'a> {
+    index: &'a PackageIndex,
+    /// 変数ごとのパッケージ。0 番はルート
+    packages: Vec<&'a PackageData>,
+    /// パッケージごとの、依存関係のグループの候補 (試す順)
+    groups: Vec<Vec<Vec<usize>>>,
+    /// パッケージごとの、推奨 (`recommends`) のグループの候補 (試す順)。節にはしない
+    recommends: Vec<Vec<Vec<usize>>>,
+    clauses: Vec<Clause>,
+    values: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    /// 割り当てた変数の順
+    trail: Vec<usize>,
+    /// 決定レベルごとの、`trail` での開始位置
+    trail_lim: Vec<usize>,
+}
+
+impl<'a> Solver<'a> {
+    /// ルートから到達できるパッケージを集め、節を作ります。
+    fn new(root: &'a PackageData, index: &'a PackageIndex, strategy: &Strategy) -> Self {
+        let mut packages = vec![root];
+        // インデックスでの位置から変数への対応
+        let mut vars: HashMap<usize, usize> = HashMap::new();
+        let mut groups = Vec::new();
+        let mut recommends = Vec::new();
+        let mut next = 0;
+        while next < packages.len() {
+            let relation = &packages[next].relation;
+            let mut candidates = |group: &'a [DependPackageData]| {
+                candidates(group, root, index, strategy, &mut packages, &mut vars)
+            };
+            let package_groups: Vec<_> =
+                relation.required_groups().map(|g| candidates(g)).collect();
+            let package_recommends: Vec<_> =
+                relation.recommends.iter().map(|g| candidates(g)).collect();
+            groups.push(package_groups);
+            recommends.push(package_recommends);
+            next += 1;
+        }
+
+        let count = packages.len();
+        let mut clauses = vec![Clause {
+            literals: vec![Literal::install(0)],
+            cause: Cause::Root,
+        }];
+        for (package, package_groups) in groups.iter().enumerate() {
+            for (group, candidates) in package_groups.iter().enumerate() {
+                let mut literals = vec![Literal::omit(package)];
+                literals.extend(candidates.iter().map(|&var| Literal::install(var)));
+                clauses.push(Clause {
+                    literals,
+                    cause: Cause::Depends { package, group },
+                });
+            }
+        }
+        for a in 0..count {
+            for b in a + 1..count {
+                let cause = if packages[a].about.package.name == packages[b].about.package.name {
+                    Cause::SameName
+                } else if packages[a].is_incompatible_with(packages[b]) {
+                    Cause::Conflict {
+                        package: a,
+                        other: b,
+                    }
+                } else if packages[b].is_incompatible_with(packages[a]) {
+                    Cause::Conflict {
+                        package: b,
+                        other: a,
+                    }
+                } else {
+                    continue;
+                };
+                clauses.push(Clause {
+                    literals: vec![Literal::omit(a), Literal::omit(b)],
+                    cause,
+                });
+            }
+        }
+
+        Solver {
+            index,
+            packages,
+            groups,
+            recommends,
+            clauses,
+            values: vec![None; count],
+            levels: vec![0; count],
+            reasons: vec![None; count],
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+        }
+    }
+
+    fn value(&self, literal: Literal) -> Option<bool> {
+        self.values[literal.var].map(|value| value == literal.positive)
+    }
+
+    fn assign(&mut self, literal: Literal, reason: Option<usize>) {
+        self.values[literal.var] = Some(literal.positive);
+        self.levels[literal.var] = self.trail_lim.len();
+        self.reasons[literal.var] = reason;
+        self.trail.push(literal.var);
+    }
+
+    /// 解を探します。解がなければ、空の節を導いた節の位置を返します。
+    fn solve(&mut self) -> Result<(), usize> {
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.trail_lim.is_empty() {
+                    return Err(self.refute(conflict));
+                }
+                let (learned, level) = self.analyze(conflict);
+                self.backtrack(level);
+                // 戻った先では学習した節が単位節になり、次の伝播で割り当てられる
+                self.clauses.push(learned);
+                continue;
+            }
+            match self.decide() {
+                Some(literal) => {
+                    self.trail_lim.push(self.trail.len());
+                    self.assign(literal, None);
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// 単位節を割り当て続けます。偽になった節があれば、その位置を返します。
+    fn propagate(&mut self) -> Option<usize> {
+        loop {
+            let mut changed = false;
+            for index in 0..self.clauses.len() {
+                let mut unassigned = Vec::new();
+                let mut satisfied = false;
+                for &literal in &self.clauses[index].literals {
+                    match self.value(literal) {
+                        Some(true) => {
+                            satisfied = true;
+                            break;
+                        }
+                        Some(false) => {}
+                        None => unassigned.push(literal),
+                    }
+                }
+                if satisfied {
+                    continue;
+                }
+                match unassigned.as_slice() {
+                    [] => return Some(index),
+                    [literal] => {
+                        self.assign(*literal, Some(index));
+                        changed = true;
+                    }
+                    _ => {}
+                }
+            }
+            if !changed {
+                return None;
+            }
+        }
+    }
+
+    /// 次にインストールを試すパッケージを選びます。
+    ///
+    /// 依存元がすべてインストールされる、まだ満たされていない節から、最初の候補を選びます。
+    /// そのような節がなければ、まだ満たされていない推奨の候補を選びます。
+    /// どちらもなければ、残りのパッケージはインストールしないことで解になります。
+    fn decide(&self) -> Option<Literal> {
+        self.required().or_else(|| self.recommended())
+    }
+
+    /// 依存元がインストールされる、まだ満たされていない節の最初の候補を返します。
+    fn required(&self) -> Option<Literal> {
+        self.clauses.iter().find_map(|clause| {
+            let active = clause
+                .literals
+                .iter()
+                .all(|&literal| literal.positive || self.value(literal) == Some(false));
+            let satisfied = clause
+                .literals
+                .iter()
+                .any(|&literal| self.value(literal) == Some(true));
+            if !active || satisfied {
+                return None;
+            }
+            clause
+                .literals
+                .iter()
+                .find(|&&literal| literal.positive && self.value(literal).is_none())
+                .copied()
+        })
+    }
+
+    /// インストールするパッケージの、まだ満たされていない推奨の最初の候補を返します。
+    ///
+    /// インストールしないと決まった候補は飛ばします。推奨は節ではないので、
+    /// 候補がすべて外れた推奨は満たさないままにします。
+    fn recommended(&self) -> Option<Literal> {
+        self.recommends
+            .iter()
+            .enumerate()
+            .filter(|&(var, _)| self.values[var] == Some(true))
+            .flat_map(|(_, groups)| groups)
+            .filter(|candidates| {
+                !candidates
+                    .iter()
+                    .any(|&candidate| self.values[candidate] == Some(true))
+            })
+            .find_map(|candidates| {
+                candidates
+                    .iter()
+                    .find(|&&candidate| self.values[candidate].is_none())
+            })
+            .map(|&var| Literal::install(var))
+    }
+
+    /// 矛盾した節から、最初の唯一含意点 (1-UIP) までさかのぼって節を学習します。
+    ///
+    /// # 戻り値
+    ///
+    /// 学習した節と、その節が単位節になる決定レベル。
+    fn analyze(&self, conflict: usize) -> (Clause, usize) {
+        let level = self.trail_lim.len();
+        let mut seen = vec![false; self.packages.len()];
+        let mut literals = Vec::new();
+        let mut used = vec![conflict];
+        let mut pending = 0;
+        let mut clause = conflict;
+        let mut position = self.trail.len();
+        loop {
+            for &literal in &self.clauses[clause].literals {
+                if seen[literal.var] {
+                    continue;
+                }
+                seen[literal.var] = true;
+                if self.levels[literal.var] == level {
+                    pending += 1;
+                } else {
+                    literals.push(literal);
+                }
+            }
+            // 現在のレベルで最後に割り当てた、節に現れる変数
+            let var = loop {
+                position -= 1;
+                if seen[self.trail[position]] {
+                    break self.trail[position];
+                }
+            };
+            pending -= 1;
+            if pending == 0 {
+                let positive = self.values[var] != Some(true);
+                literals.insert(0, Literal { var, positive });
+                break;
+            }
+            clause = self.reasons[var].expect("propagated variables have a reason");
+            used.push(clause);
+        }
+        let back = literals[1..]
+            .iter()
+            .map(|literal| self.levels[literal.var])
+            .max()
+            .unwrap_or(0);
+        let learned = Clause {
+            literals,
+            cause: Cause::Derived(used),
+        };
+        (learned, back)
+    }
+
+    /// 決定レベル `level` まで割り当てを戻します。
+    fn backtrack(&mut self, level: usize) {
+        let start = self.trail_lim[level];
+        for var in self.trail.drain(start..) {
+            self.values[var] = None;
+            self.reasons[var] = None;
+        }
+        self.trail_lim.truncate(level);
+    }
+
+    /// 決定なしで起きた矛盾から空の節を導き、その位置を返します。
+    fn refute(&mut self, conflict: usize) -> usize {
+        let mut seen = vec![false; self.packages.len()];
+        let mut used = vec![conflict];
+        for literal in &self.clauses[conflict].literals {
+            seen[literal.var] = true;
+        }
+        for &var in self.trail.iter().rev() {
+            if !seen[var] {
+                continue;
+            }
+            let reason = self.reasons[var].expect("variables without decisions have a reason");
+            used.push(reason);
+            for literal in &self.clauses[reason].literals {
+                seen[literal.var] = true;
+            }
+        }
+        self.clauses.push(Clause {
+            literals: Vec::new(),
+            cause: Cause::Derived(used),
+        });
+        self.clauses.len() - 1
+    }
+
+    /// インストールするパッケージを、依存される順に並べます。
+    ///
+    /// ルートから、各グループと推奨で選ばれた最初の候補をたどります。
+    /// たどれないパッケージは不要です。
+    fn plan(&self) -> Vec<&'a PackageData> {
+        let mut visited = vec![false; self.packages.len()];
+        let mut order = Vec::new();
+        self.visit(0, &mut visited, &mut order);
+        order
+            .into_iter()
+            .filter(|&var| var != 0)
+            .map(|var| self.packages[var])
+            .collect()
+    }
+
+    fn visit(&self, var: usize, visited: &mut [bool], order: &mut Vec<usize>) {
+        visited[var] = true;
+        for candidates in self.groups[var].iter().chain(&self.recommends[var]) {
+            let chosen = candidates
+                .iter()
+                .find(|&&candidate| self.values[candidate] == Some(true));
+            if let Some(&chosen) = chosen
+                && !visited[chosen]
+            {
+                self.visit(chosen, visited, order);
+            }
+        }
+        order.push(var);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::pkg::manifest::ManifestFormat;
+
+    /// `[relation]` に `relation` を書いたマニフェストからパッケージを作ります。
+    pub(super) fn package(name: &str, version: &str, relation: &str) -> PackageData {
+        let text = format!(
+            "[about.package]\nname = \"{}\"\nversion = \"{}\"\n\n[relation]\n{}\n",
+            name, version, relation
+        );
+        PackageData::from_manifest_str(&text, ManifestFormat::Toml).unwrap()
+    }
+
+    fn names(plan: &InstallPlan) -> Vec<String> {
+        plan.packages()
+            .iter()
+            .map(|package| {
+                let about = &package.about.package;
+                format!("{} {}", about.name, about.version)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn newest_versions_are_installed_in_order() {
+        let root = package("app", "1.0.0", r#"depend = "libfoo (>= 1.0), libbar""#);
+        let index = PackageIndex::new(vec![
+            package("libfoo", "1.0.0", ""),
+            package("libfoo", "1.2.0", r#"pre-depends = "libbaz""#),
+            package("libbar", "0.9.0", r#"depend = "libbaz (<< 2.0)""#),
+            package("libbaz", "1.0.0", ""),
+            package("libbaz", "2.0.0", ""),
+            package("unused", "1.0.0", ""),
+        ]);
+        let plan = resolve(&root, &index).unwrap();
+        assert_eq!(
+            names(&plan),
+            ["libbaz 1.0.0", "libfoo 1.2.0", "libbar 0.9.0"]
+        );
+    }
+
+    #[test]
+    fn versions_and_alternatives_are_backtracked() {
+        // libfoo 2.0 と libbar は libbaz の範囲が重ならないので、libfoo 1.0 を選ぶ
+        let root = package("app", "1.0.0", r#"depend = "libfoo, libbar""#);
+        let index = PackageIndex::new(vec![
+            package("libfoo", "1.0.0", r#"depend = "libbaz (<< 2.0)""#),
+            package("libfoo", "2.0.0", r#"depend = "libbaz (>= 2.0)""#),
+            package("libbar", "1.0.0", r#"depend = "libbaz (<< 2.0)""#),
+            package("libbaz", "1.5.0", ""),
+            package("libbaz", "2.1.0", ""),
+        ]);
+        let plan = resolve(&root, &index).unwrap();
+        assert_eq!(
+            names(&plan),
+            ["libbaz 1.5.0", "libfoo 1.0.0", "libbar 1.0.0"]
+        );
+
+        // 最初の代替は競合するので、仮想パッケージを提供する二つ目を選ぶ
+        let root = package(
+            "app",
+            "1.0.0",
+            "depend = \"mta-postfix | mail-transport-agent\"\nconflict = [{ name = \"mta-postfix\" }]",
+        );
+        let index = PackageIndex::new(vec![
+            package("mta-postfix", "3.0.0", ""),
+            package("exim", "4.0.0", r#"provides = "mail-transport-agent""#),
+        ]);
+        let plan = resolve(&root, &index).unwrap();
+        assert_eq!(names(&plan), ["exim 4.0.0"]);
+    }
+
+    #[test]
+    fn recommends_are_installed_when_possible() {
+        let root = package("app", "1.0.0", r#"depend = "libfoo""#);
+        let index = PackageIndex::new(vec![
+            package(
+                "libfoo",
+                "1.0.0",
+                "recommends = \"libdoc, libextra | libfallback\"\nreplaces = \"libold\"",
+            ),
+            package("libdoc", "1.0.0", r#"depend = "libdata""#),
+            package("libdata", "1.0.0", ""),
+            package("libextra", "1.0.0", r#"conflict = [{ name = "app" }]"#),
+            package("libfallback", "1.0.0", ""),
+            package("libold", "1.0.0", ""),
+        ]);
+        let plan = resolve(&root, &index).unwrap();
+        // libextra はルートと競合するので、同じ推奨の次の候補が選ばれる
+        assert_eq!(
+            names(&plan),
+            [
+                "libdata 1.0.0",
+                "libdoc 1.0.0",
+                "libfallback 1.0.0",
+                "libfoo 1.0.0"
+            ]
+        );
+
+        // 推奨を満たせなくても、必要な依存関係は解決できる
+        let root = package("app", "1.0.0", r#"depend = "libfoo (>= 2.0)""#);
+        let index = PackageIndex::new(vec![
+            package("libfoo", "2.0.0", r#"recommends = "libbar""#),
+            package("libbar", "1.0.0", r#"depend = "libbaz (>= 2.0)""#),
+            package("libbaz", "1.0.0", ""),
+        ]);
+        let plan = resolve(&root, &index).unwrap();
+        assert_eq!(names(&plan), ["libfoo 2.0.0"]);
+    }
+
+    #[test]
+    fn strategies_order_the_versions() {
+        let root = package("app", "1.0.0", r#"depend = "libfoo (>= 1.1), libbar""#);
+        let index = PackageIndex::new(vec![
+            package("libfoo", "1.0.0", ""),
+            package("libfoo", "1.1.0", ""),
+            package("libfoo", "1.2.0", ""),
+            package("libfoo", "2.0.0", ""),
+            package("libbar", "1.0.0", r#"depend = "libbaz (>= 0.2)""#),
+            package("libbar", "2.0.0", ""),
+            package("libbaz", "0.1.0", ""),
+            package("libbaz", "0.2.0", ""),
+            package("libbaz", "0.3.0", ""),
+        ]);
+        let resolved = |strategy: Strategy| names(&resolve_with(&root, &index, &strategy).unwrap());
+        assert_eq!(
+            resolved(Strategy::Highest),
+            ["libfoo 2.0.0", "libbar 2.0.0"]
+        );
+        assert_eq!(
+            resolved(Strategy::Lowest),
+            ["libfoo 1.1.0", "libbaz 0.2.0", "libbar 1.0.0"]
+        );
+        // 優先するバージョンが範囲を満たさなければ、新しいバージョンから試す
+        let versions = HashMap::from([
+            ("libfoo".to_string(), "1.2.0".parse().unwrap()),
+            ("libbar".to_string(), "1.0.0".parse().unwrap()),
+            ("libbaz".to_string(), "0.1.0".parse().unwrap()),
+        ]);
+        let expected = ["libfoo 1.2.0", "libbaz 0.3.0", "libbar 1.0.0"];
+        assert_eq!(
+            resolved(Strategy::PreferInstalled(versions.clone())),
+            expected
+        );
+        assert_eq!(resolved(Strategy::PreferLocked(versions)), expected);
+    }
+
+    #[test]
+    fn unsatisfiable_requirements_are_explained() {
+        colored::control::set_override(false);
+        let root = package("app", "1.0.0", r#"depend = "libfoo (>= 2.0), libbar""#);
+        let index = PackageIndex::new(vec![
+            package("libfoo", "1.0.0", ""),
+            package("libfoo", "2.0.0", ""),
+            package("libbar", "1.0.0", r#"breaks = "libfoo (>= 2.0)""#),
+        ]);
+        let error = resolve(&root, &index).unwrap_err();
+        assert_eq!(error.root, "app 1.0.0");
+        assert_eq!(
+            error.explanation,
+            [
+                "Because libbar breaks libfoo (>= 2.0) and app depends on libbar, \
+                 app cannot be installed together with libfoo (>= 2.0).",
+                "And because app depends on libfoo (>= 2.0), app cannot be installed.",
+            ]
+        );
+
+        let root = package("app", "1.0.0", r#"depend = "libqux""#);
+        let error = resolve(&root, &PackageIndex::default()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "cannot resolve the dependencies of app 1.0.0\n\n\
+             Because app depends on libqux but no package in the index satisfies it, \
+             app cannot be installed."
+        );
+    }
+}
@@ -0,0 +1,357 @@
+//! 解がないことの説明。
+//!
+//! 空の節の導出を、「Because ... and ..., ...」という文の列にします。導出は節を順に
+//! 融合 (resolution) したものなので、一回の融合を一文にします。途中で導いた節を使うときは、
+//! その節を結論とする行に番号を付けて参照します。
+//!
+//! パッケージの組は、関係に書かれた範囲 (`libfoo (>= 2.0)`) で表せればそれを使い、
+//! 表せなければインデックスにあるバージョンから範囲を作ります。
+use super::{Cause, Literal, ResolveError, Solver};
+use crate::modules::pkg::{DependPackageData, PackageAboutData, PackageData};
+use crate::modules::version::VersionRange;
+use colored::{Color, Colorize};
+use std::collections::HashMap;
+
+/// 二つの節を融合した節を返します。相補的なリテラルの組は取り除きます。
+fn resolve_literals(a: &[Literal], b: &[Literal]) -> Vec<Literal> {
+    let mut literals = Vec::new();
+    for &literal in a.iter().chain(b) {
+        let opposite = Literal {
+            var: literal.var,
+            positive: !literal.positive,
+        };
+        if !a.contains(&opposite) && !b.contains(&opposite) && !literals.contains(&literal) {
+            literals.push(literal);
+        }
+    }
+    literals
+}
+
+/// `package` が `other` を拒む関係と、その種類 (`conflicts with` か `breaks`) を返します。
+fn rejection<'a>(
+    package: &'a PackageData,
+    other: &PackageData,
+) -> Option<(&'static str, &'a DependPackageData)> {
+    let relation = &package.relation;
+    let conflict = relation.conflict.iter().map(|r| ("conflicts with", r));
+    let breaks = relation.breaks.iter().flatten().map(|r| ("breaks", r));
+    conflict
+        .chain(breaks)
+        .find(|(_, relation)| other.satisfies(relation))
+}
+
+/// 関係を `名前 (範囲)` の形で、名前に色を付けて返します。範囲が `*` なら名前だけです。
+fn relation_text(depend: &DependPackageData, color: Color) -> String {
+    if depend.version == VersionRange::default() {
+        depend.name.color(color).to_string()
+    } else {
+        format!("{} ({})", depend.name.color(color), depend.version)
+    }
+}
+
+impl Solver<'_> {
+    /// 空の節の導出から、解がない理由を説明するエラーを作ります。
+    pub(super) fn error(&self, refutation: usize) -> ResolveError {
+        let mut explanation = Vec::new();
+        self.explain(refutation, &mut explanation, &mut HashMap::new());
+        let about = &self.packages[0].about.package;
+        ResolveError {
+            root: format!("{} {}", about.name, about.version),
+            explanation,
+        }
+    }
+
+    /// 導いた節を説明する行を加えます。途中で導いた節は先に説明し、番号を付けます。
+    fn explain(&self, clause: usize, lines: &mut Vec<String>, numbers: &mut HashMap<usize, usize>) {
+        let Cause::Derived(used) = &self.clauses[clause].cause else {
+            return;
+        };
+        // ルートは常にインストールするので、ルートとの融合は説明しない
+        let steps: Vec<usize> = used[1..]
+            .iter()
+            .copied()
+            .filter(|&step| self.clauses[step].cause != Cause::Root)
+            .collect();
+        if steps.is_empty() && matches!(self.clauses[used[0]].cause, Cause::Derived(_)) {
+            return self.explain(used[0], lines, numbers);
+        }
+        for &used in used {
+            if matches!(self.clauses[used].cause, Cause::Derived(_)) && !numbers.contains_key(&used)
+            {
+                self.explain(used, lines, numbers);
+                let number = numbers.len() + 1;
+                if let Some(line) = lines.last_mut() {
+                    line.push_str(&format!(" ({})", number));
+                }
+                numbers.insert(used, number);
+            }
+        }
+
+        let mut current = self.clauses[used[0]].literals.clone();
+        let mut line = format!("{} {}", "Because".bold(), self.cite(used[0], numbers));
+        for (i, &step) in steps.iter().enumerate() {
+            current = resolve_literals(&current, &self.clauses[step].literals);
+            if i > 0 {
+                line = format!("{} {}", "And because".bold(), self.cite(step, numbers));
+            } else {
+                line = format!("{} and {}", line, self.cite(step, numbers));
+            }
+            lines.push(format!("{}, {}.", line, self.statement(&current)));
+        }
+        if steps.is_empty() {
+            lines.push(format!("{}, {}.", line, self.statement(&current)));
+        }
+    }
+
+    /// 節を、説明の中で引用する形で返します。導いた節には行の番号を付けます。
+    fn cite(&self, clause: usize, numbers: &HashMap<usize, usize>) -> String {
+        match numbers.get(&clause) {
+            Some(number) => format!(
+                "{} ({})",
+                self.statement(&self.clauses[clause].literals),
+                number
+            ),
+            None => self.describe(clause),
+        }
+    }
+
+    /// 元の節を、パッケージの関係として説明します。
+    fn describe(&self, clause: usize) -> String {
+        let clause = &self.clauses[clause];
+        match clause.cause {
+            Cause::Depends { package, group } => {
+                let relation = self.packages[package]
+                    .relation
+                    .required_groups()
+                    .nth(group)
+                    .map(|group| {
+                        group
+                            .iter()
+                            .map(|depend| relation_text(depend, Color::Green))
+                            .collect::<Vec<_>>()
+                            .join(" | ")
+                    })
+                    .unwrap_or_default();
+                let text = format!("{} depends on {}", self.package_term(package), relation);
+                if self.groups[package][group].is_empty() {
+                    format!("{} but no package in the index satisfies it", text)
+                } else {
+                    text
+                }
+            }
+            Cause::Conflict { package, other } => {
+                match rejection(self.packages[package], self.packages[other]) {
+                    Some((kind, relation)) => format!(
+                        "{} {} {}",
+                        self.package_term(package),
+                        kind,
+                        relation_text(relation, Color::Red)
+                    ),
+                    None => format!(
+                        "{} conflicts with {}",
+                        self.package_term(package),
+                        self.package_term(other)
+                    ),
+                }
+            }
+            Cause::SameName => {
+                let name = &self.packages[clause.literals[0].var].about.package.name;
+                format!("only one version of {} can be installed", name.cyan())
+            }
+            Cause::Root | Cause::Derived(_) => self.statement(&clause.literals),
+        }
+    }
+
+    /// 節を、インストールできない組や依存の形の文にします。
+    fn statement(&self, literals: &[Literal]) -> String {
+        let mut omitted: Vec<usize> = literals
+            .iter()
+            .filter(|literal| !literal.positive)
+            .map(|literal| literal.var)
+            .collect();
+        // ルートやルートに近いパッケージを主語にする
+        omitted.sort();
+        let installed: Vec<usize> = literals
+            .iter()
+            .filter(|literal| literal.positive)
+            .map(|literal| literal.var)
+            .collect();
+        let omitted = self.terms(&omitted, Color::Cyan);
+        let installed = self.terms(&installed, Color::Green).join(" | ");
+        match (omitted.as_slice(), installed.is_empty()) {
+            ([], true) => "the dependencies cannot be satisfied".to_string(),
+            ([], false) => format!("{} must be installed", installed),
+            ([package], true) => format!("{} cannot be installed", package),
+            ([package, others @ ..], true) => format!(
+                "{} cannot be installed together with {}",
+                package,
+                others.join(" and ")
+            ),
+            ([package], false) => format!("{} depends on {}", package, installed),
+            (packages, false) => format!("{} depend on {}", packages.join(" and "), installed),
+        }
+    }
+
+    /// 関係を宣言したパッケージを、`名前` か `名前 バージョン` にします。
+    fn package_term(&self, var: usize) -> String {
+        let about = &self.packages[var].about.package;
+        if self.available(&about.name).len() == 1 {
+            about.name.cyan().to_string()
+        } else {
+            format!("{} {}", about.name.cyan(), about.version)
+        }
+    }
+
+    /// パッケージの列を名前ごとにまとめ、`名前`、`名前 バージョン` か `名前 (範囲)` にします。
+    ///
+    /// 名前のすべてのバージョンを含むなら名前だけ、一つだけならそのバージョンを示します。
+    fn terms(&self, vars: &[usize], color: Color) -> Vec<String> {
+        let mut names: Vec<&str> = Vec::new();
+        for &var in vars {
+            let name = self.packages[var].about.package.name.as_str();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+            .into_iter()
+            .map(|name| {
+                let versions: Vec<&PackageAboutData> = vars
+                    .iter()
+                    .map(|&var| &self.packages[var].about.package)
+                    .filter(|about| about.name == name)
+                    .collect();
+                let available = self.available(name);
+                if versions.len() == available.len() {
+                    return name.color(color).to_string();
+                }
+                match (
+                    self.relation_range(name, &versions, &available),
+                    versions.as_slice(),
+                ) {
+                    (Some(range), _) => format!("{} ({})", name.color(color), range),
+                    (None, [about]) => format!("{} {}", name.color(color), about.version),
+                    (None, _) => format!(
+                        "{} ({})",
+                        name.color(color),
+                        interval_range(&versions, &available)
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    /// 名前が `name` のパッケージとして、ルートかインデックスにあるものを古い順に返します。
+    fn available(&self, name: &str) -> Vec<&PackageAboutData> {
+        let root = &self.packages[0].about.package;
+        if root.name == name {
+            return vec![root];
+        }
+        let mut available: Vec<&PackageAboutData> = self
+            .index
+            .packages()
+            .iter()
+            .map(|package| &package.about.package)
+            .filter(|about| about.name == name)
+            .collect();
+        available.sort_by(|a, b| a.version.cmp(&b.version));
+        available.dedup_by(|a, b| a.version == b.version);
+        available
+    }
+
+    /// 関係に書かれた範囲のうち、利用できるバージョンからちょうど `versions` を選ぶものを返します。
+    fn relation_range(
+        &self,
+        name: &str,
+        versions: &[&PackageAboutData],
+        available: &[&PackageAboutData],
+    ) -> Option<String> {
+        let mut relations = self.packages.iter().flat_map(|package| {
+            let relation = &package.relation;
+            relation
+                .required_groups()
+                .flatten()
+                .chain(&relation.conflict)
+                .chain(relation.breaks.iter().flatten())
+        });
+        relations
+            .find(|depend| {
+                depend.name == name
+                    && available
+                        .iter()
+                        .all(|about| depend.is_satisfied_by(about) == contains(versions, about))
+            })
+            .map(|depend| depend.version.to_string())
+    }
+}
+
+fn contains(versions: &[&PackageAboutData], about: &PackageAboutData) -> bool {
+    versions.iter().any(|v| v.version == about.version)
+}
+
+/// 利用できるバージョンを古い順に並べ、`versions` の連続する部分ごとに区間を作ります。
+fn interval_range(versions: &[&PackageAboutData], available: &[&PackageAboutData]) -> String {
+    let mut intervals = Vec::new();
+    let mut i = 0;
+    while i < available.len() {
+        if !contains(versions, available[i]) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i + 1 < available.len() && contains(versions, available[i + 1]) {
+            i += 1;
+        }
+        let mut bounds = Vec::new();
+        if start > 0 {
+            bounds.push(format!(">= {}", available[start].version));
+        }
+        if i + 1 < available.len() {
+            bounds.push(format!("< {}", available[i + 1].version));
+        }
+        intervals.push(bounds.join(", "));
+        i += 1;
+    }
+    let text = intervals.join(" || ");
+    VersionRange::parse_with(&text, available[0].scheme)
+        .map(|range| range.to_string())
+        .unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::resolve;
+    use super::super::tests::package;
+    use crate::modules::pkg::index::PackageIndex;
+
+    #[test]
+    fn learned_clauses_are_numbered_and_ranges_are_derived() {
+        colored::control::set_override(false);
+        // libbar が必要とする libbaz 2.0 は libbar を壊すので、libbar はインストールできない
+        let root = package("app", "1.0.0", r#"depend = "libfoo, libbar""#);
+        let index = PackageIndex::new(vec![
+            package("libfoo", "1.0.0", r#"depend = "libbaz (<< 2.0)""#),
+            package("libfoo", "2.0.0", r#"depend = "libbaz (>= 2.0)""#),
+            package("libbar", "1.0.0", r#"depend = "libbaz (>= 1.5)""#),
+            package("libbar", "1.1.0", r#"depend = "libbaz (>= 1.5)""#),
+            package("libbaz", "1.0.0", ""),
+            package("libbaz", "2.0.0", r#"breaks = "libbar""#),
+        ]);
+        let error = resolve(&root, &index).unwrap_err();
+        assert_eq!(
+            error.explanation,
+            [
+                "Because app depends on libbar and libbaz 2.0.0 breaks libbar, \
+                 app and libbaz (>= 2.0) depend on libbar 1.0.0.",
+                "And because libbaz 2.0.0 breaks libbar, \
+                 app cannot be installed together with libbaz (>= 2.0). (1)",
+                "Because app depends on libbar and libbar 1.1.0 depends on libbaz (>= 1.5), \
+                 app depends on libbar 1.0.0 | libbaz (>= 2.0).",
+                "And because libbar 1.0.0 depends on libbaz (>= 1.5), \
+                 app depends on libbaz (>= 2.0).",
+                "And because app cannot be installed together with libbaz (>= 2.0) (1), \
+                 app cannot be installed.",
+            ]
+        );
+    }
+}
@@ -1,5 +1,17 @@
+use colored::Colorize;
 use std::fmt::{Display, Formatter, Result};
+use std::path::{Path, PathBuf};
 
+use crate::modules::config::Config;
+use crate::modules::pkg::PackageData;
+use crate::modules::pkg::check::{self, Severity};
+use crate::modules::pkg::index::PackageIndex;
+use crate::modules::pkg::license;
+use crate::modules::pkg::lock::{self, LOCK_FILE, LockFile};
+use crate::modules::pkg::manifest::{MANIFEST_FILE, Manifest, ManifestFormat, find_manifest};
+use crate::modules::pkg::resolve::{self, Strategy};
+use crate::modules::version::Version;
+use crate::modules::version::bump::VersionPart;
 use crate::utils::shell::question;
 
 enum ProjectTemplateType {
@@ -35,3 +47,360 @@ pub fn create_project_interactively() {
     let params = get_params_interactively();
     create_empty_project(params);
 }
+
+/// コマンドの対象のマニフェストを返します。
+///
+/// # 引数
+///
+/// * `input` - コマンドラインで指定したマニフェスト。省略するとカレントディレクトリから探します。
+///
+/// # 戻り値
+///
+/// * `Ok(PathBuf)` - マニフェストのパス。
+/// * `Err(String)` - 省略されていて、カレントディレクトリにマニフェストがない場合。
+fn manifest_path(input: Option<&str>) -> std::result::Result<PathBuf, String> {
+    match input {
+        Some(input) => Ok(PathBuf::from(input)),
+        None => find_manifest(Path::new("."))
+            .ok_or_else(|| format!("No manifest found (expected {})", MANIFEST_FILE)),
+    }
+}
+
+/// `ipkg version bump <part>`: カレントディレクトリのマニフェストのバージョンを繰り上げて
+/// 書き戻します。
+///
+/// # 引数
+///
+/// * `part` - 繰り上げる部分 (`major`、`minor`、`patch`、`prerelease`)。
+///
+/// # 戻り値
+///
+/// * `Ok(())` - マニフェストを書き換えた場合。
+/// * `Err(String)` - マニフェストやバージョンが不正な場合、エラーメッセージを含む。
+pub fn bump_version(part: &str) -> std::result::Result<(), String> {
+    let part: VersionPart = part.parse()?;
+    let path = manifest_path(None)?;
+    let (current, next) = bump_manifest(&path, part)?;
+    println!(
+        "{} {} -> {}",
+        "Bumped".green().bold(),
+        current.to_string().yellow(),
+        next.to_string().green()
+    );
+    Ok(())
+}
+
+/// マニフェストのバージョンを繰り上げて書き戻し、前後のバージョンを返します。
+///
+/// TOML はコメントや書式を残したまま書き換え、JSON と YAML は読み込んだ内容を書き直します。
+fn bump_manifest(
+    path: &Path,
+    part: VersionPart,
+) -> std::result::Result<(Version, Version), String> {
+    if ManifestFormat::from_path(path)? == ManifestFormat::Toml {
+        let mut manifest = Manifest::load(path)?;
+        let current = manifest.version()?;
+        let next = current.bump(part).map_err(|e| e.to_string())?;
+        manifest.set_version(&next)?;
+        manifest.save(path)?;
+        return Ok((current, next));
+    }
+    let mut data = PackageData::load(path)?;
+    let current = data.about.package.version.clone();
+    let next = current.bump(part).map_err(|e| e.to_string())?;
+    data.about.package.version = next.clone();
+    data.save(path)?;
+    Ok((current, next))
+}
+
+/// `ipkg manifest convert --to <format> [file]`: マニフェストを別の形式で書き出します。
+///
+/// 書き出し先は元のファイルと同じ名前で拡張子だけを変えたファイルです。
+///
+/// # 引数
+///
+/// * `to` - 書き出す形式 (`toml`、`json`、`yaml`)。
+/// * `input` - 元のマニフェスト。省略するとカレントディレクトリから探します。
+///
+/// # 戻り値
+///
+/// * `Ok(())` - 書き出した場合。
+/// * `Err(String)` - マニフェストが見つからないか不正な場合、または書き出し先がすでにある場合。
+pub fn convert_manifest(to: &str, input: Option<&str>) -> std::result::Result<(), String> {
+    let format: ManifestFormat = to.parse()?;
+    let input = manifest_path(input)?;
+    let output = input.with_extension(format.extension());
+    if output.exists() {
+        return Err(format!("{} already exists", output.display()));
+    }
+    PackageData::load(&input)?.save(&output)?;
+    println!(
+        "{} {} -> {}",
+        "Converted".green().bold(),
+        input.display().to_string().yellow(),
+        output.display().to_string().green()
+    );
+    Ok(())
+}
+
+/// `ipkg check [file]`: マニフェストを検証し、問題を rustc と同じ形で表示します。
+///
+/// # 引数
+///
+/// * `input` - 検証するマニフェスト。省略するとカレントディレクトリから探します。
+///
+/// # 戻り値
+///
+/// * `Ok(())` - エラーがなかった場合 (警告はあってもよい)。
+/// * `Err(String)` - マニフェストが読めないか、エラーが見つかった場合。
+pub fn check_manifest(input: Option<&str>) -> std::result::Result<(), String> {
+    let path = manifest_path(input)?;
+    let (source, diagnostics) = check::check_manifest(&path)?;
+    let name = path.display().to_string();
+    for diagnostic in &diagnostics {
+        eprintln!("{}", diagnostic.render(&name, &source));
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    match errors {
+        0 => {
+            println!("{} {}", "Checked".green().bold(), name);
+            Ok(())
+        }
+        1 => Err(format!(
+            "could not check `{}` due to 1 previous error",
+            name
+        )),
+        n => Err(format!(
+            "could not check `{}` due to {} previous errors",
+            name, n
+        )),
+    }
+}
+
+/// `ipkg license check [file]`: 依存パッケージのライセンスを設定の許可リストで調べます。
+///
+/// 依存パッケージは設定の `index` のディレクトリから探し、許可リストは `[license]` の `allow` です。
+///
+/// # 引数
+///
+/// * `input` - プロジェクトのマニフェスト。省略するとカレントディレクトリから探します。
+///
+/// # 戻り値
+///
+/// * `Ok(())` - すべての依存パッケージのライセンスが許可されている場合。
+/// * `Err(String)` - 設定やマニフェストが不正な場合、または許可されないライセンスがある場合。
+pub fn check_licenses(input: Option<&str>) -> std::result::Result<(), String> {
+    let path = manifest_path(input)?;
+    let project = path.parent().unwrap_or(Path::new("."));
+    let config = Config::for_project(project)?;
+    let policy = config.license_policy()?;
+    if policy.is_empty() {
+        return Err(
+            "No license allow-list configured (set license.allow in .ipkg/config.toml)".to_string(),
+        );
+    }
+    let root = PackageData::load(&path)?;
+    let report = license::check_licenses(&root, &config.package_index()?, &policy);
+    for missing in &report.missing {
+        eprintln!(
+            "{}: dependency {} was not found in the package index",
+            "warning".yellow().bold(),
+            missing
+        );
+    }
+    for violation in &report.violations {
+        eprintln!("{}: {}", "error".red().bold(), violation);
+    }
+    match report.violations.len() {
+        0 => {
+            println!(
+                "{} licenses of {} package(s)",
+                "Checked".green().bold(),
+                report.checked
+            );
+            Ok(())
+        }
+        1 => Err("1 package has a license that is not allowed".to_string()),
+        n => Err(format!("{} packages have licenses that are not allowed", n)),
+    }
+}
+
+/// 解決の方針を解釈します。`installed` なら設定の `installed` のディレクトリにある
+/// インストール済みのバージョンを、`locked` ならプロジェクトの `ipkg.lock` のバージョンを優先します。
+fn parse_strategy(
+    name: Option<&str>,
+    project: &Path,
+    config: &Config,
+    index: &PackageIndex,
+) -> std::result::Result<Strategy, String> {
+    match name.unwrap_or("highest") {
+        "highest" => Ok(Strategy::Highest),
+        "lowest" => Ok(Strategy::Lowest),
+        "installed" => Ok(Strategy::PreferInstalled(config.installed_versions()?)),
+        "locked" => {
+            let lock = LockFile::load(&project.join(LOCK_FILE))?;
+            Ok(Strategy::PreferLocked(lock.versions(index)))
+        }
+        other => Err(format!(
+            "Unknown strategy `{}` (expected highest, lowest, installed or locked)",
+            other
+        )),
+    }
+}
+
+/// `ipkg resolve [file] [--strategy <highest|lowest|installed|locked>] [--frozen]`: 依存関係を解決し、
+/// インストールするパッケージを順に表示します。
+///
+/// 依存パッケージは設定の `index` のディレクトリから探します。`--frozen` では `ipkg.lock` に
+/// ロックしたパッケージだけを使い、マニフェストがロックと一致しなければエラーにします。
+///
+/// # 引数
+///
+/// * `input` - プロジェクトのマニフェスト。省略するとカレントディレクトリから探します。
+/// * `strategy` - `highest` なら新しいバージョンを、`lowest` なら範囲の下限のバージョンを、
+///   `installed` ならインストール済みのバージョンを、`locked` なら `ipkg.lock` のバージョンを
+///   優先します。省略すると `highest` です。
+/// * `frozen` - ロックしたパッケージだけで解決するか。
+///
+/// # 戻り値
+///
+/// * `Ok(())` - 依存関係を解決できた場合。
+/// * `Err(String)` - 設定やマニフェストが不正な場合、依存関係を満たせない場合、
+///   または `frozen` でロックがマニフェストと一致しない場合。
+pub fn resolve_dependencies(
+    input: Option<&str>,
+    strategy: Option<&str>,
+    frozen: bool,
+) -> std::result::Result<(), String> {
+    let path = manifest_path(input)?;
+    let project = path.parent().unwrap_or(Path::new("."));
+    let root = PackageData::load(&path)?;
+    let index;
+    let plan = if frozen {
+        if strategy.is_some() {
+            return Err("--frozen cannot be combined with --strategy".to_string());
+        }
+        let lock = LockFile::load(&project.join(LOCK_FILE))?;
+        index = lock.load_packages(project)?;
+        lock::resolve_frozen(&root, &index)?
+    } else {
+        let config = Config::for_project(project)?;
+        index = config.package_index()?;
+        let strategy = parse_strategy(strategy, project, &config, &index)?;
+        resolve::resolve_with(&root, &index, &strategy).map_err(|e| e.to_string())?
+    };
+    for package in plan.packages() {
+        let about = &package.about.package;
+        println!("  {} {}", about.name.cyan(), about.version);
+    }
+    println!(
+        "{} {} package(s)",
+        "Resolved".green().bold(),
+        plan.packages().len()
+    );
+    Ok(())
+}
+
+/// `ipkg lock [file] [--strategy <highest|lowest|installed|locked>]`: 依存関係を解決し直し、
+/// 結果を `ipkg.lock` に書き込みます。
+///
+/// # 引数
+///
+/// * `input` - プロジェクトのマニフェスト。省略するとカレントディレクトリから探します。
+/// * `strategy` - 解決の方針。[`resolve_dependencies`] と同じです。
+///
+/// # 戻り値
+///
+/// * `Ok(())` - ロックファイルを書き込んだ場合。
+/// * `Err(String)` - 設定やマニフェストが不正な場合、または依存関係を満たせない場合。
+pub fn lock_dependencies(
+    input: Option<&str>,
+    strategy: Option<&str>,
+) -> std::result::Result<(), String> {
+    let path = manifest_path(input)?;
+    let project = path.parent().unwrap_or(Path::new("."));
+    let config = Config::for_project(project)?;
+    let index = config.package_index()?;
+    let strategy = parse_strategy(strategy, project, &config, &index)?;
+    let root = PackageData::load(&path)?;
+    let plan = resolve::resolve_with(&root, &index, &strategy).map_err(|e| e.to_string())?;
+    let lock = LockFile::from_plan(&plan, &index, project)?;
+    let output = project.join(LOCK_FILE);
+    lock.save(&output)?;
+    println!(
+        "{} {} package(s) in {}",
+        "Locked".green().bold(),
+        lock.packages.len(),
+        output.display().to_string().green()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn installed_versions_are_preferred() {
+        let dir = std::env::temp_dir().join(format!("ipkg-project-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let installed = dir.join("installed");
+        fs::create_dir_all(&installed).unwrap();
+        let libfoo = |version: &str| {
+            format!(
+                "[about.package]\nname = \"libfoo\"\nversion = \"{}\"\n",
+                version
+            )
+        };
+        fs::write(installed.join("libfoo.toml"), libfoo("1.0.0")).unwrap();
+        let config = Config {
+            installed: vec![installed],
+            ..Config::default()
+        };
+        let index = PackageIndex::new(
+            ["1.0.0", "2.0.0"]
+                .iter()
+                .map(|version| {
+                    PackageData::from_manifest_str(&libfoo(version), ManifestFormat::Toml).unwrap()
+                })
+                .collect(),
+        );
+        let root = PackageData::from_manifest_str(
+            "[about.package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[relation]\ndepend = \"libfoo\"\n",
+            ManifestFormat::Toml,
+        )
+        .unwrap();
+        let version = |name: Option<&str>| {
+            let strategy = parse_strategy(name, &dir, &config, &index).unwrap();
+            let plan = resolve::resolve_with(&root, &index, &strategy).unwrap();
+            plan.packages()[0].about.package.version.to_string()
+        };
+        assert_eq!(version(None), "2.0.0");
+        assert_eq!(version(Some("installed")), "1.0.0");
+        let error = parse_strategy(Some("newest"), &dir, &config, &index).unwrap_err();
+        assert!(error.contains("installed"), "{}", error);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn json_manifests_are_bumped() {
+        let dir = std::env::temp_dir().join(format!("ipkg-bump-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("ipkg.json"),
+            r#"{"about": {"package": {"name": "app", "version": "1.2.3"}}}"#,
+        )
+        .unwrap();
+        let path = find_manifest(&dir).unwrap();
+        let (current, next) = bump_manifest(&path, VersionPart::Minor).unwrap();
+        assert_eq!((current.as_str(), next.as_str()), ("1.2.3", "1.3.0"));
+        let data = PackageData::load(&path).unwrap();
+        assert_eq!(data.about.package.name, "app");
+        assert_eq!(data.about.package.version.as_str(), "1.3.0");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
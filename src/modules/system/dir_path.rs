@@ -0,0 +1,27 @@
+//! ipkg が使うディレクトリとファイルの場所。
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// 設定などを置くディレクトリの名前
+pub const IPKG_DIR: &str = ".ipkg";
+
+/// 設定ファイルの名前
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// ホームディレクトリを返します。
+pub fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// プロジェクトの設定ファイルの場所 (`<project>/.ipkg/config.toml`) を返します。
+pub fn project_config_file(project: &Path) -> PathBuf {
+    project.join(IPKG_DIR).join(CONFIG_FILE)
+}
+
+/// ユーザーの設定ファイルの場所 (`~/.ipkg/config.toml`) を返します。
+pub fn user_config_file() -> Option<PathBuf> {
+    home_dir().map(|home| home.join(IPKG_DIR).join(CONFIG_FILE))
+}
@@ -1,38 +1,281 @@
-use std::{fmt, fmt::Display, str::FromStr};
+use std::{
+    cmp::Ordering,
+    fmt,
+    fmt::Display,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
 
-#[derive(Debug, PartialEq, Clone)]
+pub mod bump;
+pub mod dpkg;
+pub mod ecosystem;
+pub mod error;
+pub mod nightly;
+pub mod pep440;
+pub mod rpm;
+pub mod scheme;
+pub mod serialize;
+
+pub use error::{VersionError, VersionErrorKind};
+use scheme::VersionScheme;
+
+/// バージョン文字列の解釈と比較の方式。
+///
+/// 実際の規則は [`VersionScheme`] の各実装が持ち、[`Scheme::get`] で取り出せます。
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+pub enum Scheme {
+    /// SemVer 2.0 のプレリリース・ビルドメタデータを理解する既定の方式
+    #[default]
+    SemVer,
+    /// `dpkg --compare-versions` 互換の方式 (エポック、リビジョン、`~`)
+    Dpkg,
+    /// `rpmvercmp` 互換の方式 (エポック、リリース、`~`、`^`)
+    Rpm,
+    /// Python の PEP 440
+    Pep440,
+    /// カレンダーバージョン (YYYY.MM.DD など)
+    CalVer,
+}
+
+impl Scheme {
+    /// この方式の比較規則を返します。
+    pub fn get(self) -> &'static dyn VersionScheme {
+        match self {
+            Scheme::SemVer => &scheme::SemVer,
+            Scheme::Dpkg => &scheme::Dpkg,
+            Scheme::Rpm => &scheme::Rpm,
+            Scheme::Pep440 => &scheme::Pep440,
+            Scheme::CalVer => &scheme::CalVer,
+        }
+    }
+}
+
+impl Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.get().name())
+    }
+}
+
+impl FromStr for Scheme {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "semver" => Ok(Scheme::SemVer),
+            "dpkg" | "debian" => Ok(Scheme::Dpkg),
+            "rpm" => Ok(Scheme::Rpm),
+            "pep440" => Ok(Scheme::Pep440),
+            "calver" => Ok(Scheme::CalVer),
+            _ => Err(format!("Unknown version scheme: {}", s)),
+        }
+    }
+}
+
+/// 範囲の照合でプレリリースを扱う方針。
+///
+/// 集合としては `2.0.0-beta` も `>= 1.0` に含まれますが、依存関係の解決では
+/// 利用者が明示しない限りプレリリースを選ばないのが一般的です。
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum PrereleasePolicy {
+    /// 同じリリースのプレリリースを条件に書いた区間でだけ受け入れる (npm・Cargo と同じ)
+    #[default]
+    SameRelease,
+    /// 範囲に含まれるプレリリースをすべて受け入れる
+    Include,
+    /// プレリリースを受け入れない
+    Exclude,
+}
+
+impl Display for PrereleasePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            PrereleasePolicy::SameRelease => "same-release",
+            PrereleasePolicy::Include => "include",
+            PrereleasePolicy::Exclude => "exclude",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for PrereleasePolicy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "same-release" => Ok(PrereleasePolicy::SameRelease),
+            "include" => Ok(PrereleasePolicy::Include),
+            "exclude" => Ok(PrereleasePolicy::Exclude),
+            _ => Err(format!("Unknown pre-release policy: {}", s)),
+        }
+    }
+}
+
+/// バージョン。
+///
+/// 等価性・順序・ハッシュはすべて方式ごとの正規形に基づくため、
+/// `1.0` と `1.0.0` は等しく、`BTreeMap` や `HashMap` のキーとして使えます。
+/// 方式が異なるバージョン同士は、まず方式の宣言順で並べられます。
+#[derive(Debug, Clone)]
 pub struct Version {
     string: String,
     nums: Vec<u32>,
+    /// 数値部分の間の区切り文字 (先頭や末尾の数字以外の文字も含む)
     separators: Vec<String>,
+    pre: Vec<Identifier>,
+    build: Vec<String>,
+    scheme: Scheme,
+}
+
+/// SemVer 2.0 のプレリリース識別子。
+///
+/// 数値の識別子は英数字の識別子より常に低い優先順位を持ち、
+/// 英数字同士は ASCII の辞書順で比較されます。
+/// (バリアントの宣言順がそのまま優先順位になっています)
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+enum Identifier {
+    Numeric(u64),
+    AlphaNumeric(String),
+}
+
+impl Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Identifier::Numeric(n) => write!(f, "{}", n),
+            Identifier::AlphaNumeric(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-'
+}
+
+/// ドット区切りの識別子を、開始位置と共に列挙します。
+fn identifiers(s: &str, start: usize) -> Vec<(usize, &str)> {
+    let mut offset = start;
+    s.split('.')
+        .map(|part| {
+            let item = (offset, part);
+            offset += part.len() + 1;
+            item
+        })
+        .collect()
+}
+
+/// 識別子が空でなく、使える文字だけでできていることを確かめます。
+fn check_identifier(input: &str, start: usize, part: &str) -> Result<(), VersionError> {
+    if part.is_empty() {
+        return Err(VersionError::new(
+            VersionErrorKind::EmptyComponent,
+            input,
+            start..start,
+        ));
+    }
+    if let Some((i, c)) = part.char_indices().find(|&(_, c)| !is_identifier_char(c)) {
+        let i = start + i;
+        return Err(VersionError::new(
+            VersionErrorKind::InvalidCharacter(c),
+            input,
+            i..i + c.len_utf8(),
+        ));
+    }
+    Ok(())
+}
+
+/// `input[start..end]` のドット区切りのプレリリース部分を識別子の列に変換します。
+fn parse_pre_release(
+    input: &str,
+    start: usize,
+    end: usize,
+) -> Result<Vec<Identifier>, VersionError> {
+    identifiers(&input[start..end], start)
+        .into_iter()
+        .map(|(start, part)| {
+            check_identifier(input, start, part)?;
+            if !part.chars().all(|c| c.is_ascii_digit()) {
+                return Ok(Identifier::AlphaNumeric(part.to_string()));
+            }
+            let span = start..start + part.len();
+            // 数値の識別子は先頭に0を付けてはならない
+            if part.len() > 1 && part.starts_with('0') {
+                return Err(VersionError::new(
+                    VersionErrorKind::LeadingZero,
+                    input,
+                    span,
+                ));
+            }
+            part.parse::<u64>()
+                .map(Identifier::Numeric)
+                .map_err(|_| VersionError::new(VersionErrorKind::NumberTooLarge, input, span))
+        })
+        .collect()
+}
+
+/// `input` の `start` バイト目から末尾までのビルドメタデータを識別子の列に変換します。
+fn parse_build_metadata(input: &str, start: usize) -> Result<Vec<String>, VersionError> {
+    identifiers(&input[start..], start)
+        .into_iter()
+        .map(|(start, part)| {
+            check_identifier(input, start, part)?;
+            Ok(part.to_string())
+        })
+        .collect()
 }
 impl Default for Version {
     fn default() -> Self {
         Version::from_str("1.0.0").unwrap()
     }
 }
-fn serialize_version_str(version_str: &str) -> (Vec<u32>, Vec<String>) {
+/// `input[start..end]` を数字の並びとそれ以外の区切り文字の並びに分けます。
+///
+/// `checked` なら、`u32` に収まらない数字の並びはその範囲を示す `NumberTooLarge` になります。
+/// 文字列のまま比べる方式 (dpkg、RPM、PEP 440) では数字は範囲の省略記法などにしか
+/// 使わないので、`checked` にせず `u32::MAX` に丸めます。
+fn serialize_version_str(
+    input: &str,
+    start: usize,
+    end: usize,
+    checked: bool,
+) -> Result<(Vec<u32>, Vec<String>), VersionError> {
     let mut numbers = Vec::new();
     let mut separators = Vec::new();
     let mut current_num = String::new();
     let mut current_sep = String::new();
+    // 蓄積中の数字の並びの開始位置
+    let mut num_start = start;
+
+    let push_num = |numbers: &mut Vec<u32>, current_num: &mut String, num_start: usize| {
+        let span = num_start..num_start + current_num.len();
+        let num = match current_num.parse::<u32>() {
+            Ok(num) => num,
+            Err(_) if !checked => u32::MAX,
+            Err(_) => {
+                return Err(VersionError::new(
+                    VersionErrorKind::NumberTooLarge,
+                    input,
+                    span,
+                ));
+            }
+        };
+        numbers.push(num);
+        current_num.clear();
+        Ok::<(), VersionError>(())
+    };
 
-    for c in version_str.chars() {
+    for (i, c) in input[start..end].char_indices() {
         if c.is_ascii_digit() {
             // 区切り文字のシーケンスが終わった場合、追加
             if !current_sep.is_empty() {
                 separators.push(current_sep.clone());
                 current_sep.clear();
             }
+            if current_num.is_empty() {
+                num_start = start + i;
+            }
             // 数字を蓄積
             current_num.push(c);
         } else {
             // 数字のシーケンスが終わった場合、追加
             if !current_num.is_empty() {
-                if let Ok(num) = current_num.parse::<u32>() {
-                    numbers.push(num);
-                }
-                current_num.clear();
+                push_num(&mut numbers, &mut current_num, num_start)?;
             }
             // 非数字を蓄積
             current_sep.push(c);
@@ -41,131 +284,283 @@ fn serialize_version_str(version_str: &str) -> (Vec<u32>, Vec<String>) {
 
     // 残りの数字または区切り文字を追加
     if !current_num.is_empty() {
-        if let Ok(num) = current_num.parse::<u32>() {
-            numbers.push(num);
-        }
+        push_num(&mut numbers, &mut current_num, num_start)?;
     }
     if !current_sep.is_empty() {
         separators.push(current_sep);
     }
 
-    (numbers, separators)
+    Ok((numbers, separators))
 }
 
 impl FromStr for Version {
-    type Err = String;
+    type Err = VersionError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (nums, separators) = serialize_version_str(s);
-        if nums.is_empty() {
-            return Err("There is no values for Version struct.".to_string());
+        Version::parse_with(s, Scheme::SemVer)
+    }
+}
+
+impl Version {
+    /// 指定した方式でバージョン文字列を解釈します。
+    ///
+    /// # 引数
+    ///
+    /// * `s` - バージョン文字列。
+    /// * `scheme` - 解釈と比較に使う方式。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(Version)` - 方式の構文に従っている場合。
+    /// * `Err(VersionError)` - 構文が不正な場合、問題のある位置を含む。
+    pub fn parse_with(s: &str, scheme: Scheme) -> Result<Self, VersionError> {
+        match scheme {
+            Scheme::SemVer => Version::parse_semver(s),
+            Scheme::Dpkg => Version::parse_dpkg(s),
+            Scheme::CalVer => {
+                scheme.get().validate(s)?;
+                Ok(Version {
+                    scheme,
+                    ..Version::parse_semver(s)?
+                })
+            }
+            Scheme::Rpm | Scheme::Pep440 => {
+                scheme.get().validate(s)?;
+                let (nums, separators) = serialize_version_str(s, 0, s.len(), false)?;
+                Ok(Version {
+                    string: s.to_string(),
+                    nums,
+                    separators,
+                    pre: Vec::new(),
+                    build: Vec::new(),
+                    scheme,
+                })
+            }
         }
+    }
+
+    /// このバージョンの比較に使われる方式を返します。
+    pub fn scheme(&self) -> Scheme {
+        self.scheme
+    }
+
+    /// 元のバージョン文字列を返します。
+    pub fn as_str(&self) -> &str {
+        &self.string
+    }
+
+    /// ビルドメタデータ ("+" 以降) の識別子を返します。
+    pub fn build_metadata(&self) -> &[String] {
+        &self.build
+    }
+
+    /// 方式の規則で正規化したバージョン文字列を返します。
+    ///
+    /// 等しいバージョン同士は同じ正規形になります。
+    pub fn canonical(&self) -> String {
+        self.scheme.get().canonical(self)
+    }
+
+    fn parse_dpkg(s: &str) -> Result<Self, VersionError> {
+        let parsed = dpkg::parse(s)?;
+        // 上流のバージョンはエポック (最初の ":" まで) の後に始まる
+        let start = s.find(':').map_or(0, |colon| colon + 1);
+        let (nums, separators) =
+            serialize_version_str(s, start, start + parsed.upstream.len(), false)?;
         Ok(Version {
             string: s.to_string(),
             nums,
             separators,
+            pre: Vec::new(),
+            build: Vec::new(),
+            scheme: Scheme::Dpkg,
+        })
+    }
+
+    fn parse_semver(s: &str) -> Result<Self, VersionError> {
+        if s.is_empty() {
+            return Err(VersionError::new(VersionErrorKind::Empty, s, 0..0));
+        }
+        // "+" 以降はビルドメタデータで、優先順位には影響しない
+        let (without_build, build) = match s.find('+') {
+            Some(plus) => (&s[..plus], parse_build_metadata(s, plus + 1)?),
+            None => (s, Vec::new()),
+        };
+        // 最初の "-" 以降はプレリリース識別子
+        let (core, pre) = match without_build.find('-') {
+            Some(dash) => (
+                &s[..dash],
+                parse_pre_release(s, dash + 1, without_build.len())?,
+            ),
+            None => (without_build, Vec::new()),
+        };
+        // 本体は (`v` の接頭辞の後) ドットで区切った数字だけでできている
+        let first = usize::from(core.starts_with(['v', 'V']));
+        if core.len() == first {
+            return Err(VersionError::new(
+                VersionErrorKind::Malformed("version has no numeric component"),
+                s,
+                0..core.len(),
+            ));
+        }
+        for (start, part) in identifiers(&core[first..], first) {
+            if part.is_empty() {
+                return Err(VersionError::new(
+                    VersionErrorKind::EmptyComponent,
+                    s,
+                    start..start,
+                ));
+            }
+            if let Some((i, c)) = part.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+                let i = start + i;
+                return Err(VersionError::new(
+                    VersionErrorKind::InvalidCharacter(c),
+                    s,
+                    i..i + c.len_utf8(),
+                ));
+            }
+        }
+        let (nums, separators) = serialize_version_str(s, 0, core.len(), true)?;
+        Ok(Version {
+            string: s.to_string(),
+            nums,
+            separators,
+            pre,
+            build,
+            scheme: Scheme::SemVer,
         })
     }
 }
 
 impl Version {
+    /// 区間に条件を一つ加えます。
+    ///
+    /// 条件が区間と矛盾する場合は `None` を返します。
+    /// より緩い条件は取り除かれるので、区間は常に最小の条件で表されます。
     fn insert_to_range_data(
         &self,
         range_data: Option<RangeData>,
         insert_type: VersionRangeInsertType,
     ) -> Option<RangeData> {
-        range_data.map(|mut range_data| match insert_type {
-            VersionRangeInsertType::StrictlyEarlier => {
-                if range_data.exactly_equal.as_ref().is_some_and(|v| v >= self)
-                    || range_data
-                        .later_or_equal
-                        .as_ref()
-                        .is_some_and(|v| v >= self)
-                    || range_data
-                        .strictly_later
+        range_data
+            .map(|mut range_data| match insert_type {
+                VersionRangeInsertType::StrictlyEarlier => {
+                    if range_data.exactly_equal.as_ref().is_some_and(|v| v >= self)
+                        || range_data
+                            .later_or_equal
+                            .as_ref()
+                            .is_some_and(|v| v >= self)
+                        || range_data
+                            .strictly_later
+                            .as_ref()
+                            .is_some_and(|v| v >= self)
+                    {
+                        return None;
+                    }
+                    // より厳しい上限であれば置き換える
+                    if range_data
+                        .earlier_or_equal
                         .as_ref()
-                        .is_some_and(|v| v >= self)
-                {
-                    return None;
-                }
-                if let Some(check_ver) = &range_data.earlier_or_equal {
-                    if check_ver >= self {
+                        .is_none_or(|v| v >= self)
+                        && range_data
+                            .strictly_earlier
+                            .as_ref()
+                            .is_none_or(|v| v > self)
+                    {
                         range_data.earlier_or_equal = None;
                         range_data.strictly_earlier = Some(self.clone());
                     }
+                    Some(range_data)
                 }
-                if let Some(check_ver) = &range_data.strictly_earlier {
-                    if check_ver > self {
-                        range_data.strictly_earlier = Some(self.clone());
+                VersionRangeInsertType::EarlierOrEqual => {
+                    if range_data.exactly_equal.as_ref().is_some_and(|v| v > self)
+                        || range_data.later_or_equal.as_ref().is_some_and(|v| v > self)
+                        || range_data
+                            .strictly_later
+                            .as_ref()
+                            .is_some_and(|v| v >= self)
+                    {
+                        return None;
                     }
-                }
-                Some(range_data)
-            }
-            VersionRangeInsertType::EarlierOrEqual => {
-                if range_data.exactly_equal.as_ref().is_some_and(|v| v > self)
-                    || range_data.later_or_equal.as_ref().is_some_and(|v| v > self)
-                    || range_data.strictly_later.as_ref().is_some_and(|v| v > self)
-                {
-                    return None;
-                }
-                if let Some(check_ver) = &range_data.earlier_or_equal {
-                    if check_ver > self {
+                    if range_data
+                        .earlier_or_equal
+                        .as_ref()
+                        .is_none_or(|v| v > self)
+                        && range_data
+                            .strictly_earlier
+                            .as_ref()
+                            .is_none_or(|v| v > self)
+                    {
+                        range_data.strictly_earlier = None;
                         range_data.earlier_or_equal = Some(self.clone());
                     }
-                } else {
-                    range_data.earlier_or_equal = Some(self.clone());
-                }
-                Some(range_data)
-            }
-            VersionRangeInsertType::ExactlyEqual => {
-                if range_data.exactly_equal.as_ref().is_some_and(|v| v != self) {
-                    return None;
+                    Some(range_data)
                 }
-                range_data.exactly_equal = Some(self.clone());
-                Some(range_data)
-            }
-            VersionRangeInsertType::LaterOrEqual => {
-                if range_data.exactly_equal.as_ref().is_some_and(|v| v < self)
-                    || range_data
-                        .strictly_earlier
-                        .as_ref()
-                        .is_some_and(|v| v < self)
-                {
-                    return None;
+                VersionRangeInsertType::ExactlyEqual => {
+                    if range_data.exactly_equal.as_ref().is_some_and(|v| v != self)
+                        || range_data
+                            .strictly_earlier
+                            .as_ref()
+                            .is_some_and(|v| v <= self)
+                        || range_data
+                            .earlier_or_equal
+                            .as_ref()
+                            .is_some_and(|v| v < self)
+                        || range_data.later_or_equal.as_ref().is_some_and(|v| v > self)
+                        || range_data
+                            .strictly_later
+                            .as_ref()
+                            .is_some_and(|v| v >= self)
+                    {
+                        return None;
+                    }
+                    range_data.exactly_equal = Some(self.clone());
+                    Some(range_data)
                 }
-                if let Some(check_ver) = &range_data.later_or_equal {
-                    if check_ver < self {
+                VersionRangeInsertType::LaterOrEqual => {
+                    if range_data.exactly_equal.as_ref().is_some_and(|v| v < self)
+                        || range_data
+                            .strictly_earlier
+                            .as_ref()
+                            .is_some_and(|v| v <= self)
+                        || range_data
+                            .earlier_or_equal
+                            .as_ref()
+                            .is_some_and(|v| v < self)
+                    {
+                        return None;
+                    }
+                    // より厳しい下限であれば置き換える
+                    if range_data.later_or_equal.as_ref().is_none_or(|v| v < self)
+                        && range_data.strictly_later.as_ref().is_none_or(|v| v < self)
+                    {
+                        range_data.strictly_later = None;
                         range_data.later_or_equal = Some(self.clone());
                     }
-                } else {
-                    range_data.later_or_equal = Some(self.clone());
+                    Some(range_data)
                 }
-                Some(range_data)
-            }
-            VersionRangeInsertType::StrictlyLater => {
-                if range_data.exactly_equal.as_ref().is_some_and(|v| v <= self)
-                    || range_data
-                        .earlier_or_equal
-                        .as_ref()
-                        .is_some_and(|v| v <= self)
-                {
-                    return None;
-                }
-                if let Some(check_ver) = &range_data.later_or_equal {
-                    if check_ver <= self {
-                        range_data.later_or_equal = None;
-                        range_data.strictly_later = Some(self.clone());
+                VersionRangeInsertType::StrictlyLater => {
+                    if range_data.exactly_equal.as_ref().is_some_and(|v| v <= self)
+                        || range_data
+                            .earlier_or_equal
+                            .as_ref()
+                            .is_some_and(|v| v <= self)
+                        || range_data
+                            .strictly_earlier
+                            .as_ref()
+                            .is_some_and(|v| v <= self)
+                    {
+                        return None;
                     }
-                }
-                if let Some(check_ver) = &range_data.strictly_later {
-                    if check_ver < self {
+                    if range_data.later_or_equal.as_ref().is_none_or(|v| v <= self)
+                        && range_data.strictly_later.as_ref().is_none_or(|v| v < self)
+                    {
+                        range_data.later_or_equal = None;
                         range_data.strictly_later = Some(self.clone());
                     }
-                } else {
-                    range_data.strictly_later = Some(self.clone());
+                    Some(range_data)
                 }
-                Some(range_data)
-            }
-        })?
+            })?
+            .map(RangeData::collapsed)
     }
 }
 
@@ -184,25 +579,105 @@ enum VersionRangeInsertType {
     StrictlyLater,
 }
 
-impl PartialOrd for Version {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        let min_len = self.nums.len().min(other.nums.len());
-        for i in 0..min_len {
-            match self.nums[i].cmp(&other.nums[i]) {
-                std::cmp::Ordering::Equal => continue,
-                ord => return Some(ord),
-            }
+impl VersionRangeInsertType {
+    /// 条件を文字列にするときの演算子。
+    ///
+    /// dpkg 方式の範囲では、再び dpkg 方式として解釈されるように `<<` と `>>` を使います。
+    fn symbol(self, scheme: Option<Scheme>) -> &'static str {
+        let dpkg = scheme == Some(Scheme::Dpkg);
+        match self {
+            VersionRangeInsertType::StrictlyEarlier if dpkg => "<<",
+            VersionRangeInsertType::StrictlyEarlier => "<",
+            VersionRangeInsertType::EarlierOrEqual => "<=",
+            VersionRangeInsertType::ExactlyEqual => "==",
+            VersionRangeInsertType::LaterOrEqual => ">=",
+            VersionRangeInsertType::StrictlyLater if dpkg => ">>",
+            VersionRangeInsertType::StrictlyLater => ">",
+        }
+    }
+}
+
+/// バージョンが範囲を満たさなかった理由。
+///
+/// `violated` には、和集合の区間ごとに最初に満たさなかった条件が入ります。
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeViolation {
+    pub version: String,
+    pub violated: Vec<String>,
+}
+
+impl Display for RangeViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.violated.is_empty() {
+            return write!(
+                f,
+                "{} is rejected because the range matches no version",
+                self.version
+            );
         }
-        Some(self.nums.len().cmp(&other.nums.len()))
+        let bounds: Vec<String> = self.violated.iter().map(|b| format!("`{}`", b)).collect();
+        write!(
+            f,
+            "{} does not satisfy {}",
+            self.version,
+            bounds.join(" or ")
+        )
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // 方式が異なるバージョンの大小には意味がないので、方式の順で並べる
+        self.scheme
+            .cmp(&other.scheme)
+            .then_with(|| self.scheme.get().compare(self, other))
     }
 }
 
-#[derive(Clone, Debug, Default)]
+impl Hash for Version {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.scheme.hash(state);
+        self.canonical().hash(state);
+    }
+}
+
+/// バージョンの範囲。
+///
+/// 区間 (`RangeData`) の和集合で、いずれかの区間に含まれるバージョンが範囲に含まれます。
+/// 文字列では区間を `||` で区切り、区間内の条件を `,` で区切ります
+/// (例: `>= 1.2, < 2.0 || >= 3.0`)。
+#[derive(Clone, Debug, PartialEq)]
 pub struct VersionRange {
-    _range_data: Option<RangeData>,
+    ranges: Vec<RangeData>,
+    scheme: Option<Scheme>,
 }
 
-#[derive(Clone, Debug)]
+impl Default for VersionRange {
+    /// すべてのバージョンを含む範囲 (`*`)
+    fn default() -> Self {
+        VersionRange {
+            ranges: vec![RangeData::default()],
+            scheme: None,
+        }
+    }
+}
+
+/// 条件の積で表される一つの区間。
+#[derive(Clone, Debug, Default, PartialEq)]
 struct RangeData {
     strictly_earlier: Option<Version>,
     earlier_or_equal: Option<Version>,
@@ -211,121 +686,673 @@ struct RangeData {
     strictly_later: Option<Version>,
 }
 
-impl FromStr for VersionRange {
-    type Err = String;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut range_data = Some(RangeData {
-            strictly_earlier: None,
-            earlier_or_equal: None,
-            exactly_equal: None,
-            later_or_equal: None,
-            strictly_later: None,
-        });
-
-        for part in s.split(',').map(str::trim) {
-            let parts: Vec<&str> = part.split_whitespace().collect();
-            if parts.len() == 1 {
-                let version_str = parts[0];
-                if version_str == "*" {
-                    continue;
-                } else {
-                    let version = Version::from_str(version_str).unwrap();
-                    range_data = version
-                        .insert_to_range_data(range_data, VersionRangeInsertType::ExactlyEqual);
-                }
-            } else if parts.len() == 2 {
-                let symbol = parts[0];
-                let version_str = parts[1];
-                let version = Version::from_str(version_str).unwrap();
-                let insert_type = match symbol {
-                    ">>" | ">" => VersionRangeInsertType::StrictlyLater,
-                    ">=" => VersionRangeInsertType::LaterOrEqual,
-                    "=" | "==" => VersionRangeInsertType::ExactlyEqual,
-                    "<=" => VersionRangeInsertType::EarlierOrEqual,
-                    "<<" | "<" => VersionRangeInsertType::StrictlyEarlier,
-                    _ => {
-                        return Err(format!("Invalid relation: {}", symbol));
-                    }
-                };
-                range_data = version.insert_to_range_data(range_data, insert_type);
-            } else {
-                return Err(format!("Invalid range format: {}", part));
+/// どのバージョンも含まない範囲の文字列表現
+const EMPTY_RANGE: &str = "none";
+
+/// `sep` で区切った各部分を、前後の空白を除いた文字列と `s` の中での開始位置の組にします。
+fn split_trimmed<'a>(s: &'a str, sep: &str) -> Vec<(usize, &'a str)> {
+    let mut offset = 0;
+    s.split(sep)
+        .map(|part| {
+            let trimmed = part.trim();
+            let start = offset + (part.len() - part.trim_start().len());
+            offset += part.len() + sep.len();
+            (start, trimmed)
+        })
+        .collect()
+}
+
+/// 条件を先頭の演算子とバージョン部分に分けます。
+fn split_operator(part: &str) -> (&str, &str) {
+    let part = part.trim();
+    let op_len = part
+        .find(|c: char| !"<>=!^~".contains(c))
+        .unwrap_or(part.len());
+    (&part[..op_len], part[op_len..].trim())
+}
+
+/// `1.2.*` や `1.x` のようなワイルドカードであれば、固定されている数値部分を返します。
+///
+/// `*` だけの場合は空の列を返します。ワイルドカードでなければ `None` を返します。
+fn wildcard_prefix(version_str: &str) -> Result<Option<Vec<u32>>, VersionErrorKind> {
+    let is_wildcard = |c: &str| matches!(c, "*" | "x" | "X");
+    let components: Vec<&str> = version_str.split('.').collect();
+    let Some(first_wildcard) = components.iter().position(|c| is_wildcard(c)) else {
+        return Ok(None);
+    };
+    if !components[first_wildcard..].iter().all(|c| is_wildcard(c)) {
+        return Err(VersionErrorKind::InvalidWildcard);
+    }
+    components[..first_wildcard]
+        .iter()
+        .map(|c| {
+            c.parse::<u32>()
+                .map_err(|_| VersionErrorKind::ExpectedNumber)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+/// `prefix[index]` を一つ増やし、それより後ろを0にしたバージョンを作ります。
+///
+/// 数値部分は少なくとも3つになるように0で埋めます (例: `[1, 2]`, 0 → `2.0.0`)。
+fn bumped_version(
+    prefix: &[u32],
+    index: usize,
+    scheme: Scheme,
+) -> Result<Version, VersionErrorKind> {
+    let mut nums = prefix[..index].to_vec();
+    let bumped = prefix[index]
+        .checked_add(1)
+        .ok_or(VersionErrorKind::NumberTooLarge)?;
+    nums.push(bumped);
+    padded_version(nums, scheme)
+}
+
+/// 数値部分を少なくとも3つになるように0で埋めたバージョンを作ります。
+///
+/// 作ったバージョンが方式の構文に合わない場合は、その理由を返します。
+fn padded_version(mut nums: Vec<u32>, scheme: Scheme) -> Result<Version, VersionErrorKind> {
+    while nums.len() < 3 {
+        nums.push(0);
+    }
+    let version_str: Vec<String> = nums.iter().map(u32::to_string).collect();
+    Version::parse_with(&version_str.join("."), scheme).map_err(|e| e.kind)
+}
+
+/// 範囲の条件一つ分を、区間に加える条件の列に変換します。
+///
+/// `^`、`~`、`~=`、ワイルドカードは `>=` と `<` の組に展開されます。
+///
+/// * `^1.2.3` → `>= 1.2.3, < 2.0.0` (`^0.2.3` → `< 0.3.0`, `^0.0.3` → `< 0.0.4`)
+/// * `~1.2.3` → `>= 1.2.3, < 1.3.0` (`~1` → `< 2.0.0`)
+/// * `~=1.4.5` → `>= 1.4.5, < 1.5.0` (PEP 440 の互換リリース)
+/// * `1.2.*` → `>= 1.2.0, < 1.3.0`
+fn parse_clause(
+    part: &str,
+    scheme: Scheme,
+) -> Result<Vec<(VersionRangeInsertType, Version)>, VersionError> {
+    let (symbol, version_str) = split_operator(part);
+    let rest = &part[symbol.len()..];
+    let version_start = symbol.len() + rest.len() - rest.trim_start().len();
+    let version_span = version_start..version_start + version_str.len();
+    let error = |kind, span| VersionError::new(kind, part, span);
+    if version_str.is_empty() {
+        return Err(error(VersionErrorKind::MissingVersion, 0..part.len()));
+    }
+    if let Some((i, c)) = version_str.char_indices().find(|(_, c)| c.is_whitespace()) {
+        let i = version_start + i;
+        return Err(error(
+            VersionErrorKind::InvalidCharacter(c),
+            i..i + c.len_utf8(),
+        ));
+    }
+    let in_version = |kind| error(kind, version_span.clone());
+
+    if let Some(prefix) = wildcard_prefix(version_str).map_err(in_version)? {
+        if !matches!(symbol, "" | "=" | "==") {
+            return Err(error(VersionErrorKind::InvalidWildcard, 0..symbol.len()));
+        }
+        if prefix.is_empty() {
+            return Ok(Vec::new());
+        }
+        return Ok(vec![
+            (
+                VersionRangeInsertType::LaterOrEqual,
+                padded_version(prefix.clone(), scheme).map_err(in_version)?,
+            ),
+            (
+                VersionRangeInsertType::StrictlyEarlier,
+                bumped_version(&prefix, prefix.len() - 1, scheme).map_err(in_version)?,
+            ),
+        ]);
+    }
+
+    let version =
+        Version::parse_with(version_str, scheme).map_err(|e| e.within(part, version_start))?;
+    let insert_type = match symbol {
+        ">>" | ">" => VersionRangeInsertType::StrictlyLater,
+        ">=" => VersionRangeInsertType::LaterOrEqual,
+        "" | "=" | "==" => VersionRangeInsertType::ExactlyEqual,
+        "<=" => VersionRangeInsertType::EarlierOrEqual,
+        "<<" | "<" => VersionRangeInsertType::StrictlyEarlier,
+        "^" => {
+            // 最初の0でない部分 (最大で3つ目まで) より上が変わらない範囲
+            let nums = &version.nums;
+            let significant = nums.len().min(3);
+            let index = nums[..significant]
+                .iter()
+                .position(|&n| n != 0)
+                .unwrap_or(significant - 1);
+            let upper = bumped_version(nums, index, scheme).map_err(in_version)?;
+            return Ok(vec![
+                (VersionRangeInsertType::LaterOrEqual, version),
+                (VersionRangeInsertType::StrictlyEarlier, upper),
+            ]);
+        }
+        "~" => {
+            let index = if version.nums.len() >= 2 { 1 } else { 0 };
+            let upper = bumped_version(&version.nums, index, scheme).map_err(in_version)?;
+            return Ok(vec![
+                (VersionRangeInsertType::LaterOrEqual, version),
+                (VersionRangeInsertType::StrictlyEarlier, upper),
+            ]);
+        }
+        "~=" => {
+            // 互換リリースはリリース番号の最後の部分だけが変わってよい
+            let release = pep440::parse(version_str)
+                .map_err(|e| e.within(part, version_start))?
+                .release;
+            if release.len() < 2 {
+                return Err(in_version(VersionErrorKind::TooFewComponents));
             }
+            let release: Vec<u32> = release
+                .iter()
+                .map(|&n| u32::try_from(n))
+                .collect::<Result<_, _>>()
+                .map_err(|_| in_version(VersionErrorKind::NumberTooLarge))?;
+            let upper = bumped_version(&release, release.len() - 2, scheme).map_err(in_version)?;
+            return Ok(vec![
+                (VersionRangeInsertType::LaterOrEqual, version),
+                (VersionRangeInsertType::StrictlyEarlier, upper),
+            ]);
+        }
+        _ => {
+            return Err(error(
+                VersionErrorKind::UnknownOperator(symbol.to_string()),
+                0..symbol.len(),
+            ));
         }
+    };
+    Ok(vec![(insert_type, version)])
+}
 
-        Ok(VersionRange {
-            _range_data: range_data.clone(),
-        })
+impl FromStr for VersionRange {
+    type Err = VersionError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // `pep440: >= 1.0rc1` のように方式の名前で始まっていれば、その方式で解釈する
+        if let Some((name, rest)) = s.split_once(':')
+            && let Ok(scheme) = name.trim().parse::<Scheme>()
+        {
+            return VersionRange::parse_with(rest, scheme).map_err(|e| e.within(s, name.len() + 1));
+        }
+        // ">>" と "<<" は dpkg 固有の演算子なので、使われていれば dpkg 方式で解釈する
+        let scheme = uses_dpkg_operator(s).then_some(Scheme::Dpkg);
+        VersionRange::parse_clauses(s, scheme)
     }
 }
 
+/// dpkg 固有の演算子 (`>>`、`<<`) を使っている範囲の文字列かを返します。
+fn uses_dpkg_operator(s: &str) -> bool {
+    s.split("||")
+        .flat_map(|alternative| alternative.split(','))
+        .any(|part| matches!(split_operator(part).0, ">>" | "<<"))
+}
+
 impl VersionRange {
-    pub fn compare(&self, version: &Version) -> bool {
-        self._range_data.as_ref().is_some_and(|range_data| {
-            if let Some(v) = &range_data.strictly_earlier {
-                if version >= v {
-                    return false;
-                }
-            }
-            if let Some(v) = &range_data.earlier_or_equal {
-                if version > v {
-                    return false;
-                }
+    /// 範囲内のバージョンをすべて指定した方式で解釈し、比較にもその方式を使います。
+    pub fn parse_with(s: &str, scheme: Scheme) -> Result<Self, VersionError> {
+        VersionRange::parse_clauses(s, Some(scheme))
+    }
+
+    fn parse_clauses(s: &str, range_scheme: Option<Scheme>) -> Result<Self, VersionError> {
+        let scheme = range_scheme.unwrap_or_default();
+        let mut ranges = Vec::new();
+
+        for (alternative_start, alternative) in split_trimmed(s, "||") {
+            if alternative == EMPTY_RANGE {
+                continue;
             }
-            if let Some(v) = &range_data.exactly_equal {
-                if version != v {
-                    return false;
+            let mut range_data = RangeData::default();
+            // 矛盾を報告するために、これまでの条件を元の文字列と共に覚えておく
+            let mut clauses: Vec<(&str, Vec<(VersionRangeInsertType, Version)>)> = Vec::new();
+            for (part_start, part) in split_trimmed(alternative, ",") {
+                let start = alternative_start + part_start;
+                let constraints = parse_clause(part, scheme).map_err(|e| e.within(s, start))?;
+                match RangeData::from_constraints(&constraints, Some(range_data)) {
+                    Some(inserted) => range_data = inserted,
+                    None => {
+                        // 区間同士の矛盾は必ず二つの条件の間で起こる
+                        let conflicting = clauses
+                            .iter()
+                            .find(|(_, earlier)| {
+                                RangeData::from_constraints(earlier, Some(RangeData::default()))
+                                    .and_then(|r| {
+                                        RangeData::from_constraints(&constraints, Some(r))
+                                    })
+                                    .is_none()
+                            })
+                            .map_or(part, |(text, _)| text);
+                        return Err(VersionError::new(
+                            VersionErrorKind::Unsatisfiable {
+                                conflicting: conflicting.to_string(),
+                            },
+                            s,
+                            start..start + part.len(),
+                        ));
+                    }
                 }
+                clauses.push((part, constraints));
             }
-            if let Some(v) = &range_data.later_or_equal {
-                if version < v {
-                    return false;
-                }
+            ranges.push(range_data);
+        }
+
+        Ok(VersionRange::normalized(ranges, range_scheme))
+    }
+
+    /// どのバージョンも含まない範囲を返します。
+    pub fn empty() -> Self {
+        VersionRange {
+            ranges: Vec::new(),
+            scheme: None,
+        }
+    }
+
+    /// 区間を下限の順に並べ、重なるか隣接する区間をまとめた範囲を作ります。
+    fn normalized(mut ranges: Vec<RangeData>, scheme: Option<Scheme>) -> Self {
+        ranges.sort_by(RangeData::cmp_lower);
+        let mut merged: Vec<RangeData> = Vec::new();
+        for range_data in ranges {
+            if let Some(last) = merged.last_mut()
+                && let Some(union) = last.merge(&range_data)
+            {
+                *last = union;
+            } else {
+                merged.push(range_data);
             }
-            if let Some(v) = &range_data.strictly_later {
-                if version <= v {
-                    return false;
-                }
+        }
+        VersionRange {
+            ranges: merged,
+            scheme,
+        }
+    }
+
+    /// 範囲内のバージョンを指定した方式で解釈し直します。
+    ///
+    /// 指定した方式で解釈できないバージョンはそのまま残します。
+    fn in_scheme(&self, scheme: Option<Scheme>) -> VersionRange {
+        if self.scheme == scheme {
+            return self.clone();
+        }
+        let target = scheme.unwrap_or_default();
+        let convert = |v: &Option<Version>| {
+            v.as_ref()
+                .map(|v| Version::parse_with(v.as_str(), target).unwrap_or_else(|_| v.clone()))
+        };
+        let ranges = self
+            .ranges
+            .iter()
+            .map(|r| RangeData {
+                strictly_earlier: convert(&r.strictly_earlier),
+                earlier_or_equal: convert(&r.earlier_or_equal),
+                exactly_equal: convert(&r.exactly_equal),
+                later_or_equal: convert(&r.later_or_equal),
+                strictly_later: convert(&r.strictly_later),
+            })
+            .collect();
+        VersionRange::normalized(ranges, scheme)
+    }
+
+    /// 二つの範囲を同じ方式にそろえます。
+    ///
+    /// どちらかに方式が指定されていれば (`self` を優先して) その方式を使います。
+    fn aligned(&self, other: &VersionRange) -> (VersionRange, VersionRange) {
+        let scheme = self.scheme.or(other.scheme);
+        (self.in_scheme(scheme), other.in_scheme(scheme))
+    }
+
+    /// どのバージョンも含まない範囲かを判定します。
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// 両方の範囲に含まれるバージョンの範囲を返します。
+    pub fn intersect(&self, other: &VersionRange) -> VersionRange {
+        let (a, b) = self.aligned(other);
+        let ranges = a
+            .ranges
+            .iter()
+            .flat_map(|x| b.ranges.iter().filter_map(|y| x.intersect(y)))
+            .collect();
+        VersionRange::normalized(ranges, a.scheme)
+    }
+
+    /// バージョンが範囲を満たさない理由を返します。満たす場合は `Ok(())` を返します。
+    ///
+    /// 比較の方式は [`VersionRange::compare`] と同じです。
+    pub fn explain(&self, version: &Version) -> Result<(), RangeViolation> {
+        let scheme = self.scheme.unwrap_or(version.scheme).get();
+        let mut violated = Vec::new();
+        for range_data in &self.ranges {
+            match range_data.violated_bound(version, scheme) {
+                None => return Ok(()),
+                Some((insert_type, bound)) => violated.push(format!(
+                    "{} {}",
+                    insert_type.symbol(self.scheme),
+                    bound.string
+                )),
             }
-            true
+        }
+        Err(RangeViolation {
+            version: version.to_string(),
+            violated,
         })
     }
-}
-impl Display for VersionRange {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self._range_data.is_none() {
-            return write!(f, "*");
+
+    /// どちらかの範囲に含まれるバージョンの範囲を返します。
+    pub fn union(&self, other: &VersionRange) -> VersionRange {
+        let (a, b) = self.aligned(other);
+        let ranges = a.ranges.into_iter().chain(b.ranges).collect();
+        VersionRange::normalized(ranges, a.scheme)
+    }
+
+    /// 範囲に含まれないバージョンの範囲を返します。
+    pub fn complement(&self) -> VersionRange {
+        let any = VersionRange {
+            scheme: self.scheme,
+            ..VersionRange::default()
+        };
+        // 和集合の補集合は、各区間の補集合の共通部分
+        self.ranges.iter().fold(any, |result, range_data| {
+            result.intersect(&VersionRange {
+                ranges: range_data.complement(),
+                scheme: self.scheme,
+            })
+        })
+    }
+
+    /// この範囲のバージョンがすべて `other` にも含まれるかを判定します。
+    pub fn is_subset_of(&self, other: &VersionRange) -> bool {
+        self.intersect(&other.complement()).is_empty()
+    }
+
+    /// 二つの範囲に共通するバージョンがないかを判定します。
+    pub fn is_disjoint(&self, other: &VersionRange) -> bool {
+        self.intersect(other).is_empty()
+    }
+
+    /// バージョンが範囲に含まれるかを判定します。
+    ///
+    /// 範囲に方式が指定されていればその方式で、
+    /// そうでなければ判定するバージョン自身の方式で比較します。
+    /// 集合としての判定なので、プレリリースも区別せずに扱います
+    /// (依存関係の照合には [`VersionRange::matches`] を使います)。
+    pub fn compare(&self, version: &Version) -> bool {
+        self.compare_with(version, self.scheme.unwrap_or(version.scheme))
+    }
+
+    /// 指定した方式の規則で、バージョンが範囲に含まれるかを判定します。
+    pub fn compare_with(&self, version: &Version, scheme: Scheme) -> bool {
+        let scheme = scheme.get();
+        self.ranges
+            .iter()
+            .any(|range_data| range_data.contains(version, scheme))
+    }
+
+    /// プレリリースの扱いを `policy` に従わせて、バージョンが範囲を満たすかを判定します。
+    ///
+    /// 方式の選び方は [`VersionRange::compare`] と同じです。
+    ///
+    /// # 引数
+    ///
+    /// * `version` - 判定するバージョン。
+    /// * `policy` - プレリリースを受け入れる条件。
+    ///
+    /// # 戻り値
+    ///
+    /// 範囲に含まれ、プレリリースであれば `policy` にも合う場合に `true`。
+    pub fn matches(&self, version: &Version, policy: PrereleasePolicy) -> bool {
+        self.matches_with(version, self.scheme.unwrap_or(version.scheme), policy)
+    }
+
+    /// 指定した方式の規則で、プレリリースの扱いを `policy` に従わせて判定します。
+    pub fn matches_with(
+        &self,
+        version: &Version,
+        scheme: Scheme,
+        policy: PrereleasePolicy,
+    ) -> bool {
+        let rules = scheme.get();
+        if policy == PrereleasePolicy::Include || !rules.is_prerelease(version) {
+            return self.compare_with(version, scheme);
+        }
+        if policy == PrereleasePolicy::Exclude {
+            return false;
         }
-        write!(f, "")
+        // 同じリリースのプレリリースを条件に書いた区間でだけ受け入れる
+        let release = rules.release(version);
+        self.ranges.iter().any(|range_data| {
+            range_data.contains(version, rules)
+                && range_data
+                    .constraints()
+                    .into_iter()
+                    .any(|(_, bound)| rules.is_prerelease(bound) && rules.release(bound) == release)
+        })
     }
 }
 
-impl Display for RangeData {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut parts = Vec::new();
-        if let Some(v) = &self.strictly_earlier {
-            parts.push(format!("< {}", v.string));
+impl RangeData {
+    /// 一点に定まる区間を `== x` にまとめます。
+    fn collapsed(self) -> RangeData {
+        let exactly_equal = match (&self.later_or_equal, &self.earlier_or_equal) {
+            (Some(lower), Some(upper)) if lower == upper => Some(lower.clone()),
+            _ => self.exactly_equal.clone(),
+        };
+        match exactly_equal {
+            Some(v) => RangeData {
+                exactly_equal: Some(v),
+                ..RangeData::default()
+            },
+            None => self,
+        }
+    }
+
+    /// 区間に条件の列を順に加えます。
+    fn from_constraints(
+        constraints: &[(VersionRangeInsertType, Version)],
+        range_data: Option<RangeData>,
+    ) -> Option<RangeData> {
+        constraints
+            .iter()
+            .fold(range_data, |range_data, (insert_type, version)| {
+                version.insert_to_range_data(range_data, *insert_type)
+            })
+    }
+
+    /// この区間を表す条件の列を、下限、上限の順に返します。
+    fn constraints(&self) -> Vec<(VersionRangeInsertType, &Version)> {
+        [
+            (VersionRangeInsertType::ExactlyEqual, &self.exactly_equal),
+            (VersionRangeInsertType::LaterOrEqual, &self.later_or_equal),
+            (VersionRangeInsertType::StrictlyLater, &self.strictly_later),
+            (
+                VersionRangeInsertType::EarlierOrEqual,
+                &self.earlier_or_equal,
+            ),
+            (
+                VersionRangeInsertType::StrictlyEarlier,
+                &self.strictly_earlier,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(insert_type, v)| v.as_ref().map(|v| (insert_type, v)))
+        .collect()
+    }
+
+    /// 二つの区間の共通部分を返します。共通部分がなければ `None` を返します。
+    fn intersect(&self, other: &RangeData) -> Option<RangeData> {
+        other
+            .constraints()
+            .into_iter()
+            .try_fold(self.clone(), |range_data, (insert_type, v)| {
+                v.insert_to_range_data(Some(range_data), insert_type)
+            })
+    }
+
+    /// 区間の補集合を、区間の列 (和集合) で返します。
+    fn complement(&self) -> Vec<RangeData> {
+        let below = |insert_type, v: &Version| {
+            v.insert_to_range_data(Some(RangeData::default()), insert_type)
+        };
+        let mut ranges = Vec::new();
+        // 下限より下
+        match self.lower() {
+            Some((v, true)) => ranges.extend(below(VersionRangeInsertType::StrictlyEarlier, v)),
+            Some((v, false)) => ranges.extend(below(VersionRangeInsertType::EarlierOrEqual, v)),
+            None => {}
         }
-        if let Some(v) = &self.earlier_or_equal {
-            parts.push(format!("<= {}", v.string));
+        // 上限より上
+        match self.upper() {
+            Some((v, true)) => ranges.extend(below(VersionRangeInsertType::StrictlyLater, v)),
+            Some((v, false)) => ranges.extend(below(VersionRangeInsertType::LaterOrEqual, v)),
+            None => {}
         }
+        ranges
+    }
+
+    /// 下限と、下限自身を含むかどうか。
+    fn lower(&self) -> Option<(&Version, bool)> {
+        if let Some(v) = &self.exactly_equal {
+            Some((v, true))
+        } else if let Some(v) = &self.later_or_equal {
+            Some((v, true))
+        } else {
+            self.strictly_later.as_ref().map(|v| (v, false))
+        }
+    }
+
+    /// 上限と、上限自身を含むかどうか。
+    fn upper(&self) -> Option<(&Version, bool)> {
         if let Some(v) = &self.exactly_equal {
-            parts.push(format!("== {}", v.string));
+            Some((v, true))
+        } else if let Some(v) = &self.earlier_or_equal {
+            Some((v, true))
+        } else {
+            self.strictly_earlier.as_ref().map(|v| (v, false))
         }
-        if let Some(v) = &self.later_or_equal {
-            parts.push(format!(">= {}", v.string));
+    }
+
+    /// 下限の小さい順に並べるための比較。下限のない区間が先頭に来ます。
+    fn cmp_lower(&self, other: &RangeData) -> Ordering {
+        match (self.lower(), other.lower()) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            // 同じ版なら、その版を含む方が先
+            (Some((a, a_inc)), Some((b, b_inc))) => a.cmp(b).then(b_inc.cmp(&a_inc)),
         }
-        if let Some(v) = &self.strictly_later {
-            parts.push(format!("> {}", v.string));
+    }
+
+    /// 下限が `self` 以上の区間 `next` と重なるか隣接していれば、二つを合わせた区間を返します。
+    fn merge(&self, next: &RangeData) -> Option<RangeData> {
+        let connected = match (self.upper(), next.lower()) {
+            (None, _) | (_, None) => true,
+            (Some((upper, upper_inc)), Some((lower, lower_inc))) => {
+                upper > lower || (upper == lower && (upper_inc || lower_inc))
+            }
+        };
+        if !connected {
+            return None;
         }
+        // 上限はより大きい方 (同じ版なら含む方) を採る
+        let upper = match (self.upper(), next.upper()) {
+            (None, _) | (_, None) => None,
+            (Some(a), Some(b)) => Some(if a.0 > b.0 || (a.0 == b.0 && a.1) {
+                a
+            } else {
+                b
+            }),
+        };
+        let mut merged = Some(RangeData::default());
+        if let Some((v, inclusive)) = self.lower() {
+            let insert_type = if inclusive {
+                VersionRangeInsertType::LaterOrEqual
+            } else {
+                VersionRangeInsertType::StrictlyLater
+            };
+            merged = v.insert_to_range_data(merged, insert_type);
+        }
+        if let Some((v, inclusive)) = upper {
+            let insert_type = if inclusive {
+                VersionRangeInsertType::EarlierOrEqual
+            } else {
+                VersionRangeInsertType::StrictlyEarlier
+            };
+            merged = v.insert_to_range_data(merged, insert_type);
+        }
+        merged
+    }
+
+    /// バージョンがこの区間に含まれるかを判定します。
+    fn contains(&self, version: &Version, scheme: &dyn VersionScheme) -> bool {
+        self.violated_bound(version, scheme).is_none()
+    }
+
+    /// バージョンが満たさない最初の条件を返します。すべて満たせば `None` を返します。
+    fn violated_bound(
+        &self,
+        version: &Version,
+        scheme: &dyn VersionScheme,
+    ) -> Option<(VersionRangeInsertType, &Version)> {
+        self.constraints().into_iter().find(|(insert_type, bound)| {
+            let ord = scheme.compare(version, bound);
+            match insert_type {
+                VersionRangeInsertType::StrictlyEarlier => ord.is_ge(),
+                VersionRangeInsertType::EarlierOrEqual => ord.is_gt(),
+                VersionRangeInsertType::ExactlyEqual => ord.is_ne(),
+                VersionRangeInsertType::LaterOrEqual => ord.is_lt(),
+                VersionRangeInsertType::StrictlyLater => ord.is_le(),
+            }
+        })
+    }
+
+    /// 区間を文字列にします。
+    fn to_string_with(&self, scheme: Option<Scheme>) -> String {
+        let parts: Vec<String> = self
+            .constraints()
+            .into_iter()
+            .map(|(insert_type, v)| format!("{} {}", insert_type.symbol(scheme), v.string))
+            .collect();
         if parts.is_empty() {
-            write!(f, "*")
+            "*".to_string()
         } else {
-            write!(f, "{}", parts.join(", "))
+            parts.join(", ")
         }
     }
 }
 
+/// 正規化した構文で出力します。
+///
+/// 区間は下限の順に `||` で区切り、各区間は下限、上限の順に書きます
+/// (例: `>= 1.2, < 2.0 || >= 3.0`)。出力を解釈し直すと元と等しい範囲になります。
+/// `parse_with` で指定した方式は `pep440: >= 1.0rc1` のように名前を前に付けて残します。
+/// ただし dpkg 方式は `<<` と `>>` が残る場合、それだけで復元されるので付けません。
+impl Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let body = if self.ranges.is_empty() {
+            EMPTY_RANGE.to_string()
+        } else {
+            let alternatives: Vec<String> = self
+                .ranges
+                .iter()
+                .map(|range_data| range_data.to_string_with(self.scheme))
+                .collect();
+            alternatives.join(" || ")
+        };
+        match self.scheme {
+            Some(Scheme::Dpkg) if uses_dpkg_operator(&body) => write!(f, "{}", body),
+            Some(scheme) => write!(f, "{}: {}", scheme, body),
+            None => write!(f, "{}", body),
+        }
+    }
+}
+
+impl Display for RangeData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_string_with(None))
+    }
+}
+
 pub fn test() {
     let version1 = Version::from_str("1.2.3").unwrap();
     let version2 = Version::from_str("1.2.2-build-4").unwrap();
@@ -337,3 +1364,880 @@ pub fn test() {
     println!("Range1: {:?}", &range1);
     println!("In Range1, version1: {}", range1.compare(&version1));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        Version::from_str(s).unwrap()
+    }
+
+    fn dpkg(s: &str) -> Version {
+        Version::parse_with(s, Scheme::Dpkg).unwrap()
+    }
+
+    #[test]
+    fn semver_spec_precedence() {
+        // SemVer 2.0 仕様 11 節の例
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "2.0.0",
+            "2.1.0",
+            "2.1.1",
+        ];
+        for (i, a) in ordered.iter().enumerate() {
+            for (j, b) in ordered.iter().enumerate() {
+                assert_eq!(v(a).partial_cmp(&v(b)), Some(i.cmp(&j)), "{} <=> {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn pre_release_is_lower_than_release() {
+        assert!(v("1.0.0-rc1") < v("1.0.0"));
+        assert!(v("1.0.0-0.3.7") < v("1.0.0"));
+        assert!(v("1.0.0-x.7.z.92") < v("1.0.0"));
+        assert!(v("1.0.0-x-y-z.--") < v("1.0.0"));
+        assert!(v("1.2.2-build-4") < v("1.2.2"));
+        assert!(v("1.2.2-build-4") < v("1.2.2.4"));
+    }
+
+    #[test]
+    fn numeric_identifiers_compare_numerically() {
+        assert!(v("1.0.0-2") < v("1.0.0-10"));
+        assert!(v("1.0.0-alpha.2") < v("1.0.0-alpha.10"));
+        // 数値の識別子は英数字の識別子より低い
+        assert!(v("1.0.0-999") < v("1.0.0-a"));
+        assert!(v("1.0.0-alpha.1") < v("1.0.0-alpha.a"));
+        // 英数字は ASCII 順
+        assert!(v("1.0.0-Beta") < v("1.0.0-alpha"));
+        assert!(v("1.0.0-rc.1") < v("1.0.0-rc-1"));
+    }
+
+    #[test]
+    fn build_metadata_is_ignored_for_precedence() {
+        let pairs = [
+            ("1.0.0-alpha+001", "1.0.0-alpha"),
+            ("1.0.0+20130313144700", "1.0.0"),
+            ("1.0.0-beta+exp.sha.5114f85", "1.0.0-beta"),
+            ("1.0.0+21AF26D3----117B344092BD", "1.0.0"),
+            ("1.0.0+build.1", "1.0.0+build.2"),
+        ];
+        for (a, b) in pairs {
+            assert_eq!(
+                v(a).partial_cmp(&v(b)),
+                Some(Ordering::Equal),
+                "{} <=> {}",
+                a,
+                b
+            );
+        }
+        assert!(v("1.0.0-alpha+zzz") < v("1.0.0-beta+aaa"));
+        assert_eq!(
+            v("1.0.0-beta+exp.sha.5114f85").build,
+            ["exp", "sha", "5114f85"]
+        );
+    }
+
+    #[test]
+    fn valid_pre_release_and_build() {
+        for s in [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-0.3.7",
+            "1.0.0-x.7.z.92",
+            "1.0.0-x-y-z.--",
+            "1.0.0-alpha+001",
+            "1.0.0+20130313144700",
+            "1.0.0-beta+exp.sha.5114f85",
+            "1.0.0+21AF26D3----117B344092BD",
+            "1.0.0-0A.is.legal",
+            "1.0.0+0.build.1-rc.10000aaa-kk-0.1",
+        ] {
+            assert!(Version::from_str(s).is_ok(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn invalid_pre_release_and_build() {
+        for s in [
+            "1.0.0-",
+            "1.0.0+",
+            "1.0.0-alpha..1",
+            "1.0.0-alpha.",
+            "1.0.0+build..1",
+            "1.0.0-01",
+            "1.0.0-alpha.01",
+            "1.0.0-alpha_beta",
+            "1.0.0+build+1",
+            "1.0.0-99999999999999999999999",
+        ] {
+            assert!(Version::from_str(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn display_keeps_original_string() {
+        assert_eq!(v("1.0.0-rc.1+build.5").to_string(), "1.0.0-rc.1+build.5");
+    }
+
+    #[test]
+    fn range_respects_pre_release() {
+        let range = VersionRange::from_str(">= 1.0.0, <= 2.0.0").unwrap();
+        assert!(!range.compare(&v("1.0.0-rc1")));
+        assert!(range.compare(&v("1.0.0")));
+        assert!(range.compare(&v("2.0.0-beta")));
+        assert!(range.compare(&v("2.0.0+build.7")));
+    }
+
+    #[test]
+    fn dpkg_scheme_is_selectable() {
+        let a = Version::parse_with("1:2.3~rc1-4ubuntu1", Scheme::Dpkg).unwrap();
+        let b = Version::parse_with("1:2.3-1", Scheme::Dpkg).unwrap();
+        assert_eq!(a.scheme(), Scheme::Dpkg);
+        assert!(a < b);
+        assert!(Version::parse_with("a1.0", Scheme::Dpkg).is_err());
+        // SemVer では "~" を使えない
+        assert_eq!(
+            Version::from_str("2.3~rc1").unwrap_err().kind,
+            VersionErrorKind::InvalidCharacter('~')
+        );
+        assert!(dpkg("2.3~rc1") < dpkg("2.3"));
+    }
+
+    #[test]
+    fn dpkg_operators_use_dpkg_ordering() {
+        let range = VersionRange::from_str(">> 1.0~rc1, << 2.0").unwrap();
+        assert!(range.compare(&v("1.0")));
+        assert!(range.compare(&dpkg("2.0~beta1")));
+        assert!(!range.compare(&dpkg("1.0~rc1")));
+        assert!(!range.compare(&v("2.0")));
+        assert!(!range.compare(&v("2.0+b1")));
+
+        let range = VersionRange::from_str(">> 1:2.3~rc1-4, << 1:2.3-1").unwrap();
+        assert!(range.compare(&dpkg("1:2.3~rc1-4ubuntu1")));
+        assert!(!range.compare(&v("2.3")));
+    }
+
+    #[test]
+    fn explicit_dpkg_range() {
+        let range = VersionRange::parse_with(">= 1.0~, < 1.0", Scheme::Dpkg).unwrap();
+        assert!(range.compare(&dpkg("1.0~rc1")));
+        assert!(!range.compare(&v("1.0")));
+    }
+
+    #[test]
+    fn tighter_bounds_replace_looser_ones() {
+        let range = VersionRange::from_str("< 2.0").unwrap();
+        assert!(!range.compare(&v("2.0")));
+        let range = VersionRange::from_str("<= 3.0, < 2.0").unwrap();
+        assert!(!range.compare(&v("2.5")));
+        let range = VersionRange::from_str("> 1.0, >= 2.0").unwrap();
+        assert!(!range.compare(&v("1.5")));
+        assert!(range.compare(&v("2.0")));
+        assert!(VersionRange::from_str(">= 2.0, <= 1.0").is_err());
+    }
+
+    #[test]
+    fn range_dispatches_on_candidate_scheme() {
+        let range = VersionRange::from_str(">= 1.0").unwrap();
+        assert!(!range.compare(&Version::parse_with("1.0rc1", Scheme::Pep440).unwrap()));
+        assert!(range.compare(&Version::parse_with("1.0.post1", Scheme::Pep440).unwrap()));
+        // 範囲に方式が指定されていれば、そちらが優先される
+        let rc = Version::parse_with("1.0rc1", Scheme::Pep440).unwrap();
+        let range = VersionRange::parse_with(">= 1.0", Scheme::Rpm).unwrap();
+        assert!(range.compare(&rc));
+        assert!(!range.compare_with(&rc, Scheme::Pep440));
+    }
+
+    #[test]
+    fn prerelease_policy() {
+        use PrereleasePolicy::*;
+        let range = VersionRange::from_str(">= 1.0").unwrap();
+        assert!(range.compare(&v("2.0.0-beta")));
+        assert!(!range.matches(&v("2.0.0-beta"), SameRelease));
+        assert!(range.matches(&v("2.0.0-beta"), Include));
+        // リリースは方針に関係なく判定される
+        for policy in [SameRelease, Include, Exclude] {
+            assert!(range.matches(&v("2.0.0"), policy));
+            assert!(!range.matches(&v("0.9.0"), policy));
+        }
+
+        let range = VersionRange::from_str(">= 2.0.0-alpha").unwrap();
+        assert!(range.matches(&v("2.0.0-beta"), SameRelease));
+        assert!(!range.matches(&v("2.1.0-beta"), SameRelease));
+        assert!(!range.matches(&v("2.0.0-beta"), Exclude));
+
+        let range = VersionRange::from_str("^1.2.3-beta.2").unwrap();
+        assert!(range.matches(&v("1.2.3-beta.4"), SameRelease));
+        assert!(!range.matches(&v("1.2.3-beta.1"), SameRelease));
+        assert!(!range.matches(&v("1.2.4-beta"), SameRelease));
+        assert!(range.matches(&v("1.2.4"), SameRelease));
+
+        // 区間ごとに判定する
+        let range = VersionRange::from_str("< 1.0 || >= 2.0.0-rc.1").unwrap();
+        assert!(range.matches(&v("2.0.0-rc.2"), SameRelease));
+        assert!(!range.matches(&v("0.9.0-rc.1"), SameRelease));
+    }
+
+    #[test]
+    fn prerelease_policy_follows_scheme() {
+        use PrereleasePolicy::*;
+        let pep440 = |s| Version::parse_with(s, Scheme::Pep440).unwrap();
+        let range = VersionRange::parse_with(">= 1.0", Scheme::Pep440).unwrap();
+        assert!(!range.matches(&pep440("2.0rc1"), SameRelease));
+        assert!(!range.matches(&pep440("2.0.dev1"), SameRelease));
+        assert!(range.matches(&pep440("2.0.post1"), SameRelease));
+        let range = VersionRange::parse_with(">= 2.0b1", Scheme::Pep440).unwrap();
+        assert!(range.matches(&pep440("2.0.0rc1"), SameRelease));
+
+        let dpkg = |s| Version::parse_with(s, Scheme::Dpkg).unwrap();
+        let range = VersionRange::parse_with(">= 1.0", Scheme::Dpkg).unwrap();
+        assert!(!range.matches(&dpkg("2.0~rc1-1"), SameRelease));
+        assert!(range.matches(&dpkg("2.0-1"), SameRelease));
+        let range = VersionRange::parse_with(">= 2.0~beta1", Scheme::Dpkg).unwrap();
+        assert!(range.matches(&dpkg("2.0~rc1-1"), SameRelease));
+    }
+
+    #[test]
+    fn prerelease_policy_names() {
+        for policy in [
+            PrereleasePolicy::SameRelease,
+            PrereleasePolicy::Include,
+            PrereleasePolicy::Exclude,
+        ] {
+            assert_eq!(policy.to_string().parse::<PrereleasePolicy>(), Ok(policy));
+        }
+        assert!("sometimes".parse::<PrereleasePolicy>().is_err());
+    }
+
+    fn hash_of(version: &Version) -> u64 {
+        use std::hash::DefaultHasher;
+        let mut hasher = DefaultHasher::new();
+        version.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn samples() -> Vec<Version> {
+        let semver = [
+            "1",
+            "1.0",
+            "1.0.0",
+            "v1.0.0",
+            "1.0.0+build",
+            "1.0.0-rc.1",
+            "1.0.0-rc.1+x",
+            "1.0.0-rc.2",
+            "1.0.1",
+            "1.2.2-build-4",
+            "1.2.2.4",
+            "2.0",
+        ];
+        let dpkg = [
+            "1.0", "0:1.0-0", "1.0-1", "1.0~rc1", "1:0.5", "1.0a", "1.0a0", "1.00",
+        ];
+        let rpm = [
+            "1.0", "1_0", "1.0-1", "1.0~rc1", "1.0^git1", "0:1.0", "1.0.a", "1.0a",
+        ];
+        let pep440 = [
+            "1.0",
+            "1.0.0",
+            "1.0rc1",
+            "1.0.post1",
+            "1.0.dev0",
+            "1!0.1",
+            "1.0+local",
+        ];
+        let calver = [
+            "2026.10",
+            "2026.10.0",
+            "2026.10.17",
+            "2026.10.17-dev.1",
+            "24.04",
+        ];
+        let mut versions = Vec::new();
+        for (scheme, list) in [
+            (Scheme::SemVer, &semver[..]),
+            (Scheme::Dpkg, &dpkg[..]),
+            (Scheme::Rpm, &rpm[..]),
+            (Scheme::Pep440, &pep440[..]),
+            (Scheme::CalVer, &calver[..]),
+        ] {
+            for s in list {
+                versions.push(Version::parse_with(s, scheme).unwrap());
+            }
+        }
+        versions
+    }
+
+    #[test]
+    fn equality_is_canonical() {
+        assert_eq!(v("1.0"), v("1.0.0"));
+        assert_eq!(v("v1.0.0"), v("1"));
+        assert_eq!(v("1.0.0+a"), v("1.0.0+b"));
+        assert_ne!(v("1.0.0-rc.1"), v("1.0.0"));
+        assert_eq!(v("1.0").canonical(), "1.0.0");
+        // 文字列としては同じでも、方式が異なれば別のバージョン
+        assert_ne!(v("1.0"), Version::parse_with("1.0", Scheme::Dpkg).unwrap());
+    }
+
+    #[test]
+    fn eq_ord_hash_laws() {
+        let versions = samples();
+        for a in &versions {
+            assert_eq!(a.cmp(a), Ordering::Equal, "{}", a);
+            for b in &versions {
+                let ord = a.cmp(b);
+                assert_eq!(b.cmp(a), ord.reverse(), "{} <=> {}", a, b);
+                assert_eq!(a == b, ord == Ordering::Equal, "{} == {}", a, b);
+                assert_eq!(a.partial_cmp(b), Some(ord), "{} <=> {}", a, b);
+                let same_canonical = a.scheme() == b.scheme() && a.canonical() == b.canonical();
+                assert_eq!(a == b, same_canonical, "{} / {}", a, b);
+                if a == b {
+                    assert_eq!(hash_of(a), hash_of(b), "{} / {}", a, b);
+                }
+                for c in &versions {
+                    if a <= b && b <= c {
+                        assert!(a <= c, "{} <= {} <= {}", a, b, c);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn usable_as_map_key() {
+        use std::collections::{BTreeMap, HashMap};
+        let mut btree = BTreeMap::new();
+        let mut hash = HashMap::new();
+        for (i, s) in ["1.0", "1.0.0", "1", "2.0", "1.0.0-rc.1"]
+            .iter()
+            .enumerate()
+        {
+            btree.insert(v(s), i);
+            hash.insert(v(s), i);
+        }
+        assert_eq!(btree.len(), 3);
+        assert_eq!(hash.len(), 3);
+        assert_eq!(btree[&v("1.0.0")], 2);
+        assert_eq!(hash[&v("1")], 2);
+        let keys: Vec<String> = btree.keys().map(Version::canonical).collect();
+        assert_eq!(keys, ["1.0.0-rc.1", "1.0.0", "2.0.0"]);
+    }
+
+    #[test]
+    fn exactly_equal_uses_canonical_equality() {
+        let range = VersionRange::from_str("= 1.0, == 1.0.0").unwrap();
+        assert!(range.compare(&v("1")));
+        assert!(range.compare(&v("1.0.0+build.1")));
+        assert!(!range.compare(&v("1.0.1")));
+    }
+
+    #[test]
+    fn disjunctive_ranges() {
+        let range = VersionRange::from_str(">= 1.2, < 2.0 || >= 3.0").unwrap();
+        assert!(!range.compare(&v("1.1")));
+        assert!(range.compare(&v("1.2")));
+        assert!(range.compare(&v("1.9.9")));
+        assert!(!range.compare(&v("2.0")));
+        assert!(!range.compare(&v("2.5")));
+        assert!(range.compare(&v("3.0")));
+        assert!(range.compare(&v("10.0")));
+
+        let range = VersionRange::from_str("1.0 || 1.2 || >> 2.0").unwrap();
+        assert_eq!(range.scheme, Some(Scheme::Dpkg));
+        assert!(range.compare(&v("1.2")));
+        assert!(!range.compare(&v("1.1")));
+        assert!(range.compare(&v("2.0+b1")));
+    }
+
+    #[test]
+    fn unsatisfiable_ranges_name_conflicting_clauses() {
+        // 矛盾した条件の文字列と、矛盾の相手
+        let unsatisfiable = |s: &str| {
+            let error = VersionRange::from_str(s).unwrap_err();
+            let VersionErrorKind::Unsatisfiable { conflicting } = &error.kind else {
+                panic!("{}: {}", s, error);
+            };
+            (error.fragment().to_string(), conflicting.clone())
+        };
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+        assert_eq!(unsatisfiable("> 2.0, < 1.0"), pair("< 1.0", "> 2.0"));
+        assert_eq!(
+            unsatisfiable(">= 0.5, > 2.0, < 3.0, <= 1.0"),
+            pair("<= 1.0", "> 2.0")
+        );
+        assert_eq!(unsatisfiable("== 1.5 || ^1.2, ~1.0"), pair("~1.0", "^1.2"));
+        assert_eq!(unsatisfiable("1.0, 1.0.1"), pair("1.0.1", "1.0"));
+        assert_eq!(
+            VersionRange::from_str("> 2.0, < 1.0")
+                .unwrap_err()
+                .to_string(),
+            "`< 1.0` conflicts with `> 2.0`: no version can satisfy both"
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_failing_fragment() {
+        let error = |s: &str| VersionRange::from_str(s).unwrap_err();
+        let cases = [
+            (
+                ">= 0.5, != 1.0",
+                VersionErrorKind::UnknownOperator("!=".to_string()),
+                "!=",
+            ),
+            (">= 1.0, < 2.0-rc.01", VersionErrorKind::LeadingZero, "01"),
+            (
+                "^1.2 || >= 1.0-b@d",
+                VersionErrorKind::InvalidCharacter('@'),
+                "@",
+            ),
+            (">= 1.0, >=", VersionErrorKind::MissingVersion, ">="),
+            ("1.*.3", VersionErrorKind::InvalidWildcard, "1.*.3"),
+            (">= 1.*", VersionErrorKind::InvalidWildcard, ">="),
+            ("~=1", VersionErrorKind::TooFewComponents, "1"),
+            ("1.0 ||  ^a", VersionErrorKind::InvalidCharacter('a'), "a"),
+        ];
+        for (input, kind, fragment) in cases {
+            let error = error(input);
+            assert_eq!(error.input, input);
+            assert_eq!(error.kind, kind, "{}", input);
+            assert_eq!(error.fragment(), fragment, "{}", input);
+        }
+        assert_eq!(error(">> 1.0, << 1:x").span, 13..14);
+        assert_eq!(
+            error(">= 0.5, != 1.0").to_string(),
+            "unknown operator `!=` at 8..10 in `>= 0.5, != 1.0`"
+        );
+    }
+
+    #[test]
+    fn version_errors_have_spans() {
+        let error = Version::from_str("1.2.3-rc..1").unwrap_err();
+        assert_eq!(error.kind, VersionErrorKind::EmptyComponent);
+        assert_eq!(error.span, 9..9);
+        let error = Version::from_str("1.2.3+build.ü").unwrap_err();
+        assert_eq!(error.kind, VersionErrorKind::InvalidCharacter('ü'));
+        assert_eq!(error.fragment(), "ü");
+        assert_eq!(
+            Version::from_str("").unwrap_err().kind,
+            VersionErrorKind::Empty
+        );
+    }
+
+    #[test]
+    fn overflowing_numbers_are_rejected() {
+        let cases = [
+            ("1.99999999999", Scheme::SemVer, 2..13),
+            ("4294967296.1", Scheme::SemVer, 0..10),
+            ("v1.2.99999999999-rc.1", Scheme::SemVer, 5..16),
+            ("2024.99999999999", Scheme::CalVer, 5..16),
+        ];
+        for (input, scheme, span) in cases {
+            let error = Version::parse_with(input, scheme).unwrap_err();
+            assert_eq!(error.kind, VersionErrorKind::NumberTooLarge, "{}", input);
+            assert_eq!(error.span, span, "{}", input);
+        }
+        assert_eq!(v("4294967295.0").nums, [u32::MAX, 0]);
+    }
+
+    #[test]
+    fn string_schemes_accept_long_numbers() {
+        // 文字列のまま比べる方式では、日時などの長い数字も使える
+        let cases = [
+            ("1.0+20130313144700", Scheme::Pep440),
+            ("1.0+git20230101123456-1", Scheme::Dpkg),
+            ("1.0^git20230101123456", Scheme::Rpm),
+        ];
+        for (input, scheme) in cases {
+            let version = Version::parse_with(input, scheme).unwrap();
+            assert_eq!(version.nums.last(), Some(&u32::MAX), "{}", input);
+        }
+        assert!(
+            Version::parse_with("1.0^git20230101123456", Scheme::Rpm).unwrap()
+                > Version::parse_with("1.0^git20230101123455", Scheme::Rpm).unwrap()
+        );
+    }
+
+    #[test]
+    fn stray_characters_are_rejected() {
+        let cases = [
+            ("abc1def", 'a', 0..1),
+            ("1.2a3", 'a', 3..4),
+            ("1.0rc1", 'r', 3..4),
+            ("1:2.3-1", ':', 1..2),
+            ("1_2_3", '_', 1..2),
+            ("1.2.3x-rc.1", 'x', 5..6),
+            ("v1.2.3~", '~', 6..7),
+        ];
+        for (input, c, span) in cases {
+            let error = Version::from_str(input).unwrap_err();
+            assert_eq!(
+                error.kind,
+                VersionErrorKind::InvalidCharacter(c),
+                "{}",
+                input
+            );
+            assert_eq!(error.span, span, "{}", input);
+        }
+        assert_eq!(
+            Version::from_str("1..2").unwrap_err().kind,
+            VersionErrorKind::EmptyComponent
+        );
+        assert_eq!(
+            Version::from_str("v-rc.1").unwrap_err().kind,
+            VersionErrorKind::Malformed("version has no numeric component")
+        );
+        assert_eq!(v("v1.2.3").nums, [1, 2, 3]);
+        assert_eq!(v("V2").nums, [2]);
+    }
+
+    #[test]
+    fn explain_reports_violated_bounds() {
+        let range = r(">= 1.2, < 2.0 || >= 3.0");
+        assert_eq!(range.explain(&v("1.5")), Ok(()));
+        let violation = range.explain(&v("2.5")).unwrap_err();
+        assert_eq!(violation.violated, ["< 2.0", ">= 3.0"]);
+        assert_eq!(
+            violation.to_string(),
+            "2.5 does not satisfy `< 2.0` or `>= 3.0`"
+        );
+        assert_eq!(
+            r("^1.2").explain(&v("1.0")).unwrap_err().violated,
+            [">= 1.2"]
+        );
+        assert_eq!(
+            r(">> 1.0").explain(&dpkg("1.0~rc1")).unwrap_err().violated,
+            [">> 1.0"]
+        );
+        assert!(r("none").explain(&v("1.0")).is_err());
+        for s in ["1.0", "1.3", "2.0", "3.0-rc.1"] {
+            assert_eq!(range.explain(&v(s)).is_ok(), range.compare(&v(s)), "{}", s);
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in [
+            "*",
+            "none",
+            ">= 1.2, < 2.0 || >= 3.0",
+            "< 2.0, >= 1.0.0-rc.1",
+            "== 1.0 || == 1.2",
+            "<< 2.0, >> 1.0~rc1 || >= 3:1.0",
+            "* || >= 1.0",
+        ] {
+            let range = VersionRange::from_str(s).unwrap();
+            let printed = range.to_string();
+            assert_eq!(VersionRange::from_str(&printed).unwrap(), range, "{}", s);
+        }
+        assert_eq!(
+            VersionRange::from_str(">= 1.2,< 2.0||>= 3.0")
+                .unwrap()
+                .to_string(),
+            ">= 1.2, < 2.0 || >= 3.0"
+        );
+        assert_eq!(VersionRange::default().to_string(), "*");
+    }
+
+    #[test]
+    fn display_round_trips_the_scheme() {
+        let cases = [
+            (Scheme::SemVer, ">= 1.0.0-rc.1, < 2.0 || == 3.0"),
+            (Scheme::Dpkg, "<< 1:2.0, >> 1.0~rc1"),
+            (Scheme::Dpkg, ">= 1.0~rc1"),
+            (Scheme::Rpm, ">= 1.0~rc1, < 2.0^git1 || == 3.0-2"),
+            (Scheme::Pep440, ">= 1.0rc1, < 2.0.post1 || == 3.0.dev2"),
+            (Scheme::CalVer, ">= 2024.1, < 2025.6.1"),
+            (Scheme::Pep440, "*"),
+            (Scheme::Rpm, "none"),
+        ];
+        for (scheme, s) in cases {
+            let range = VersionRange::parse_with(s, scheme).unwrap();
+            let printed = range.to_string();
+            assert_eq!(
+                VersionRange::from_str(&printed).unwrap(),
+                range,
+                "{}",
+                printed
+            );
+        }
+        assert_eq!(
+            VersionRange::parse_with(">= 1.0rc1", Scheme::Pep440)
+                .unwrap()
+                .to_string(),
+            "pep440: >= 1.0rc1"
+        );
+        assert_eq!(r("<< 2.0").to_string(), "<< 2.0");
+        let error = VersionRange::from_str("rpm: >= 1.0, !! 2").unwrap_err();
+        assert_eq!(error.fragment(), "!!");
+    }
+
+    #[test]
+    fn malformed_alternatives() {
+        assert!(VersionRange::from_str(">= 1.0 ||").is_err());
+        assert!(VersionRange::from_str("|| >= 1.0").is_err());
+        assert!(VersionRange::from_str(">= 1.0 || ~> 2.0").is_err());
+    }
+
+    fn assert_desugars(input: &str, expected: &str) {
+        let range = VersionRange::from_str(input).unwrap();
+        assert_eq!(
+            range,
+            VersionRange::from_str(expected).unwrap(),
+            "{}",
+            input
+        );
+    }
+
+    #[test]
+    fn caret_ranges() {
+        assert_desugars("^1.2.3", ">= 1.2.3, < 2.0.0");
+        assert_desugars("^1.2", ">= 1.2, < 2.0.0");
+        assert_desugars("^1", ">= 1, < 2.0.0");
+        assert_desugars("^0.2.3", ">= 0.2.3, < 0.3.0");
+        assert_desugars("^0.2", ">= 0.2, < 0.3.0");
+        assert_desugars("^0.0.3", ">= 0.0.3, < 0.0.4");
+        assert_desugars("^0.0", ">= 0.0, < 0.1.0");
+        assert_desugars("^0", ">= 0, < 1.0.0");
+        assert_desugars("^ 1.2.3-beta.1", ">= 1.2.3-beta.1, < 2.0.0");
+
+        let range = VersionRange::from_str("^0.0.3").unwrap();
+        assert!(range.compare(&v("0.0.3")));
+        assert!(!range.compare(&v("0.0.4")));
+        assert!(!range.compare(&v("0.0.2")));
+    }
+
+    #[test]
+    fn tilde_ranges() {
+        assert_desugars("~1.2.3", ">= 1.2.3, < 1.3.0");
+        assert_desugars("~1.2", ">= 1.2, < 1.3.0");
+        assert_desugars("~1", ">= 1, < 2.0.0");
+        assert_desugars("~0.0.1", ">= 0.0.1, < 0.1.0");
+    }
+
+    #[test]
+    fn compatible_release_ranges() {
+        assert_desugars("~=1.4", ">= 1.4, < 2.0.0");
+        assert_desugars("~= 1.4.5", ">= 1.4.5, < 1.5.0");
+        let pep440 = |s: &str| VersionRange::parse_with(s, Scheme::Pep440).unwrap();
+        assert_eq!(pep440("~=2.2.post3"), pep440(">= 2.2.post3, < 3.0.0"));
+        assert_eq!(pep440("~=1.4.5a4"), pep440(">= 1.4.5a4, < 1.5.0"));
+        assert!(VersionRange::from_str("~=2.2.post3").is_err());
+        assert!(VersionRange::from_str("~=1").is_err());
+        assert!(VersionRange::from_str("~=foo").is_err());
+    }
+
+    #[test]
+    fn wildcard_ranges() {
+        assert_desugars("1.2.*", ">= 1.2.0, < 1.3.0");
+        assert_desugars("== 1.*", ">= 1.0.0, < 2.0.0");
+        assert_desugars("1.x", ">= 1.0.0, < 2.0.0");
+        assert_desugars("1.2.x.X", ">= 1.2.0, < 1.3.0");
+        assert_desugars("*.*", "*");
+        assert_desugars("1.2.*, ^1.2.5", ">= 1.2.5, < 1.3.0");
+        assert!(
+            !VersionRange::from_str("1.2.*")
+                .unwrap()
+                .compare(&v("1.2.0-rc.1"))
+        );
+        assert!(VersionRange::from_str("1.*.3").is_err());
+        assert!(VersionRange::from_str(">= 1.*").is_err());
+    }
+
+    #[test]
+    fn operators_without_spaces() {
+        assert_desugars(">=1.2,<2.0||>=3.0", ">= 1.2, < 2.0 || >= 3.0");
+        assert_desugars("<<2.0", "<< 2.0");
+        assert_eq!(
+            VersionRange::from_str("<<2.0").unwrap().scheme,
+            Some(Scheme::Dpkg)
+        );
+        assert!(VersionRange::from_str(">= 1.0 2.0").is_err());
+        assert!(VersionRange::from_str("^").is_err());
+    }
+
+    fn r(s: &str) -> VersionRange {
+        VersionRange::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn unions_are_normalized() {
+        assert_eq!(r(">= 3.0 || < 1.0").to_string(), "< 1.0 || >= 3.0");
+        assert_eq!(
+            r(">= 1.0, < 2.0 || >= 1.5, < 3.0").to_string(),
+            ">= 1.0, < 3.0"
+        );
+        // 隣接する区間はまとめる
+        assert_eq!(r(">= 1.0, < 2.0 || >= 2.0").to_string(), ">= 1.0");
+        assert_eq!(r("< 2.0 || == 2.0").to_string(), "<= 2.0");
+        // 一点だけ抜けた区間はまとめない
+        assert_eq!(r("< 2.0 || > 2.0").to_string(), "< 2.0 || > 2.0");
+        assert_eq!(r(">= 1.0, <= 1.0").to_string(), "== 1.0");
+        assert_eq!(r("* || >= 1.0").to_string(), "*");
+    }
+
+    #[test]
+    fn intersect_ranges() {
+        assert_eq!(r(">= 1.0").intersect(&r("< 2.0")), r(">= 1.0, < 2.0"));
+        assert_eq!(r("^1.2").intersect(&r("~1.4")), r(">= 1.4, < 1.5.0"));
+        assert_eq!(
+            r("< 1.0 || >= 2.0").intersect(&r(">= 0.5, < 3.0")),
+            r(">= 0.5, < 1.0 || >= 2.0, < 3.0")
+        );
+        assert_eq!(r("== 1.5").intersect(&r(">= 1.0, < 2.0")), r("== 1.5"));
+        assert!(r("== 2.5").intersect(&r(">= 1.0, < 2.0")).is_empty());
+        assert!(r("< 1.0").intersect(&r(">= 1.0")).is_empty());
+        assert_eq!(r("<= 1.0").intersect(&r(">= 1.0")), r("== 1.0"));
+    }
+
+    #[test]
+    fn union_ranges() {
+        assert_eq!(r("< 1.0").union(&r(">= 2.0")), r("< 1.0 || >= 2.0"));
+        assert_eq!(r("< 1.0").union(&r(">= 1.0")), r("*"));
+        assert_eq!(r("^1.0").union(&r("^2.0")), r(">= 1.0, < 3.0.0"));
+        assert_eq!(r("none").union(&r("1.0")), r("== 1.0"));
+    }
+
+    #[test]
+    fn complement_ranges() {
+        assert_eq!(r(">= 1.0, < 2.0").complement(), r("< 1.0 || >= 2.0"));
+        assert_eq!(r("== 1.0").complement(), r("< 1.0 || > 1.0"));
+        assert_eq!(r("*").complement(), r("none"));
+        assert_eq!(r("none").complement(), r("*"));
+        assert_eq!(r("< 1.0 || > 2.0").complement(), r(">= 1.0, <= 2.0"));
+        for s in ["^1.2", "< 1.0 || == 1.5 || >= 3.0", "*", "none", "> 1.0"] {
+            assert_eq!(r(s).complement().complement(), r(s), "{}", s);
+            assert!(r(s).is_disjoint(&r(s).complement()), "{}", s);
+            assert_eq!(r(s).union(&r(s).complement()), r("*"), "{}", s);
+        }
+    }
+
+    #[test]
+    fn subset_and_disjoint() {
+        assert!(r("~1.2.3").is_subset_of(&r("^1.0")));
+        assert!(!r("^1.0").is_subset_of(&r("~1.2.3")));
+        assert!(r("none").is_subset_of(&r("== 1.0")));
+        assert!(r("== 1.5 || == 1.7").is_subset_of(&r(">= 1.0, < 2.0")));
+        assert!(!r("== 1.5 || == 2.7").is_subset_of(&r(">= 1.0, < 2.0")));
+        assert!(r("< 1.0").is_disjoint(&r(">= 1.0")));
+        assert!(!r("<= 1.0").is_disjoint(&r(">= 1.0")));
+        assert!(r("none").is_empty());
+        assert!(!r("*").is_empty());
+    }
+
+    #[test]
+    fn set_operations_follow_range_scheme() {
+        let before = r("<< 2.0");
+        let semver = r(">= 1.5");
+        // dpkg 方式にそろえると 2.0~rc2 は 2.0 より前
+        let both = before.intersect(&semver);
+        assert_eq!(both.scheme, Some(Scheme::Dpkg));
+        assert!(!both.is_empty());
+        assert!(both.compare(&dpkg("2.0~rc2")));
+    }
+
+    /// テスト用の決定的な疑似乱数 (xorshift)
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % n
+        }
+
+        fn version(&mut self) -> String {
+            let mut s = format!("{}.{}", self.below(3), self.below(3));
+            if self.below(2) == 0 {
+                s.push_str(&format!(".{}", self.below(3)));
+            }
+            if self.below(5) == 0 {
+                s.push_str(&format!("-rc.{}", self.below(2)));
+            }
+            s
+        }
+
+        fn clause(&mut self) -> String {
+            match self.below(12) {
+                0 => "*".to_string(),
+                1 => format!("{}.*", self.below(3)),
+                op => {
+                    let op = ["", "=", "==", "<", "<=", ">", ">=", "^", "~", "~="][op as usize - 2];
+                    format!("{}{}", op, self.version())
+                }
+            }
+        }
+
+        fn range(&mut self) -> String {
+            let alternatives: Vec<String> = (0..=self.below(3))
+                .map(|_| {
+                    let clauses: Vec<String> = (0..=self.below(3)).map(|_| self.clause()).collect();
+                    clauses.join(", ")
+                })
+                .collect();
+            alternatives.join(" || ")
+        }
+    }
+
+    #[test]
+    fn property_parse_print_parse_is_identity() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let mut checked = 0;
+        for _ in 0..3000 {
+            let input = rng.range();
+            let Ok(range) = VersionRange::from_str(&input) else {
+                continue;
+            };
+            let printed = range.to_string();
+            let reparsed = VersionRange::from_str(&printed)
+                .unwrap_or_else(|e| panic!("{} -> {}: {}", input, printed, e));
+            assert_eq!(reparsed, range, "{} -> {}", input, printed);
+            assert_eq!(reparsed.to_string(), printed, "{}", input);
+            checked += 1;
+        }
+        assert!(checked > 1000, "only {} ranges were checked", checked);
+    }
+
+    #[test]
+    fn property_normalization_keeps_matching_versions() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let versions: Vec<Version> = (0..40).map(|_| v(&rng.version())).collect();
+        for _ in 0..1000 {
+            let input = rng.range();
+            let Ok(range) = VersionRange::from_str(&input) else {
+                continue;
+            };
+            // 区間ごとに別々に解釈した結果の和と一致すること
+            let alternatives: Vec<VersionRange> = input
+                .split("||")
+                .filter_map(|a| VersionRange::from_str(a).ok())
+                .collect();
+            for version in &versions {
+                assert_eq!(
+                    range.compare(version),
+                    alternatives.iter().any(|a| a.compare(version)),
+                    "{} / {}",
+                    input,
+                    version
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn dpkg_ranges_print_dpkg_operators() {
+        let range = r(">> 1.0~rc1, << 2.0");
+        assert_eq!(range.to_string(), ">> 1.0~rc1, << 2.0");
+        assert_eq!(r(&range.to_string()), range);
+    }
+}
@@ -0,0 +1,300 @@
+//! リリース作業のためのバージョンの更新操作。
+//!
+//! どの操作も元のバージョンを変えずに新しいバージョンを返し、
+//! `v1.2.3` の `v` のような接頭辞の形式を保ちます。
+use super::error::{VersionError, VersionErrorKind};
+use super::{Identifier, Scheme, Version, parse_build_metadata, parse_pre_release};
+use std::{fmt, str::FromStr};
+
+/// 繰り上げるバージョンの部分。
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum VersionPart {
+    Major,
+    Minor,
+    Patch,
+    /// 次のプレリリース ([`Version::next_prerelease`])
+    Prerelease,
+}
+
+impl FromStr for VersionPart {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "major" => Ok(VersionPart::Major),
+            "minor" => Ok(VersionPart::Minor),
+            "patch" => Ok(VersionPart::Patch),
+            "pre" | "prerelease" => Ok(VersionPart::Prerelease),
+            _ => Err(format!(
+                "Unknown version part: {} (expected major, minor, patch or prerelease)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for VersionPart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            VersionPart::Major => "major",
+            VersionPart::Minor => "minor",
+            VersionPart::Patch => "patch",
+            VersionPart::Prerelease => "prerelease",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Version {
+    /// 指定した部分を繰り上げたバージョンを返します。
+    pub fn bump(&self, part: VersionPart) -> Result<Version, VersionError> {
+        match part {
+            VersionPart::Major => self.bump_major(),
+            VersionPart::Minor => self.bump_minor(),
+            VersionPart::Patch => self.bump_patch(),
+            VersionPart::Prerelease => self.next_prerelease(),
+        }
+    }
+
+    /// メジャーバージョンを繰り上げます (`1.2.3` → `2.0.0`)。
+    ///
+    /// プレリリースでマイナー以下が0の場合は、そのリリースになります (`2.0.0-rc.1` → `2.0.0`)。
+    pub fn bump_major(&self) -> Result<Version, VersionError> {
+        self.bumped(0)
+    }
+
+    /// マイナーバージョンを繰り上げます (`1.2.3` → `1.3.0`)。
+    pub fn bump_minor(&self) -> Result<Version, VersionError> {
+        self.bumped(1)
+    }
+
+    /// パッチバージョンを繰り上げます (`1.2.3` → `1.2.4`, `1.2.4-rc.1` → `1.2.4`)。
+    pub fn bump_patch(&self) -> Result<Version, VersionError> {
+        self.bumped(2)
+    }
+
+    /// プレリリース識別子を置き換えます。ビルドメタデータはそのまま残ります。
+    ///
+    /// # 引数
+    ///
+    /// * `pre` - ドット区切りのプレリリース識別子 (例: `rc.1`)。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(Version)` - 置き換えたバージョン。
+    /// * `Err(VersionError)` - 識別子が不正な場合、`pre` の中の位置を含む。
+    pub fn with_prerelease(&self, pre: &str) -> Result<Version, VersionError> {
+        let pre = parse_pre_release(pre, 0, pre.len())?;
+        self.rebuilt(&self.nums, &pre, &self.build)
+    }
+
+    /// ビルドメタデータを置き換えます。
+    ///
+    /// # 引数
+    ///
+    /// * `build` - ドット区切りのビルドメタデータ (例: `git.1a2b3c`)。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(Version)` - 置き換えたバージョン。
+    /// * `Err(VersionError)` - 識別子が不正な場合、`build` の中の位置を含む。
+    pub fn with_build(&self, build: &str) -> Result<Version, VersionError> {
+        let build = parse_build_metadata(build, 0)?;
+        self.rebuilt(&self.nums, &self.pre, &build)
+    }
+
+    /// プレリリース識別子を取り除きます (`1.0.0-rc.1+b5` → `1.0.0+b5`)。
+    pub fn strip_prerelease(&self) -> Result<Version, VersionError> {
+        self.rebuilt(&self.nums, &[], &self.build)
+    }
+
+    /// 次のプレリリースを返します。ビルドメタデータは取り除かれます。
+    ///
+    /// * `1.0.0-rc.1` → `1.0.0-rc.2` (最後の数値の識別子を繰り上げる)
+    /// * `1.0.0-rc` → `1.0.0-rc.0` (数値の識別子がなければ `0` を加える)
+    /// * `1.0.0` → `1.0.1-0` (リリース版ならパッチを繰り上げる)
+    pub fn next_prerelease(&self) -> Result<Version, VersionError> {
+        if self.pre.is_empty() {
+            let next = self.bump_patch()?;
+            return next.rebuilt(&next.nums, &[Identifier::Numeric(0)], &[]);
+        }
+        let mut pre = self.pre.clone();
+        match pre.last_mut() {
+            Some(Identifier::Numeric(n)) => {
+                *n = n
+                    .checked_add(1)
+                    .ok_or_else(|| self.whole_error(VersionErrorKind::NumberTooLarge))?;
+            }
+            _ => pre.push(Identifier::Numeric(0)),
+        }
+        self.rebuilt(&self.nums, &pre, &[])
+    }
+
+    /// `index` 番目の数値部分を繰り上げ、それより後ろを0にします。
+    fn bumped(&self, index: usize) -> Result<Version, VersionError> {
+        let mut nums = self.nums.clone();
+        if nums.len() <= index {
+            nums.resize(index + 1, 0);
+        }
+        // プレリリースで後ろがすべて0なら、まだそのリリースに達していない
+        let reached = self.pre.is_empty() || nums[index + 1..].iter().any(|&n| n != 0);
+        if reached {
+            nums[index] = nums[index]
+                .checked_add(1)
+                .ok_or_else(|| self.whole_error(VersionErrorKind::NumberTooLarge))?;
+            nums[index + 1..].fill(0);
+        }
+        self.rebuilt(&nums, &[], &[])
+    }
+
+    /// 元の区切り文字の形式で、各部分を置き換えたバージョンを作ります。
+    ///
+    /// 数値部分が元より多い場合は、最後の区切り文字 (なければ `.`) でつなぎます。
+    pub(super) fn rebuilt(
+        &self,
+        nums: &[u32],
+        pre: &[Identifier],
+        build: &[String],
+    ) -> Result<Version, VersionError> {
+        // プレリリースとビルドメタデータの形式は SemVer 系の方式にしかない
+        if !matches!(self.scheme, Scheme::SemVer | Scheme::CalVer) {
+            return Err(self.whole_error(VersionErrorKind::UnsupportedScheme(self.scheme)));
+        }
+        let core = self.string.split(['-', '+']).next().unwrap_or_default();
+        let is_digit = |c: char| c.is_ascii_digit();
+        let prefix = (!core.starts_with(is_digit)).then(|| self.separators.first());
+        let suffix = (!core.ends_with(is_digit)).then(|| self.separators.last());
+        let start = usize::from(prefix.is_some());
+        let end = self.separators.len() - usize::from(suffix.is_some());
+        let inner = self.separators.get(start..end).unwrap_or_default();
+
+        let mut result = String::new();
+        if let Some(Some(prefix)) = prefix {
+            result.push_str(prefix);
+        }
+        for (i, n) in nums.iter().enumerate() {
+            if i > 0 {
+                let separator = inner.get(i - 1).or(inner.last());
+                result.push_str(separator.map_or(".", String::as_str));
+            }
+            result.push_str(&n.to_string());
+        }
+        if let Some(Some(suffix)) = suffix {
+            result.push_str(suffix);
+        }
+        if !pre.is_empty() {
+            let pre: Vec<String> = pre.iter().map(ToString::to_string).collect();
+            result.push('-');
+            result.push_str(&pre.join("."));
+        }
+        if !build.is_empty() {
+            result.push('+');
+            result.push_str(&build.join("."));
+        }
+        Version::parse_with(&result, self.scheme)
+    }
+
+    /// バージョン文字列全体を指すエラーを作ります。
+    fn whole_error(&self, kind: VersionErrorKind) -> VersionError {
+        VersionError::new(kind, &self.string, 0..self.string.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        Version::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn bumps_reset_lower_parts() {
+        let cases = [
+            ("1.2.3", VersionPart::Major, "2.0.0"),
+            ("1.2.3", VersionPart::Minor, "1.3.0"),
+            ("1.2.3", VersionPart::Patch, "1.2.4"),
+            ("1.2.3+build.5", VersionPart::Patch, "1.2.4"),
+            ("2.0.0-rc.1", VersionPart::Major, "2.0.0"),
+            ("1.2.0-rc.1", VersionPart::Major, "2.0.0"),
+            ("1.3.0-beta", VersionPart::Minor, "1.3.0"),
+            ("1.2.4-rc.1", VersionPart::Patch, "1.2.4"),
+            ("1", VersionPart::Minor, "1.1"),
+            ("1.2", VersionPart::Patch, "1.2.1"),
+        ];
+        for (input, part, expected) in cases {
+            assert_eq!(
+                v(input).bump(part).unwrap().as_str(),
+                expected,
+                "{} {}",
+                input,
+                part
+            );
+        }
+    }
+
+    #[test]
+    fn separators_are_preserved() {
+        assert_eq!(v("v1.2.3").bump_minor().unwrap().as_str(), "v1.3.0");
+        assert_eq!(v("V1.2").bump_patch().unwrap().as_str(), "V1.2.1");
+        assert_eq!(
+            v("v1.2.3-rc.1+b7")
+                .with_prerelease("beta.2")
+                .unwrap()
+                .as_str(),
+            "v1.2.3-beta.2+b7"
+        );
+    }
+
+    #[test]
+    fn prerelease_and_build() {
+        assert_eq!(
+            v("1.0.0-rc.1+b5").strip_prerelease().unwrap().as_str(),
+            "1.0.0+b5"
+        );
+        assert_eq!(
+            v("1.0.0").with_build("git.1a2b3c").unwrap().as_str(),
+            "1.0.0+git.1a2b3c"
+        );
+        assert_eq!(
+            v("1.0.0-rc.1").next_prerelease().unwrap().as_str(),
+            "1.0.0-rc.2"
+        );
+        assert_eq!(
+            v("1.0.0-rc").next_prerelease().unwrap().as_str(),
+            "1.0.0-rc.0"
+        );
+        assert_eq!(v("1.0.0+b1").next_prerelease().unwrap().as_str(), "1.0.1-0");
+        assert!(v("1.0.0-rc.1").next_prerelease().unwrap() > v("1.0.0-rc.1"));
+        assert!(v("1.0.0").next_prerelease().unwrap() > v("1.0.0"));
+    }
+
+    #[test]
+    fn invalid_operations() {
+        let error = v("1.0.0").with_prerelease("rc..1").unwrap_err();
+        assert_eq!(error.kind, VersionErrorKind::EmptyComponent);
+        assert_eq!(error.span, 3..3);
+        assert!(v("1.0.0").with_build("a+b").is_err());
+        let dpkg = Version::parse_with("1:1.0-1", Scheme::Dpkg).unwrap();
+        assert_eq!(
+            dpkg.bump_minor().unwrap_err().kind,
+            VersionErrorKind::UnsupportedScheme(Scheme::Dpkg)
+        );
+        // 繰り上げた結果が方式の規則に合わない場合 (月が0になる)
+        let calver = Version::parse_with("2026.10", Scheme::CalVer).unwrap();
+        assert!(calver.bump_major().is_err());
+        assert_eq!(calver.bump_minor().unwrap().as_str(), "2026.11");
+    }
+
+    #[test]
+    fn parts_from_str() {
+        for part in [
+            VersionPart::Major,
+            VersionPart::Minor,
+            VersionPart::Patch,
+            VersionPart::Prerelease,
+        ] {
+            assert_eq!(part.to_string().parse::<VersionPart>(), Ok(part));
+        }
+        assert!("build".parse::<VersionPart>().is_err());
+    }
+}
@@ -0,0 +1,310 @@
+//! dpkg (Debian) 互換のバージョン比較。
+//!
+//! `[epoch:]upstream_version[-debian_revision]` 形式を扱い、
+//! `dpkg --compare-versions` と同じ順序を返します。
+use super::error::{VersionError, VersionErrorKind};
+use std::cmp::Ordering;
+
+/// dpkg 形式のバージョン文字列を構成要素に分解したもの。
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DpkgVersion<'a> {
+    pub epoch: u32,
+    pub upstream: &'a str,
+    pub revision: &'a str,
+}
+
+/// dpkg 形式のバージョン文字列を分解し、構文を検証します。
+///
+/// # 引数
+///
+/// * `s` - 検証するバージョン文字列。
+///
+/// # 戻り値
+///
+/// * `Ok(DpkgVersion)` - 有効な dpkg バージョンの場合。
+/// * `Err(VersionError)` - 構文が不正な場合、問題のある位置を含む。
+pub fn parse(s: &str) -> Result<DpkgVersion<'_>, VersionError> {
+    let error = |kind, span| Err(VersionError::new(kind, s, span));
+    if s.is_empty() {
+        return error(VersionErrorKind::Empty, 0..0);
+    }
+    if let Some((i, c)) = s.char_indices().find(|(_, c)| c.is_whitespace()) {
+        return error(VersionErrorKind::InvalidCharacter(c), i..i + c.len_utf8());
+    }
+    // エポックは最初の ":" より前
+    let (epoch, rest, rest_start) = match s.split_once(':') {
+        Some((epoch_str, rest)) => {
+            let span = 0..epoch_str.len();
+            if epoch_str.is_empty() || !epoch_str.chars().all(|c| c.is_ascii_digit()) {
+                return error(VersionErrorKind::ExpectedNumber, span);
+            }
+            let Ok(epoch) = epoch_str.parse::<u32>() else {
+                return error(VersionErrorKind::NumberTooLarge, span);
+            };
+            (epoch, rest, epoch_str.len() + 1)
+        }
+        None => (0, s, 0),
+    };
+    // リビジョンは最後の "-" より後
+    let (upstream, revision) = match rest.rsplit_once('-') {
+        Some((upstream, revision)) => {
+            if revision.is_empty() {
+                return error(VersionErrorKind::EmptyComponent, s.len()..s.len());
+            }
+            (upstream, revision)
+        }
+        None => (rest, ""),
+    };
+    let revision_start = rest_start + upstream.len() + 1;
+    if upstream.is_empty() {
+        return error(VersionErrorKind::EmptyComponent, rest_start..rest_start);
+    }
+    if let Some(c) = upstream.chars().next()
+        && !c.is_ascii_digit()
+    {
+        return error(
+            VersionErrorKind::Malformed("version number does not start with a digit"),
+            rest_start..rest_start + c.len_utf8(),
+        );
+    }
+    if let Some((i, c)) = upstream
+        .char_indices()
+        .find(|&(_, c)| !(c.is_ascii_alphanumeric() || ".-+~:".contains(c)))
+    {
+        let i = rest_start + i;
+        return error(VersionErrorKind::InvalidCharacter(c), i..i + c.len_utf8());
+    }
+    if let Some((i, c)) = revision
+        .char_indices()
+        .find(|&(_, c)| !(c.is_ascii_alphanumeric() || ".+~".contains(c)))
+    {
+        let i = revision_start + i;
+        return error(VersionErrorKind::InvalidCharacter(c), i..i + c.len_utf8());
+    }
+    Ok(DpkgVersion {
+        epoch,
+        upstream,
+        revision,
+    })
+}
+
+/// 非数字部分の文字の重みを返します。
+///
+/// `~` は文字列の終端よりも前、英字は記号よりも前に並びます。
+fn order(c: Option<u8>) -> i32 {
+    match c {
+        None => 0,
+        Some(c) if c.is_ascii_digit() => 0,
+        Some(c) if c.is_ascii_alphabetic() => c as i32,
+        Some(b'~') => -1,
+        Some(c) => c as i32 + 256,
+    }
+}
+
+/// dpkg の `verrevcmp` と同じ規則で upstream またはリビジョン部分を比較します。
+pub fn verrevcmp(a: &str, b: &str) -> Ordering {
+    let a = a.as_bytes();
+    let b = b.as_bytes();
+    let (mut i, mut j) = (0, 0);
+    let is_digit = |s: &[u8], k: usize| s.get(k).is_some_and(u8::is_ascii_digit);
+
+    while i < a.len() || j < b.len() {
+        // 非数字部分を一文字ずつ比較
+        while (i < a.len() && !is_digit(a, i)) || (j < b.len() && !is_digit(b, j)) {
+            let ac = order(a.get(i).copied());
+            let bc = order(b.get(j).copied());
+            if ac != bc {
+                return ac.cmp(&bc);
+            }
+            i += 1;
+            j += 1;
+        }
+        // 数字部分は先頭の0を無視して数値として比較
+        while a.get(i) == Some(&b'0') {
+            i += 1;
+        }
+        while b.get(j) == Some(&b'0') {
+            j += 1;
+        }
+        let mut first_diff = Ordering::Equal;
+        while is_digit(a, i) && is_digit(b, j) {
+            if first_diff == Ordering::Equal {
+                first_diff = a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+        if is_digit(a, i) {
+            return Ordering::Greater;
+        }
+        if is_digit(b, j) {
+            return Ordering::Less;
+        }
+        if first_diff != Ordering::Equal {
+            return first_diff;
+        }
+    }
+    Ordering::Equal
+}
+
+/// `verrevcmp` で等しくなる文字列が同じ結果になるように正規化します。
+///
+/// 非数字部分はそのまま残し、数字部分は先頭の0を取り除いた数値にします
+/// (空の数字部分は0と等しいので "0" になります)。
+fn canonical_part(s: &str) -> String {
+    let mut result = String::new();
+    let mut digits = String::new();
+    let mut in_digits = s.starts_with(|c: char| c.is_ascii_digit());
+    let flush = |digits: &mut String, result: &mut String| {
+        let trimmed = digits.trim_start_matches('0');
+        result.push_str(if trimmed.is_empty() { "0" } else { trimmed });
+        digits.clear();
+    };
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            in_digits = true;
+        } else {
+            if in_digits {
+                flush(&mut digits, &mut result);
+                in_digits = false;
+            }
+            result.push(c);
+        }
+    }
+    if !s.is_empty() {
+        flush(&mut digits, &mut result);
+    }
+    result
+}
+
+/// 分解済みの dpkg バージョンの正規形を返します。
+///
+/// 比較して等しいバージョン同士は、同じ正規形になります。
+pub fn canonical(v: &DpkgVersion) -> String {
+    let mut result = String::new();
+    if v.epoch != 0 {
+        result.push_str(&format!("{}:", v.epoch));
+    }
+    result.push_str(&canonical_part(v.upstream));
+    // リビジョンの "0" は省略した場合と等しい
+    let revision = canonical_part(v.revision);
+    if !revision.is_empty() && revision != "0" {
+        result.push('-');
+        result.push_str(&revision);
+    }
+    result
+}
+
+/// 分解済みの dpkg バージョン同士を比較します。
+pub fn compare_parsed(a: &DpkgVersion, b: &DpkgVersion) -> Ordering {
+    a.epoch
+        .cmp(&b.epoch)
+        .then_with(|| verrevcmp(a.upstream, b.upstream))
+        .then_with(|| verrevcmp(a.revision, b.revision))
+}
+
+/// dpkg 形式のバージョン文字列同士を比較します。
+///
+/// 構文が不正な文字列は、不正な方が常に小さいものとして扱います。
+pub fn compare(a: &str, b: &str) -> Ordering {
+    match (parse(a), parse(b)) {
+        (Ok(a), Ok(b)) => compare_parsed(&a, &b),
+        (Ok(_), Err(_)) => Ordering::Greater,
+        (Err(_), Ok(_)) => Ordering::Less,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tilde_sorts_before_everything() {
+        // Debian Policy 5.6.12 の例: "~~" < "~~a" < "~" < "" < "a"
+        let ordered = ["1.0~~", "1.0~~a", "1.0~", "1.0", "1.0a"];
+        for (i, a) in ordered.iter().enumerate() {
+            for (j, b) in ordered.iter().enumerate() {
+                assert_eq!(compare(a, b), i.cmp(&j), "{} <=> {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn letters_sort_before_non_letters() {
+        assert_eq!(compare("1.0a", "1.0+"), Ordering::Less);
+        assert_eq!(compare("1.0a", "1.0."), Ordering::Less);
+        assert_eq!(compare("1.0+b1", "1.0"), Ordering::Greater);
+        assert_eq!(compare("1.0.1", "1.0+1"), Ordering::Greater);
+    }
+
+    #[test]
+    fn numeric_parts_compare_numerically() {
+        assert_eq!(compare("2.30", "2.4"), Ordering::Greater);
+        assert_eq!(compare("1.002", "1.2"), Ordering::Equal);
+        assert_eq!(compare("1.0-01", "1.0-1"), Ordering::Equal);
+        assert_eq!(compare("1.0-1", "1.0-2"), Ordering::Less);
+        assert_eq!(compare("1.0", "1.0-0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn epoch_and_revision() {
+        assert_eq!(compare("0:1.0", "1.0"), Ordering::Equal);
+        assert_eq!(compare("1:0.1", "9.9"), Ordering::Greater);
+        assert_eq!(compare("1:2.3~rc1-4ubuntu1", "1:2.3-1"), Ordering::Less);
+        assert_eq!(
+            compare("1:2.3~rc1-4ubuntu1", "1:2.3~rc1-4"),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare("1:2.3~rc1-4ubuntu1", "1:2.3~rc1-4ubuntu1"),
+            Ordering::Equal
+        );
+        assert_eq!(compare("1.2-3-4", "1.2-3-5"), Ordering::Less);
+    }
+
+    #[test]
+    fn canonical_form() {
+        let cases = [
+            ("1.0", "1.0"),
+            ("0:1.0-0", "1.0"),
+            ("1.002-01", "1.2-1"),
+            ("1:2.3~rc1-4ubuntu1", "1:2.3~rc1-4ubuntu1"),
+            ("1.0a", "1.0a0"),
+            ("1.0a0", "1.0a0"),
+            ("2.00+b1", "2.0+b1"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(canonical(&parse(input).unwrap()), expected, "{}", input);
+        }
+        assert_eq!(compare("1.0a", "1.0a0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn parse_components() {
+        assert_eq!(
+            parse("1:2.3~rc1-4ubuntu1"),
+            Ok(DpkgVersion {
+                epoch: 1,
+                upstream: "2.3~rc1",
+                revision: "4ubuntu1",
+            })
+        );
+        assert_eq!(parse("1.2-3-4").unwrap().upstream, "1.2-3");
+        assert_eq!(parse("2.0").unwrap().revision, "");
+    }
+
+    #[test]
+    fn invalid_versions() {
+        for s in [
+            "", "a1.0", "1.0 1", "x:1.0", ":1.0", "1:", "1.0-", "1.0_1", "1.0-a_b",
+        ] {
+            assert!(parse(s).is_err(), "{}", s);
+        }
+        let error = parse("1:2.0-a_b").unwrap_err();
+        assert_eq!(error.kind, VersionErrorKind::InvalidCharacter('_'));
+        assert_eq!(error.span, 7..8);
+        assert_eq!(parse("x:1.0").unwrap_err().span, 0..1);
+    }
+}
@@ -0,0 +1,782 @@
+//! 他のエコシステム (Cargo、npm、PyPI、Debian) のバージョン制約との相互変換。
+//!
+//! 各エコシステムの構文を [`VersionRange`] に変換し、逆に [`VersionRange`] を
+//! 各エコシステムの構文で書き出します。書き出し先の構文で正確に表せない範囲は、
+//! 近い範囲で書き出したうえで理由を報告します。
+use super::error::{VersionError, VersionErrorKind};
+use super::{
+    EMPTY_RANGE, RangeData, Scheme, Version, VersionRange, VersionRangeInsertType, split_operator,
+    split_trimmed,
+};
+use std::{fmt, ops::Range, str::FromStr};
+
+/// バージョン制約の構文を持つエコシステム。
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Ecosystem {
+    /// `Cargo.toml` の依存関係 (`1.2` は `^1.2` と同じ)
+    Cargo,
+    /// `package.json` の依存関係 (`||`、ハイフン範囲、`1.x`)
+    Npm,
+    /// PEP 440 のバージョン指定子 (`~=`、`!=`、`==1.2.*`)
+    PyPI,
+    /// Debian の依存関係のバージョン条件 (`(>= 1.0)`、`<<`、`>>`)
+    Debian,
+}
+
+impl FromStr for Ecosystem {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cargo" => Ok(Ecosystem::Cargo),
+            "npm" => Ok(Ecosystem::Npm),
+            "pypi" | "pip" => Ok(Ecosystem::PyPI),
+            "debian" | "deb" => Ok(Ecosystem::Debian),
+            _ => Err(format!("Unknown ecosystem: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Ecosystem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Ecosystem::Cargo => "cargo",
+            Ecosystem::Npm => "npm",
+            Ecosystem::PyPI => "pypi",
+            Ecosystem::Debian => "debian",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 範囲を他のエコシステムの構文で書き出した結果。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rendered {
+    pub constraint: String,
+    /// 正確に表せなかった理由。`None` なら `constraint` は元の範囲と同じバージョンを表す
+    pub inexact: Option<String>,
+}
+
+impl Rendered {
+    /// 元の範囲を正確に表しているかを判定します。
+    pub fn is_exact(&self) -> bool {
+        self.inexact.is_none()
+    }
+}
+
+impl Ecosystem {
+    /// このエコシステムのバージョンの方式を返します。
+    pub fn scheme(self) -> Scheme {
+        match self {
+            Ecosystem::Cargo | Ecosystem::Npm => Scheme::SemVer,
+            Ecosystem::PyPI => Scheme::Pep440,
+            Ecosystem::Debian => Scheme::Dpkg,
+        }
+    }
+
+    /// このエコシステムの構文のバージョン制約を範囲に変換します。
+    ///
+    /// # 引数
+    ///
+    /// * `s` - バージョン制約 (例: Cargo の `1.2, <1.5`、PyPI の `~=1.4, !=1.4.2`)。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(VersionRange)` - このエコシステムの方式で比較する範囲。
+    /// * `Err(VersionError)` - 構文が不正な場合、問題のある条件の位置を含む。
+    pub fn parse(self, s: &str) -> Result<VersionRange, VersionError> {
+        match self {
+            Ecosystem::Cargo => parse_cargo(s),
+            Ecosystem::Npm => parse_npm(s),
+            Ecosystem::PyPI => parse_pypi(s),
+            Ecosystem::Debian => parse_debian(s),
+        }
+    }
+
+    /// 範囲をこのエコシステムの構文で書き出します。
+    ///
+    /// 和集合を書けない構文では、すべての区間を含む一つの区間で近似します。
+    /// Debian では下限と上限の両方がある範囲も書けないので、下限だけで近似します。
+    pub fn render(self, range: &VersionRange) -> Rendered {
+        let scheme = self.scheme();
+        let range = range.in_scheme(Some(scheme));
+        let mut notes: Vec<String> = range
+            .ranges
+            .iter()
+            .flat_map(RangeData::constraints)
+            .filter(|(_, v)| v.scheme != scheme)
+            .map(|(_, v)| format!("`{}` is not a valid {} version", v, self))
+            .collect();
+
+        let constraint = match range.ranges.as_slice() {
+            [] => match minimum(scheme) {
+                // 最小のバージョンより小さい
+                Some(minimum) => format!("<{}", minimum),
+                None => {
+                    notes.push(format!(
+                        "{} cannot express a range that matches nothing",
+                        self
+                    ));
+                    "(<< 0~)".to_string()
+                }
+            },
+            [single] => self.render_interval(&single.constraints(), &mut notes),
+            [first, .., last] => {
+                let hull = hull(first, last);
+                match self {
+                    Ecosystem::Npm => {
+                        let alternatives: Vec<String> = range
+                            .ranges
+                            .iter()
+                            .map(|r| self.render_interval(&r.constraints(), &mut notes))
+                            .collect();
+                        alternatives.join(" || ")
+                    }
+                    Ecosystem::PyPI => match single_point_gaps(&range.ranges) {
+                        Some(gaps) => {
+                            let mut constraints = hull;
+                            constraints.extend(gaps.into_iter().map(|v| (None, v)));
+                            self.render_constraints(&constraints, &mut notes)
+                        }
+                        None => {
+                            notes.push(self.no_alternatives(&range));
+                            self.render_constraints(&hull, &mut notes)
+                        }
+                    },
+                    Ecosystem::Cargo | Ecosystem::Debian => {
+                        notes.push(self.no_alternatives(&range));
+                        self.render_constraints(&hull, &mut notes)
+                    }
+                }
+            }
+        };
+        Rendered {
+            constraint,
+            inexact: (!notes.is_empty()).then(|| notes.join("; ")),
+        }
+    }
+
+    fn no_alternatives(self, range: &VersionRange) -> String {
+        format!(
+            "{} cannot express alternatives; `{}` was widened to a single interval",
+            self, range
+        )
+    }
+
+    fn render_interval(
+        self,
+        constraints: &[(VersionRangeInsertType, &Version)],
+        notes: &mut Vec<String>,
+    ) -> String {
+        let constraints: Vec<(Option<VersionRangeInsertType>, &Version)> = constraints
+            .iter()
+            .map(|&(insert_type, v)| (Some(insert_type), v))
+            .collect();
+        self.render_constraints(&constraints, notes)
+    }
+
+    /// 条件の列を書き出します。条件の種類が `None` のものは「等しくない」を表します。
+    ///
+    /// Debian の関係一つには条件を一つしか書けないので、最初の条件 (下限) だけを書き出し、
+    /// 残りの条件は別の関係として書くように `notes` で知らせます。
+    fn render_constraints(
+        self,
+        constraints: &[(Option<VersionRangeInsertType>, &Version)],
+        notes: &mut Vec<String>,
+    ) -> String {
+        let constraints = match (self, constraints) {
+            (Ecosystem::Debian, [first, rest @ ..]) if !rest.is_empty() => {
+                let rest: Vec<String> = rest
+                    .iter()
+                    .map(|&(insert_type, v)| format!("`{}`", self.comparator(insert_type, v)))
+                    .collect();
+                notes.push(format!(
+                    "{} allows one condition per relation; {} must be written as a separate relation",
+                    self,
+                    rest.join(", ")
+                ));
+                std::slice::from_ref(first)
+            }
+            _ => constraints,
+        };
+        if constraints.is_empty() {
+            return match self {
+                Ecosystem::Cargo | Ecosystem::Npm => "*".to_string(),
+                Ecosystem::PyPI | Ecosystem::Debian => String::new(),
+            };
+        }
+        let parts: Vec<String> = constraints
+            .iter()
+            .map(|&(insert_type, v)| self.comparator(insert_type, v))
+            .collect();
+        match self {
+            Ecosystem::Npm => parts.join(" "),
+            Ecosystem::Cargo | Ecosystem::PyPI | Ecosystem::Debian => parts.join(", "),
+        }
+    }
+
+    fn comparator(self, insert_type: Option<VersionRangeInsertType>, v: &Version) -> String {
+        use VersionRangeInsertType::*;
+        match self {
+            // 数値部分を省略すると意味が変わるので、常に3つの数値部分で書く
+            Ecosystem::Cargo | Ecosystem::Npm => {
+                let op = match insert_type {
+                    Some(ExactlyEqual) if self == Ecosystem::Npm => "",
+                    Some(ExactlyEqual) => "=",
+                    Some(insert_type) => insert_type.symbol(None),
+                    None => unreachable!("only PyPI renders exclusions"),
+                };
+                format!("{}{}", op, v.canonical())
+            }
+            Ecosystem::PyPI => {
+                let op = match insert_type {
+                    Some(insert_type) => insert_type.symbol(None),
+                    None => "!=",
+                };
+                format!("{}{}", op, v)
+            }
+            Ecosystem::Debian => match insert_type {
+                Some(insert_type) => format!("({})", debian_condition(insert_type, v)),
+                None => unreachable!("only PyPI renders exclusions"),
+            },
+        }
+    }
+}
+
+/// Debian の演算子を、同じ範囲を表すこの crate の演算子に変換します。
+///
+/// 古い `<` と `>` は `<=` と `>=` の意味です (Debian Policy 7.1)。
+///
+/// # 戻り値
+///
+/// * `Some(&str)` - この crate の演算子。
+/// * `None` - Debian にない演算子の場合。
+pub fn debian_operator(op: &str) -> Option<&'static str> {
+    match op {
+        "<<" => Some("<<"),
+        ">>" => Some(">>"),
+        "<=" | "<" => Some("<="),
+        ">=" | ">" => Some(">="),
+        "=" => Some("=="),
+        _ => None,
+    }
+}
+
+/// 条件一つを Debian の書き方 (`>= 1.2`、`<< 2.0`、`= 1.0`) で書き出します。
+fn debian_condition(insert_type: VersionRangeInsertType, v: &Version) -> String {
+    let op = match insert_type {
+        VersionRangeInsertType::ExactlyEqual => "=",
+        insert_type => insert_type.symbol(Some(Scheme::Dpkg)),
+    };
+    format!("{} {}", op, v)
+}
+
+/// 範囲を Debian の条件の列で書き出します。すべてのバージョンを含む範囲なら空の列です。
+///
+/// # 戻り値
+///
+/// * `Some(Vec<String>)` - 条件の列。すべてを満たすバージョンが範囲に含まれる。
+/// * `None` - 和集合や何も含まない範囲のように、一つの区間で表せない場合。
+pub fn debian_conditions(range: &VersionRange) -> Option<Vec<String>> {
+    match range.ranges.as_slice() {
+        [single] => Some(
+            single
+                .constraints()
+                .into_iter()
+                .map(|(insert_type, v)| debian_condition(insert_type, v))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// 最初の区間の下限から最後の区間の上限までの区間を表す条件。
+fn hull<'a>(
+    first: &'a RangeData,
+    last: &'a RangeData,
+) -> Vec<(Option<VersionRangeInsertType>, &'a Version)> {
+    let mut constraints = Vec::new();
+    match first.lower() {
+        Some((v, true)) => constraints.push((Some(VersionRangeInsertType::LaterOrEqual), v)),
+        Some((v, false)) => constraints.push((Some(VersionRangeInsertType::StrictlyLater), v)),
+        None => {}
+    }
+    match last.upper() {
+        Some((v, true)) => constraints.push((Some(VersionRangeInsertType::EarlierOrEqual), v)),
+        Some((v, false)) => constraints.push((Some(VersionRangeInsertType::StrictlyEarlier), v)),
+        None => {}
+    }
+    constraints
+}
+
+/// 区間の間の隙間がすべて一点だけなら、その点の列を返します。
+fn single_point_gaps(ranges: &[RangeData]) -> Option<Vec<&Version>> {
+    ranges
+        .windows(2)
+        .map(|pair| match (pair[0].upper(), pair[1].lower()) {
+            (Some((a, false)), Some((b, false))) if a == b => Some(a),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 方式で最小のバージョン。dpkg には最小のバージョンがありません (`0~` より `0~~` が小さい)。
+fn minimum(scheme: Scheme) -> Option<&'static str> {
+    match scheme {
+        Scheme::SemVer => Some("0.0.0-0"),
+        Scheme::Pep440 => Some("0.dev0"),
+        _ => None,
+    }
+}
+
+/// `< version` が何も含まない条件かを判定します。
+fn below_minimum(op: &str, version: &str, scheme: Scheme) -> bool {
+    op == "<"
+        && minimum(scheme).is_some_and(|minimum| {
+            let parse = |s| Version::parse_with(s, scheme);
+            matches!((parse(version), parse(minimum)), (Ok(a), Ok(b)) if a == b)
+        })
+}
+
+/// どのバージョンも含む範囲。
+fn any(scheme: Scheme) -> VersionRange {
+    VersionRange {
+        ranges: vec![RangeData::default()],
+        scheme: Some(scheme),
+    }
+}
+
+/// この crate の構文で書いた条件を解釈します。エラーの位置は元の制約の `span` にします。
+fn clause_range(
+    ours: &str,
+    scheme: Scheme,
+    input: &str,
+    span: Range<usize>,
+) -> Result<VersionRange, VersionError> {
+    VersionRange::parse_with(ours, scheme).map_err(|e| VersionError::new(e.kind, input, span))
+}
+
+/// 省略のある SemVer のバージョン (`1.2`、`1.x`、`*`) なら、書かれている数値部分を返します。
+///
+/// 3つ以上の数値部分がすべて書かれている場合は `None` を返します。
+fn partial_prefix(core: &str) -> Result<Option<Vec<u32>>, VersionErrorKind> {
+    let is_wildcard = |c: &str| matches!(c, "*" | "x" | "X");
+    let components: Vec<&str> = core.split('.').collect();
+    let written = components
+        .iter()
+        .position(|c| is_wildcard(c))
+        .unwrap_or(components.len());
+    if !components[written..].iter().all(|c| is_wildcard(c)) {
+        return Err(VersionErrorKind::InvalidWildcard);
+    }
+    if written >= 3 && written == components.len() {
+        return Ok(None);
+    }
+    components[..written]
+        .iter()
+        .map(|c| {
+            c.parse::<u32>()
+                .map_err(|_| VersionErrorKind::ExpectedNumber)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+/// Cargo と npm の比較条件一つを、この crate の構文に直します。
+///
+/// 省略された数値部分は任意の値を表します (`>1.2` は `>= 1.3.0`、`<=1.2` は `< 1.3.0`)。
+/// 演算子のない条件は `bare` の演算子として扱います。
+fn semver_clause(op: &str, version: &str, bare: &str) -> Result<String, VersionErrorKind> {
+    if version.is_empty() {
+        return if op.is_empty() {
+            Ok("*".to_string())
+        } else {
+            Err(VersionErrorKind::MissingVersion)
+        };
+    }
+    let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+    let op = if op.is_empty() { bare } else { op };
+    if below_minimum(op, version, Scheme::SemVer) {
+        return Ok(EMPTY_RANGE.to_string());
+    }
+    let core_end = version.find(['-', '+']).unwrap_or(version.len());
+    let prefix = match partial_prefix(&version[..core_end])? {
+        Some(prefix) if core_end == version.len() => prefix,
+        _ => {
+            let op = match op {
+                "=" | "==" => "==",
+                ">" | ">=" | "<" | "<=" | "^" | "~" => op,
+                _ => return Err(VersionErrorKind::UnknownOperator(op.to_string())),
+            };
+            return Ok(format!("{} {}", op, version));
+        }
+    };
+
+    let join = |nums: &[u32]| {
+        let mut parts: Vec<String> = nums.iter().map(u32::to_string).collect();
+        while parts.len() < 3 {
+            parts.push("0".to_string());
+        }
+        parts.join(".")
+    };
+    let bump = |nums: &[u32]| {
+        let mut nums = nums.to_vec();
+        let last = nums.last_mut().expect("prefix is not empty");
+        *last = last
+            .checked_add(1)
+            .ok_or(VersionErrorKind::NumberTooLarge)?;
+        Ok::<_, VersionErrorKind>(join(&nums))
+    };
+    if prefix.is_empty() {
+        return match op {
+            "=" | "==" | ">=" | "<=" | "^" | "~" => Ok("*".to_string()),
+            ">" | "<" => Ok(EMPTY_RANGE.to_string()),
+            _ => Err(VersionErrorKind::UnknownOperator(op.to_string())),
+        };
+    }
+    match op {
+        "=" | "==" => Ok(format!(">= {}, < {}", join(&prefix), bump(&prefix)?)),
+        "^" | "~" => {
+            let written: Vec<String> = prefix.iter().map(u32::to_string).collect();
+            Ok(format!("{}{}", op, written.join(".")))
+        }
+        ">" => Ok(format!(">= {}", bump(&prefix)?)),
+        ">=" => Ok(format!(">= {}", join(&prefix))),
+        "<" => Ok(format!("< {}", join(&prefix))),
+        "<=" => Ok(format!("< {}", bump(&prefix)?)),
+        _ => Err(VersionErrorKind::UnknownOperator(op.to_string())),
+    }
+}
+
+fn parse_cargo(s: &str) -> Result<VersionRange, VersionError> {
+    let mut result = any(Scheme::SemVer);
+    if s.trim().is_empty() {
+        return Ok(result);
+    }
+    for (start, clause) in split_trimmed(s, ",") {
+        let span = start..start + clause.len();
+        let (op, version) = split_operator(clause);
+        let ours = semver_clause(op, version, "^")
+            .map_err(|kind| VersionError::new(kind, s, span.clone()))?;
+        result = result.intersect(&clause_range(&ours, Scheme::SemVer, s, span)?);
+    }
+    Ok(result)
+}
+
+/// 空白で区切った語を、`s` の中での位置と共に列挙します。
+///
+/// 演算子だけの語 (`>= 1.0` の `>=`) は次の語とつなげます。
+fn npm_tokens(s: &str, offset: usize) -> Vec<(Range<usize>, String)> {
+    let mut tokens = Vec::new();
+    let mut operator: Option<(usize, &str)> = None;
+    let mut start = 0;
+    for word in s.split(|c: char| c.is_ascii_whitespace()) {
+        let word_start = offset + start;
+        start += word.len() + 1;
+        if word.is_empty() {
+            continue;
+        }
+        match operator.take() {
+            Some((op_start, op)) => {
+                tokens.push((op_start..word_start + word.len(), format!("{}{}", op, word)));
+            }
+            None if word.chars().all(|c| "<>=^~".contains(c)) => {
+                operator = Some((word_start, word));
+            }
+            None => tokens.push((word_start..word_start + word.len(), word.to_string())),
+        }
+    }
+    if let Some((op_start, op)) = operator {
+        tokens.push((op_start..op_start + op.len(), op.to_string()));
+    }
+    tokens
+}
+
+fn parse_npm(s: &str) -> Result<VersionRange, VersionError> {
+    let mut result = VersionRange {
+        ranges: Vec::new(),
+        scheme: Some(Scheme::SemVer),
+    };
+    for (start, alternative) in split_trimmed(s, "||") {
+        let tokens = npm_tokens(alternative, start);
+        let mut range = any(Scheme::SemVer);
+        let mut add = |op: &str, version: &str, span: Range<usize>| {
+            let ours = semver_clause(op, version, "=")
+                .map_err(|kind| VersionError::new(kind, s, span.clone()))?;
+            range = range.intersect(&clause_range(&ours, Scheme::SemVer, s, span)?);
+            Ok::<_, VersionError>(())
+        };
+        match tokens.as_slice() {
+            // ハイフン範囲 (`1.2.3 - 2.3`) は両端を含む
+            [(lower_span, lower), (_, hyphen), (upper_span, upper)] if hyphen == "-" => {
+                add(">=", lower, lower_span.clone())?;
+                add("<=", upper, upper_span.clone())?;
+            }
+            _ => {
+                for (span, token) in &tokens {
+                    let (op, version) = split_operator(token);
+                    add(op, version, span.clone())?;
+                }
+            }
+        }
+        result = result.union(&range);
+    }
+    Ok(result)
+}
+
+fn parse_pypi(s: &str) -> Result<VersionRange, VersionError> {
+    let mut result = any(Scheme::Pep440);
+    if s.trim().is_empty() {
+        return Ok(result);
+    }
+    for (start, clause) in split_trimmed(s, ",") {
+        let span = start..start + clause.len();
+        let (op, version) = split_operator(clause);
+        let parse = |ours: String| clause_range(&ours, Scheme::Pep440, s, span.clone());
+        let clause_range = match op {
+            _ if below_minimum(op, version, Scheme::Pep440) => VersionRange::empty(),
+            "~=" | "<" | "<=" | ">" | ">=" => parse(format!("{} {}", op, version))?,
+            // "===" は文字列としての一致だが、正規化した一致で近似する
+            "==" | "===" => parse(format!("== {}", version))?,
+            "!=" => parse(format!("== {}", version))?.complement(),
+            "" => {
+                return Err(VersionError::new(
+                    VersionErrorKind::Malformed("version specifier needs an operator"),
+                    s,
+                    span,
+                ));
+            }
+            _ => {
+                return Err(VersionError::new(
+                    VersionErrorKind::UnknownOperator(op.to_string()),
+                    s,
+                    span,
+                ));
+            }
+        };
+        result = result.intersect(&clause_range);
+    }
+    Ok(result)
+}
+
+fn parse_debian(s: &str) -> Result<VersionRange, VersionError> {
+    let mut result = any(Scheme::Dpkg);
+    if s.trim().is_empty() {
+        return Ok(result);
+    }
+    for (start, clause) in split_trimmed(s, ",") {
+        let span = start..start + clause.len();
+        let inner = clause
+            .strip_prefix('(')
+            .and_then(|c| c.strip_suffix(')'))
+            .unwrap_or(clause);
+        let (op, version) = split_operator(inner);
+        let Some(ours) = debian_operator(op) else {
+            return Err(VersionError::new(
+                VersionErrorKind::UnknownOperator(op.to_string()),
+                s,
+                span,
+            ));
+        };
+        let clause_range = clause_range(&format!("{} {}", ours, version), Scheme::Dpkg, s, span)?;
+        result = result.intersect(&clause_range);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ours(s: &str, scheme: Scheme) -> VersionRange {
+        VersionRange::parse_with(s, scheme).unwrap()
+    }
+
+    fn same(a: &VersionRange, b: &VersionRange) -> bool {
+        a.is_subset_of(b) && b.is_subset_of(a)
+    }
+
+    #[test]
+    fn cargo_constraints() {
+        let cases = [
+            ("1.2.3", ">= 1.2.3, < 2.0.0"),
+            ("0.3", ">= 0.3.0, < 0.4.0"),
+            ("=1.2", ">= 1.2.0, < 1.3.0"),
+            ("=1.2.3", "== 1.2.3"),
+            (">1.2", ">= 1.3.0"),
+            ("<=1.2", "< 1.3.0"),
+            (">= 1.2, < 1.5", ">= 1.2.0, < 1.5.0"),
+            ("~1", ">= 1.0.0, < 2.0.0"),
+            ("1.*", ">= 1.0.0, < 2.0.0"),
+            ("*", "*"),
+            ("<1.0.0-0, >2.0.0", "none"),
+        ];
+        for (input, expected) in cases {
+            let parsed = Ecosystem::Cargo.parse(input).unwrap();
+            assert!(
+                same(&parsed, &ours(expected, Scheme::SemVer)),
+                "{} -> {}",
+                input,
+                parsed
+            );
+        }
+    }
+
+    #[test]
+    fn npm_constraints() {
+        let cases = [
+            ("1.2.3", "== 1.2.3"),
+            ("v1.2.3", "== 1.2.3"),
+            ("1.2", ">= 1.2.0, < 1.3.0"),
+            ("1.2.3 - 2.3", ">= 1.2.3, < 2.4.0"),
+            (
+                ">= 1.0.0 <2.0.0 || 3.x",
+                ">= 1.0.0, < 2.0.0 || >= 3.0.0, < 4.0.0",
+            ),
+            ("^0.2.3", ">= 0.2.3, < 0.3.0"),
+            ("> 1.x", ">= 2.0.0"),
+            ("", "*"),
+            ("<0.0.0-0", "none"),
+            ("<0.0.0", "< 0.0.0"),
+        ];
+        for (input, expected) in cases {
+            let parsed = Ecosystem::Npm.parse(input).unwrap();
+            assert!(
+                same(&parsed, &ours(expected, Scheme::SemVer)),
+                "{} -> {}",
+                input,
+                parsed
+            );
+        }
+    }
+
+    #[test]
+    fn pypi_constraints() {
+        let cases = [
+            ("~=1.4.5, !=1.4.7", ">= 1.4.5, < 1.4.7 || > 1.4.7, < 1.5"),
+            ("==1.2.*", ">= 1.2, < 1.3"),
+            (">=1.0,<2.0", ">= 1.0, < 2.0"),
+            ("!=1.*", "< 1.0 || >= 2.0"),
+            ("", "*"),
+        ];
+        for (input, expected) in cases {
+            let parsed = Ecosystem::PyPI.parse(input).unwrap();
+            assert!(
+                same(&parsed, &ours(expected, Scheme::Pep440)),
+                "{} -> {}",
+                input,
+                parsed
+            );
+        }
+        assert!(
+            Ecosystem::PyPI
+                .parse(">=1.0")
+                .unwrap()
+                .compare(&Version::parse_with("1.0.post1", Scheme::Pep440).unwrap())
+        );
+    }
+
+    #[test]
+    fn debian_constraints() {
+        let cases = [
+            (">= 1.0~rc1, << 2.0", ">= 1.0~rc1, << 2.0"),
+            ("(>> 1:1.0), (<= 1:2.0-1)", ">> 1:1.0, <= 1:2.0-1"),
+            ("(> 1.0)", ">= 1.0"),
+            ("= 2.0-1", "== 2.0-1"),
+        ];
+        for (input, expected) in cases {
+            let parsed = Ecosystem::Debian.parse(input).unwrap();
+            assert_eq!(parsed, ours(expected, Scheme::Dpkg), "{}", input);
+        }
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_clause() {
+        let error = Ecosystem::Cargo.parse(">=1.0, !=1.5").unwrap_err();
+        assert_eq!(
+            error.kind,
+            VersionErrorKind::UnknownOperator("!=".to_string())
+        );
+        assert_eq!(error.fragment(), "!=1.5");
+        let error = Ecosystem::Npm.parse("1.0.0 || >= 2.x.1").unwrap_err();
+        assert_eq!(error.kind, VersionErrorKind::InvalidWildcard);
+        assert_eq!(error.fragment(), ">= 2.x.1");
+        let error = Ecosystem::PyPI.parse(">=1.0, 2.0").unwrap_err();
+        assert_eq!(error.fragment(), "2.0");
+        assert!(Ecosystem::Debian.parse("(~= 1.0)").is_err());
+    }
+
+    #[test]
+    fn render_exact_ranges() {
+        let r = |s: &str| VersionRange::from_str(s).unwrap();
+        let cases = [
+            (Ecosystem::Cargo, ">= 1.2, < 2.0", ">=1.2.0, <2.0.0"),
+            (Ecosystem::Cargo, "== 1.0", "=1.0.0"),
+            (Ecosystem::Cargo, "*", "*"),
+            (
+                Ecosystem::Npm,
+                ">= 1.2, < 2.0 || >= 3.0",
+                ">=1.2.0 <2.0.0 || >=3.0.0",
+            ),
+            (Ecosystem::Npm, "none", "<0.0.0-0"),
+            (Ecosystem::PyPI, "< 1.0 || > 1.0", "!=1.0"),
+            (
+                Ecosystem::PyPI,
+                ">= 1.0, < 1.2 || > 1.2, < 2.0",
+                ">=1.0, <2.0, !=1.2",
+            ),
+            (Ecosystem::PyPI, "*", ""),
+            (Ecosystem::Debian, ">> 1.0~rc1", "(>> 1.0~rc1)"),
+            (Ecosystem::Debian, "<< 2.0~beta", "(<< 2.0~beta)"),
+            (Ecosystem::Debian, "== 1.0", "(= 1.0)"),
+        ];
+        for (ecosystem, input, expected) in cases {
+            let rendered = ecosystem.render(&r(input));
+            assert_eq!(rendered.constraint, expected, "{} {}", ecosystem, input);
+            assert!(rendered.is_exact(), "{:?}", rendered);
+            // 書き出した制約を読み戻すと同じ範囲になる
+            let parsed = ecosystem.parse(&rendered.constraint).unwrap();
+            assert!(same(&parsed, &r(input)), "{} {}", ecosystem, input);
+        }
+    }
+
+    #[test]
+    fn render_reports_inexact_ranges() {
+        let r = |s: &str| VersionRange::from_str(s).unwrap();
+        let rendered = Ecosystem::Cargo.render(&r(">= 1.0, < 2.0 || >= 3.0, < 4.0"));
+        assert_eq!(rendered.constraint, ">=1.0.0, <4.0.0");
+        assert!(rendered.inexact.unwrap().contains("alternatives"));
+        let rendered = Ecosystem::PyPI.render(&r("< 1.0 || >= 2.0"));
+        assert_eq!(rendered.constraint, "");
+        assert!(!rendered.is_exact());
+        assert!(!Ecosystem::Debian.render(&VersionRange::empty()).is_exact());
+        // Debian の関係一つには条件を一つしか書けない
+        let rendered = Ecosystem::Debian.render(&r(">> 1.0~rc1, <= 2.0"));
+        assert_eq!(rendered.constraint, "(>> 1.0~rc1)");
+        assert!(
+            rendered
+                .inexact
+                .unwrap()
+                .contains("`(<= 2.0)` must be written")
+        );
+        let rendered = Ecosystem::PyPI.render(&r(">= 1.0.0-foo.1"));
+        assert!(
+            rendered
+                .inexact
+                .unwrap()
+                .contains("not a valid pypi version")
+        );
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for ecosystem in [
+            Ecosystem::Cargo,
+            Ecosystem::Npm,
+            Ecosystem::PyPI,
+            Ecosystem::Debian,
+        ] {
+            assert_eq!(ecosystem.to_string().parse::<Ecosystem>(), Ok(ecosystem));
+        }
+        assert!("maven".parse::<Ecosystem>().is_err());
+    }
+}
@@ -0,0 +1,117 @@
+//! バージョンと範囲の解釈エラー。
+use super::Scheme;
+use std::{fmt, ops::Range};
+
+/// 解釈に失敗した理由の種類。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionErrorKind {
+    /// 文字列が空
+    Empty,
+    /// その位置に使えない文字
+    InvalidCharacter(char),
+    /// 数値であるべき部分が数値でない
+    ExpectedNumber,
+    /// 数値が大きすぎる
+    NumberTooLarge,
+    /// 数値の識別子の先頭に0が付いている
+    LeadingZero,
+    /// 区切り文字の間や後ろの部分が空
+    EmptyComponent,
+    /// 方式固有の構文に従っていない
+    Malformed(&'static str),
+    /// 範囲の条件にバージョンがない
+    MissingVersion,
+    /// 範囲の演算子が不明
+    UnknownOperator(String),
+    /// ワイルドカードが末尾以外にあるか、`==` 以外の演算子と使われている
+    InvalidWildcard,
+    /// `~=` に必要な数のリリース番号がない
+    TooFewComponents,
+    /// 条件が `conflicting` と矛盾していて、どのバージョンも両方を満たせない
+    Unsatisfiable { conflicting: String },
+    /// この方式のバージョンには使えない操作
+    UnsupportedScheme(Scheme),
+}
+
+impl fmt::Display for VersionErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VersionErrorKind::Empty => write!(f, "version is empty"),
+            VersionErrorKind::InvalidCharacter(c) => write!(f, "invalid character {:?}", c),
+            VersionErrorKind::ExpectedNumber => write!(f, "expected a number"),
+            VersionErrorKind::NumberTooLarge => write!(f, "number is too large"),
+            VersionErrorKind::LeadingZero => write!(f, "numeric identifier has a leading zero"),
+            VersionErrorKind::EmptyComponent => write!(f, "empty component"),
+            VersionErrorKind::Malformed(reason) => write!(f, "{}", reason),
+            VersionErrorKind::MissingVersion => write!(f, "missing version after operator"),
+            VersionErrorKind::UnknownOperator(op) => write!(f, "unknown operator `{}`", op),
+            VersionErrorKind::InvalidWildcard => {
+                write!(f, "wildcard must be last and used only with `==`")
+            }
+            VersionErrorKind::TooFewComponents => {
+                write!(f, "compatible release needs at least two release numbers")
+            }
+            VersionErrorKind::Unsatisfiable { conflicting } => {
+                write!(f, "conflicts with `{}`", conflicting)
+            }
+            VersionErrorKind::UnsupportedScheme(scheme) => {
+                write!(f, "operation is not supported for {} versions", scheme)
+            }
+        }
+    }
+}
+
+/// バージョンまたは範囲の解釈エラー。
+///
+/// `span` は `input` の中で問題のある部分のバイト範囲です。
+/// 位置しか示せない場合 (部分が空の場合など) は長さ0になります。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionError {
+    pub kind: VersionErrorKind,
+    pub input: String,
+    pub span: Range<usize>,
+}
+
+impl VersionError {
+    pub fn new(kind: VersionErrorKind, input: &str, span: Range<usize>) -> Self {
+        VersionError {
+            kind,
+            input: input.to_string(),
+            span,
+        }
+    }
+
+    /// 問題のある部分の文字列を返します。
+    pub fn fragment(&self) -> &str {
+        &self.input[self.span.clone()]
+    }
+
+    /// 長い文字列の `offset` バイト目から始まる部分で起きたエラーとして位置を付け替えます。
+    pub(crate) fn within(self, input: &str, offset: usize) -> Self {
+        VersionError {
+            kind: self.kind,
+            input: input.to_string(),
+            span: self.span.start + offset..self.span.end + offset,
+        }
+    }
+}
+
+impl fmt::Display for VersionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            VersionErrorKind::Unsatisfiable { conflicting } => write!(
+                f,
+                "`{}` conflicts with `{}`: no version can satisfy both",
+                self.fragment(),
+                conflicting
+            ),
+            kind => write!(
+                f,
+                "{} at {}..{} in `{}`",
+                kind, self.span.start, self.span.end, self.input
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VersionError {}
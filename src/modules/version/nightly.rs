@@ -0,0 +1,409 @@
+//! ナイトリービルド用のバージョンの自動生成。
+//!
+//! `git describe` の出力からは、直前のタグと次のリリースの間に並ぶ開発版
+//! (`1.4.0-dev.37+g1a2b3c`) を、日付からはカレンダーバージョン (`2026.10.17`) を作ります。
+use super::bump::VersionPart;
+use super::error::{VersionError, VersionErrorKind};
+use super::{Identifier, Scheme, Version};
+use std::{
+    fmt, io,
+    path::Path,
+    process::Command,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// `git describe --tags --long --dirty` の出力を分解したもの。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitDescribe {
+    /// 直前のタグ
+    pub tag: String,
+    /// タグからのコミット数
+    pub distance: u32,
+    /// 短縮されたコミットハッシュ (先頭の `g` を除く)
+    pub commit: String,
+    /// 作業ツリーに未コミットの変更があるか
+    pub dirty: bool,
+}
+
+impl FromStr for GitDescribe {
+    type Err = VersionError;
+
+    /// `v1.3.2-37-g1a2b3c-dirty` のような文字列を後ろから解釈します。
+    ///
+    /// タグ自体に `-` が含まれていてもかまいません。`--long` なしで
+    /// タグの上にいる場合の出力 (`v1.3.2`) はコミット数0として扱います。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (rest, dirty) = match s.strip_suffix("-dirty") {
+            Some(rest) => (rest, true),
+            None => (s, false),
+        };
+        let mut parts = rest.rsplitn(3, '-');
+        let (commit, distance, tag) = (parts.next(), parts.next(), parts.next());
+        let described = match (tag, distance, commit.and_then(|c| c.strip_prefix('g'))) {
+            (Some(tag), Some(distance), Some(commit))
+                if !commit.is_empty() && commit.chars().all(|c| c.is_ascii_hexdigit()) =>
+            {
+                let start = tag.len() + 1;
+                let distance = distance.parse::<u32>().map_err(|_| {
+                    VersionError::new(
+                        VersionErrorKind::ExpectedNumber,
+                        s,
+                        start..start + distance.len(),
+                    )
+                })?;
+                GitDescribe {
+                    tag: tag.to_string(),
+                    distance,
+                    commit: commit.to_string(),
+                    dirty,
+                }
+            }
+            _ => GitDescribe {
+                tag: rest.to_string(),
+                distance: 0,
+                commit: String::new(),
+                dirty,
+            },
+        };
+        if described.tag.is_empty() {
+            return Err(VersionError::new(VersionErrorKind::Empty, s, 0..0));
+        }
+        Ok(described)
+    }
+}
+
+/// git のチェックアウトからバージョンを作れなかった理由。
+#[derive(Debug)]
+pub enum GitVersionError {
+    /// git を実行できなかった
+    Io(io::Error),
+    /// git がエラーで終了した (標準エラー出力の内容)
+    Git(String),
+    /// タグや出力がバージョンとして解釈できなかった
+    Version(VersionError),
+}
+
+impl fmt::Display for GitVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GitVersionError::Io(e) => write!(f, "failed to run git: {}", e),
+            GitVersionError::Git(message) => write!(f, "git describe failed: {}", message),
+            GitVersionError::Version(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for GitVersionError {}
+
+impl From<VersionError> for GitVersionError {
+    fn from(e: VersionError) -> Self {
+        GitVersionError::Version(e)
+    }
+}
+
+impl Version {
+    /// git のチェックアウトの状態から開発版のバージョンを作ります。
+    ///
+    /// ローカルの `git` で `git describe --tags --long --dirty` を実行し、
+    /// [`Version::from_git_describe`] と同じ規則でバージョンにします。
+    ///
+    /// # 引数
+    ///
+    /// * `dir` - チェックアウトのディレクトリ。
+    /// * `part` - 直前のタグがリリース版の場合に、次のリリースとして繰り上げる部分。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(Version)` - 作られたバージョン。
+    /// * `Err(GitVersionError)` - git が使えないか、タグが見つからないか、解釈できない場合。
+    pub fn from_git(dir: &Path, part: VersionPart) -> Result<Version, GitVersionError> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["describe", "--tags", "--long", "--dirty"])
+            .output()
+            .map_err(GitVersionError::Io)?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(GitVersionError::Git(stderr.trim().to_string()));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(Version::from_git_describe(&stdout, part)?)
+    }
+
+    /// `git describe` の出力から開発版のバージョンを作ります。
+    ///
+    /// * `v1.3.2-37-g1a2b3c` → `1.4.0-dev.37+g1a2b3c` (`part` が `Minor` の場合)
+    /// * `v1.4.0-rc.1-5-g1a2b3c` → `1.4.0-rc.1.dev.5+g1a2b3c`
+    /// * `v1.3.2-0-g1a2b3c` → `1.3.2` (タグの上で変更がない場合)
+    ///
+    /// 先頭の `v` は取り除かれます。
+    ///
+    /// # 引数
+    ///
+    /// * `describe` - `git describe --tags --long --dirty` の出力。
+    /// * `part` - 直前のタグがリリース版の場合に、次のリリースとして繰り上げる部分。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(Version)` - 作られたバージョン。
+    /// * `Err(VersionError)` - 出力やタグが解釈できない場合。
+    pub fn from_git_describe(describe: &str, part: VersionPart) -> Result<Version, VersionError> {
+        let described = GitDescribe::from_str(describe)?;
+        let tag = described.tag.strip_prefix('v').unwrap_or(&described.tag);
+        Version::from_str(tag)?.dev_build(
+            described.distance,
+            &described.commit,
+            described.dirty,
+            part,
+        )
+    }
+
+    /// このリリース (またはプレリリース) から `distance` コミット進んだ開発版を返します。
+    ///
+    /// 開発版は、このバージョンより後で次のリリースより前に並びます。
+    /// リリース版は `part` を繰り上げてから `dev.N` を、プレリリースは
+    /// そのまま識別子の後ろに `dev.N` を加えます。コミットは `g<hash>`、
+    /// 未コミットの変更は `dirty` としてビルドメタデータに入ります。
+    ///
+    /// # 引数
+    ///
+    /// * `distance` - このバージョンからのコミット数。
+    /// * `commit` - 短縮されたコミットハッシュ。空ならビルドメタデータに入れません。
+    /// * `dirty` - 未コミットの変更があるか。
+    /// * `part` - このバージョンがリリース版の場合に、次のリリースとして繰り上げる部分。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(Version)` - 開発版。`distance` が0で変更がなければ、ビルドメタデータを除いたこのバージョン。
+    /// * `Err(VersionError)` - 繰り上げられない方式や値の場合。
+    pub fn dev_build(
+        &self,
+        distance: u32,
+        commit: &str,
+        dirty: bool,
+        part: VersionPart,
+    ) -> Result<Version, VersionError> {
+        if distance == 0 && !dirty {
+            return self.rebuilt(&self.nums, &self.pre, &[]);
+        }
+        let base = if self.pre.is_empty() {
+            self.bump(part)?
+        } else {
+            self.clone()
+        };
+        let mut pre = base.pre.clone();
+        pre.push(Identifier::AlphaNumeric("dev".to_string()));
+        pre.push(Identifier::Numeric(distance.into()));
+        let mut build = Vec::new();
+        if !commit.is_empty() {
+            build.push(format!("g{}", commit));
+        }
+        if dirty {
+            build.push("dirty".to_string());
+        }
+        base.rebuilt(&base.nums, &pre, &build)
+    }
+
+    /// 日付からカレンダーバージョン (`YYYY.MM.DD`) を作ります。
+    ///
+    /// # 引数
+    ///
+    /// * `year` - 西暦年 (4桁)。
+    /// * `month` - 月 (1〜12)。
+    /// * `day` - 日 (その月の日数まで)。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(Version)` - `2026.10.17` のような CalVer 方式のバージョン。
+    /// * `Err(VersionError)` - 存在しない日付の場合、問題のある部分の位置を含む。
+    pub fn from_date(year: u32, month: u32, day: u32) -> Result<Version, VersionError> {
+        let string = format!("{:04}.{:02}.{:02}", year, month, day);
+        let version = Version::parse_with(&string, Scheme::CalVer)?;
+        if !(1..=days_in_month(year, month)).contains(&day) {
+            let start = string.rfind('.').map_or(0, |i| i + 1);
+            return Err(VersionError::new(
+                VersionErrorKind::Malformed("day is out of range for the month"),
+                &string,
+                start..string.len(),
+            ));
+        }
+        Ok(version)
+    }
+
+    /// 時刻の UTC での日付からカレンダーバージョンを作ります。
+    pub fn from_system_time(time: SystemTime) -> Result<Version, VersionError> {
+        // エポックより前の時刻はエポックの日付として扱う
+        let days = time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() / 86_400);
+        let (year, month, day) = civil_from_days(days);
+        Version::from_date(year, month, day)
+    }
+
+    /// 今日 (UTC) の日付のカレンダーバージョンを返します。
+    pub fn today() -> Result<Version, VersionError> {
+        Version::from_system_time(SystemTime::now())
+    }
+}
+
+/// うるう年かを判定します。
+fn is_leap_year(year: u32) -> bool {
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+
+/// 月の日数を返します。存在しない月には0を返します。
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+/// 1970-01-01 からの日数を (年, 月, 日) にします。
+fn civil_from_days(days: u64) -> (u32, u32, u32) {
+    let mut year = 1970;
+    let mut days = days;
+    loop {
+        let length = if is_leap_year(year) { 366 } else { 365 };
+        if days < length {
+            break;
+        }
+        days -= length;
+        year += 1;
+    }
+    let mut month = 1;
+    while days >= u64::from(days_in_month(year, month)) {
+        days -= u64::from(days_in_month(year, month));
+        month += 1;
+    }
+    (year, month, days as u32 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn v(s: &str) -> Version {
+        Version::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn describe_output_is_split_from_the_right() {
+        let described: GitDescribe = "v1.4.0-rc.1-5-g1a2b3c-dirty".parse().unwrap();
+        assert_eq!(
+            described,
+            GitDescribe {
+                tag: "v1.4.0-rc.1".to_string(),
+                distance: 5,
+                commit: "1a2b3c".to_string(),
+                dirty: true,
+            }
+        );
+        let described: GitDescribe = "v1.3.2\n".parse().unwrap();
+        assert_eq!(described.tag, "v1.3.2");
+        assert_eq!(described.distance, 0);
+        assert!("".parse::<GitDescribe>().is_err());
+        let error = "v1.0-99999999999-g1a2b".parse::<GitDescribe>().unwrap_err();
+        assert_eq!(error.fragment(), "99999999999");
+    }
+
+    #[test]
+    fn dev_versions_from_describe() {
+        let cases = [
+            (
+                "v1.3.2-37-g1a2b3c",
+                VersionPart::Minor,
+                "1.4.0-dev.37+g1a2b3c",
+            ),
+            (
+                "1.3.2-37-g1a2b3c",
+                VersionPart::Patch,
+                "1.3.3-dev.37+g1a2b3c",
+            ),
+            (
+                "v1.4.0-rc.1-5-g1a2b3c",
+                VersionPart::Minor,
+                "1.4.0-rc.1.dev.5+g1a2b3c",
+            ),
+            ("v1.3.2-0-g1a2b3c", VersionPart::Minor, "1.3.2"),
+            (
+                "v1.3.2-0-g1a2b3c-dirty",
+                VersionPart::Patch,
+                "1.3.3-dev.0+g1a2b3c.dirty",
+            ),
+            ("v1.3.2", VersionPart::Minor, "1.3.2"),
+        ];
+        for (describe, part, expected) in cases {
+            let version = Version::from_git_describe(describe, part).unwrap();
+            assert_eq!(version.as_str(), expected, "{}", describe);
+        }
+        assert!(Version::from_git_describe("nightly-3-g1a2b3c", VersionPart::Minor).is_err());
+    }
+
+    #[test]
+    fn dev_builds_sort_between_releases() {
+        let dev = |describe| Version::from_git_describe(describe, VersionPart::Minor).unwrap();
+        let ordered = [
+            v("1.3.2"),
+            dev("v1.3.2-1-gaaaaaa"),
+            dev("v1.3.2-2-gbbbbbb"),
+            dev("v1.3.2-10-gcccccc"),
+            v("1.4.0-rc.1"),
+            dev("v1.4.0-rc.1-3-gdddddd"),
+            v("1.4.0-rc.2"),
+            v("1.4.0"),
+        ];
+        for pair in ordered.windows(2) {
+            assert!(pair[0] < pair[1], "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn calendar_versions_from_dates() {
+        let version = Version::from_date(2026, 10, 17).unwrap();
+        assert_eq!(version.as_str(), "2026.10.17");
+        assert_eq!(version.scheme, Scheme::CalVer);
+        assert!(Version::from_date(2026, 1, 5).unwrap() < version);
+        assert!(Version::from_date(2024, 2, 29).is_ok());
+        let error = Version::from_date(2026, 2, 29).unwrap_err();
+        assert_eq!(error.fragment(), "29");
+        let error = Version::from_date(2026, 13, 1).unwrap_err();
+        assert_eq!(error.fragment(), "13");
+    }
+
+    #[test]
+    fn dates_from_system_time() {
+        let at = |days: u64| UNIX_EPOCH + Duration::from_secs(days * 86_400 + 3_600);
+        assert_eq!(
+            Version::from_system_time(at(0)).unwrap().as_str(),
+            "1970.01.01"
+        );
+        assert_eq!(
+            Version::from_system_time(at(11_016)).unwrap().as_str(),
+            "2000.02.29"
+        );
+        assert_eq!(
+            Version::from_system_time(at(20_743)).unwrap().as_str(),
+            "2026.10.17"
+        );
+    }
+
+    #[test]
+    fn git_errors_are_reported() {
+        let dir = std::env::temp_dir().join(format!(
+            "ipkg-nightly-not-a-repository-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let result = Version::from_git(&dir, VersionPart::Minor);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(result.is_err());
+    }
+}
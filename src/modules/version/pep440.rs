@@ -0,0 +1,359 @@
+//! PEP 440 (Python パッケージ) 互換のバージョン解釈と比較。
+use super::error::{VersionError, VersionErrorKind};
+use regex::{Match, Regex};
+use std::{cmp::Ordering, fmt, sync::LazyLock};
+
+static PEP440_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?ix)^
+        v?
+        (?:(?P<epoch>[0-9]+)!)?
+        (?P<release>[0-9]+(?:\.[0-9]+)*)
+        (?P<pre>[-_.]?(?P<pre_l>alpha|a|beta|b|preview|pre|c|rc)[-_.]?(?P<pre_n>[0-9]+)?)?
+        (?P<post>(?:-(?P<post_n1>[0-9]+))|(?:[-_.]?(?P<post_l>post|rev|r)[-_.]?(?P<post_n2>[0-9]+)?))?
+        (?P<dev>[-_.]?(?P<dev_l>dev)[-_.]?(?P<dev_n>[0-9]+)?)?
+        (?:\+(?P<local>[a-z0-9]+(?:[-_.][a-z0-9]+)*))?
+        $",
+    )
+    .unwrap()
+});
+
+/// プレリリースの種類。宣言順がそのまま優先順位になっています。
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum PreKind {
+    Alpha,
+    Beta,
+    Rc,
+}
+
+/// ローカルバージョンの区切られた各部分。
+///
+/// 英数字の部分は数値の部分よりも前に並びます。
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub enum LocalSegment {
+    Alpha(String),
+    Numeric(u64),
+}
+
+/// 正規化済みの PEP 440 バージョン。
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Pep440Version {
+    pub epoch: u64,
+    pub release: Vec<u64>,
+    pub pre: Option<(PreKind, u64)>,
+    pub post: Option<u64>,
+    pub dev: Option<u64>,
+    pub local: Vec<LocalSegment>,
+}
+
+/// `whole` の `start` バイト目から始まる数字の並び `s` を数値にします。
+fn parse_number(s: &str, start: usize, whole: &str) -> Result<u64, VersionError> {
+    s.parse::<u64>().map_err(|_| {
+        VersionError::new(
+            VersionErrorKind::NumberTooLarge,
+            whole,
+            start..start + s.len(),
+        )
+    })
+}
+
+/// PEP 440 形式のバージョン文字列を解釈します。
+///
+/// 大文字小文字、区切り文字、省略された数字などの揺れは
+/// PEP 440 の正規化規則に従って吸収されます。
+///
+/// # 引数
+///
+/// * `s` - 解釈するバージョン文字列。
+///
+/// # 戻り値
+///
+/// * `Ok(Pep440Version)` - 有効な PEP 440 バージョンの場合。
+/// * `Err(VersionError)` - 構文が不正な場合、問題のある位置を含む。
+pub fn parse(s: &str) -> Result<Pep440Version, VersionError> {
+    let caps = PEP440_REGEX.captures(s).ok_or_else(|| {
+        VersionError::new(
+            VersionErrorKind::Malformed("not a valid PEP 440 version"),
+            s,
+            0..s.len(),
+        )
+    })?;
+    let number = |name: &str| {
+        caps.name(name)
+            .map(|m: Match| parse_number(m.as_str(), m.start(), s))
+    };
+
+    let epoch = number("epoch").transpose()?.unwrap_or(0);
+    let release_match = caps.name("release").expect("release is not optional");
+    let mut offset = release_match.start();
+    let release = release_match
+        .as_str()
+        .split('.')
+        .map(|n| {
+            let number = parse_number(n, offset, s);
+            offset += n.len() + 1;
+            number
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let pre = match caps.name("pre_l") {
+        Some(letter) => {
+            let kind = match letter.as_str().to_lowercase().as_str() {
+                "a" | "alpha" => PreKind::Alpha,
+                "b" | "beta" => PreKind::Beta,
+                _ => PreKind::Rc,
+            };
+            Some((kind, number("pre_n").transpose()?.unwrap_or(0)))
+        }
+        None => None,
+    };
+    let post = if caps.name("post").is_some() {
+        Some(
+            number("post_n1")
+                .or_else(|| number("post_n2"))
+                .transpose()?
+                .unwrap_or(0),
+        )
+    } else {
+        None
+    };
+    let dev = if caps.name("dev").is_some() {
+        Some(number("dev_n").transpose()?.unwrap_or(0))
+    } else {
+        None
+    };
+    let local = match caps.name("local") {
+        Some(local) => local
+            .as_str()
+            .split(['-', '_', '.'])
+            .map(|part| match part.parse::<u64>() {
+                Ok(n) if part.chars().all(|c| c.is_ascii_digit()) => LocalSegment::Numeric(n),
+                _ => LocalSegment::Alpha(part.to_lowercase()),
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Ok(Pep440Version {
+        epoch,
+        release,
+        pre,
+        post,
+        dev,
+        local,
+    })
+}
+
+/// プレリリース部分の比較用の値。
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum PreKey {
+    /// プレリリースもポストリリースも持たない開発版 (例: 1.0.dev0)
+    DevOnly,
+    Pre(PreKind, u64),
+    Release,
+}
+
+/// 比較用のキー (エポック、リリース、プレ、ポスト、開発版、ローカル)。
+type CmpKey<'a> = (
+    u64,
+    &'a [u64],
+    PreKey,
+    Option<u64>,
+    (bool, u64),
+    Option<&'a [LocalSegment]>,
+);
+
+impl Pep440Version {
+    /// 比較に使うキーを返します。
+    ///
+    /// リリース番号の末尾の0は比較に影響しません。
+    fn key(&self) -> CmpKey<'_> {
+        let release_len = self
+            .release
+            .iter()
+            .rposition(|&n| n != 0)
+            .map_or(0, |i| i + 1);
+        let pre = match self.pre {
+            Some((kind, n)) => PreKey::Pre(kind, n),
+            None if self.post.is_none() && self.dev.is_some() => PreKey::DevOnly,
+            None => PreKey::Release,
+        };
+        // 開発版は同じバージョンのどのリリースよりも前に並ぶ
+        let dev = (self.dev.is_none(), self.dev.unwrap_or(0));
+        let local = if self.local.is_empty() {
+            None
+        } else {
+            Some(self.local.as_slice())
+        };
+        (
+            self.epoch,
+            &self.release[..release_len],
+            pre,
+            self.post,
+            dev,
+            local,
+        )
+    }
+}
+
+impl Pep440Version {
+    /// 正規形を返します。
+    ///
+    /// 比較に影響しないリリース番号の末尾の0は取り除かれます。
+    pub fn canonical(&self) -> String {
+        let release_len = self
+            .release
+            .iter()
+            .rposition(|&n| n != 0)
+            .map_or(1, |i| i + 1);
+        Pep440Version {
+            release: self.release[..release_len].to_vec(),
+            ..self.clone()
+        }
+        .to_string()
+    }
+}
+
+impl Ord for Pep440Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl PartialOrd for Pep440Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Pep440Version {
+    /// PEP 440 の正規形で出力します。
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+        let release: Vec<String> = self.release.iter().map(u64::to_string).collect();
+        write!(f, "{}", release.join("."))?;
+        if let Some((kind, n)) = self.pre {
+            let letter = match kind {
+                PreKind::Alpha => "a",
+                PreKind::Beta => "b",
+                PreKind::Rc => "rc",
+            };
+            write!(f, "{}{}", letter, n)?;
+        }
+        if let Some(n) = self.post {
+            write!(f, ".post{}", n)?;
+        }
+        if let Some(n) = self.dev {
+            write!(f, ".dev{}", n)?;
+        }
+        if !self.local.is_empty() {
+            let local: Vec<String> = self
+                .local
+                .iter()
+                .map(|segment| match segment {
+                    LocalSegment::Alpha(s) => s.clone(),
+                    LocalSegment::Numeric(n) => n.to_string(),
+                })
+                .collect();
+            write!(f, "+{}", local.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// PEP 440 形式のバージョン文字列同士を比較します。
+///
+/// 構文が不正な文字列は、不正な方が常に小さいものとして扱います。
+pub fn compare(a: &str, b: &str) -> Ordering {
+    match (parse(a), parse(b)) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Greater,
+        (Err(_), Ok(_)) => Ordering::Less,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_ordering() {
+        // PEP 440 "Summary of permitted suffixes and relative ordering" の例
+        let ordered = [
+            "1.dev0",
+            "1.0.dev456",
+            "1.0a1",
+            "1.0a2.dev456",
+            "1.0a12.dev456",
+            "1.0a12",
+            "1.0b1.dev456",
+            "1.0b2",
+            "1.0b2.post345.dev456",
+            "1.0b2.post345",
+            "1.0rc1.dev456",
+            "1.0rc1",
+            "1.0",
+            "1.0+abc.5",
+            "1.0+abc.7",
+            "1.0+5",
+            "1.0.post456.dev34",
+            "1.0.post456",
+            "1.0.15",
+            "1.1.dev1",
+        ];
+        for (i, a) in ordered.iter().enumerate() {
+            for (j, b) in ordered.iter().enumerate() {
+                assert_eq!(compare(a, b), i.cmp(&j), "{} <=> {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn normalization() {
+        let cases = [
+            ("1.0", "1.0"),
+            ("v1.0", "1.0"),
+            ("1.0ALPHA1", "1.0a1"),
+            ("1.0-preview-2", "1.0rc2"),
+            ("1.0c1", "1.0rc1"),
+            ("1.0.beta", "1.0b0"),
+            ("1.0-1", "1.0.post1"),
+            ("1.0.rev", "1.0.post0"),
+            ("1.0_r4", "1.0.post4"),
+            ("1.0dev", "1.0.dev0"),
+            ("1.0+Ubuntu-1", "1.0+ubuntu.1"),
+            ("0!1.0", "1.0"),
+            ("2!01.02", "2!1.2"),
+        ];
+        for (input, normalized) in cases {
+            assert_eq!(parse(input).unwrap().to_string(), normalized, "{}", input);
+        }
+    }
+
+    #[test]
+    fn trailing_zeros_and_epoch() {
+        assert_eq!(compare("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare("1!0.1", "2.0"), Ordering::Greater);
+        assert_eq!(compare("1.0a", "1.0a0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn canonical_form() {
+        assert_eq!(parse("1.0.0").unwrap().canonical(), "1");
+        assert_eq!(parse("v1.2.0RC1").unwrap().canonical(), "1.2rc1");
+        assert_eq!(parse("0.0").unwrap().canonical(), "0");
+        assert_eq!(parse("1.0.0-1").unwrap().canonical(), "1.post1");
+    }
+
+    #[test]
+    fn invalid_versions() {
+        for s in ["", "1.0+", "1.0-", "foo", "1.0+local+1", "1..0", "1.0a1b2"] {
+            assert!(parse(s).is_err(), "{}", s);
+        }
+        let error = parse("1.99999999999999999999.0").unwrap_err();
+        assert_eq!(error.kind, VersionErrorKind::NumberTooLarge);
+        assert_eq!(error.fragment(), "99999999999999999999");
+    }
+}
@@ -0,0 +1,357 @@
+//! rpm 互換のバージョン比較 (`rpmvercmp`)。
+//!
+//! `[epoch:]version[-release]` 形式を扱います。
+//! リリースが省略された場合は空文字列として比較するため、
+//! `1.0` は `1.0-1` より小さくなります。
+use super::error::{VersionError, VersionErrorKind};
+use std::cmp::Ordering;
+
+/// rpm 形式のバージョン文字列を構成要素に分解したもの。
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RpmVersion<'a> {
+    pub epoch: u32,
+    pub version: &'a str,
+    pub release: &'a str,
+}
+
+fn is_version_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "._+~^".contains(c)
+}
+
+/// rpm 形式のバージョン文字列を分解し、構文を検証します。
+///
+/// # 引数
+///
+/// * `s` - 検証するバージョン文字列。
+///
+/// # 戻り値
+///
+/// * `Ok(RpmVersion)` - 有効な rpm バージョンの場合。
+/// * `Err(VersionError)` - 構文が不正な場合、問題のある位置を含む。
+pub fn parse(s: &str) -> Result<RpmVersion<'_>, VersionError> {
+    let error = |kind, span| Err(VersionError::new(kind, s, span));
+    if s.is_empty() {
+        return error(VersionErrorKind::Empty, 0..0);
+    }
+    let (epoch, rest, rest_start) = match s.split_once(':') {
+        Some((epoch_str, rest)) => {
+            let span = 0..epoch_str.len();
+            if epoch_str.is_empty() || !epoch_str.chars().all(|c| c.is_ascii_digit()) {
+                return error(VersionErrorKind::ExpectedNumber, span);
+            }
+            let Ok(epoch) = epoch_str.parse::<u32>() else {
+                return error(VersionErrorKind::NumberTooLarge, span);
+            };
+            (epoch, rest, epoch_str.len() + 1)
+        }
+        None => (0, s, 0),
+    };
+    let (version, release) = match rest.rsplit_once('-') {
+        Some((version, release)) => {
+            if release.is_empty() {
+                return error(VersionErrorKind::EmptyComponent, s.len()..s.len());
+            }
+            (version, release)
+        }
+        None => (rest, ""),
+    };
+    if version.is_empty() {
+        return error(VersionErrorKind::EmptyComponent, rest_start..rest_start);
+    }
+    // バージョンとリリースの間の "-" も含めて、使えない文字を探す
+    if let Some((i, c)) = rest
+        .char_indices()
+        .find(|&(i, c)| !is_version_char(c) && i != version.len())
+    {
+        let i = rest_start + i;
+        return error(VersionErrorKind::InvalidCharacter(c), i..i + c.len_utf8());
+    }
+    Ok(RpmVersion {
+        epoch,
+        version,
+        release,
+    })
+}
+
+/// rpm の `rpmvercmp` と同じ規則で文字列を比較します。
+///
+/// 英数字以外は区切りとして読み飛ばし、数字の並びは数値として、
+/// 英字の並びは辞書順で比較します。数字の並びは英字の並びより大きく、
+/// `~` は終端よりも前、`^` は終端よりも後に並びます。
+pub fn rpmvercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let a = a.as_bytes();
+    let b = b.as_bytes();
+    let (mut i, mut j) = (0, 0);
+    let is_sep = |c: u8| !c.is_ascii_alphanumeric() && c != b'~' && c != b'^';
+
+    while i < a.len() || j < b.len() {
+        while i < a.len() && is_sep(a[i]) {
+            i += 1;
+        }
+        while j < b.len() && is_sep(b[j]) {
+            j += 1;
+        }
+
+        // "~" は何よりも前に並ぶ
+        let (ac, bc) = (a.get(i).copied(), b.get(j).copied());
+        if ac == Some(b'~') || bc == Some(b'~') {
+            if ac != Some(b'~') {
+                return Ordering::Greater;
+            }
+            if bc != Some(b'~') {
+                return Ordering::Less;
+            }
+            i += 1;
+            j += 1;
+            continue;
+        }
+        // "^" は終端よりも後、それ以外よりも前に並ぶ
+        if ac == Some(b'^') || bc == Some(b'^') {
+            if ac.is_none() {
+                return Ordering::Less;
+            }
+            if bc.is_none() {
+                return Ordering::Greater;
+            }
+            if ac != Some(b'^') {
+                return Ordering::Greater;
+            }
+            if bc != Some(b'^') {
+                return Ordering::Less;
+            }
+            i += 1;
+            j += 1;
+            continue;
+        }
+        if i >= a.len() || j >= b.len() {
+            break;
+        }
+
+        let is_num = a[i].is_ascii_digit();
+        let same_kind = |c: u8| {
+            if is_num {
+                c.is_ascii_digit()
+            } else {
+                c.is_ascii_alphabetic()
+            }
+        };
+        let (start_a, start_b) = (i, j);
+        while i < a.len() && same_kind(a[i]) {
+            i += 1;
+        }
+        while j < b.len() && same_kind(b[j]) {
+            j += 1;
+        }
+        // 種類の異なる並び同士では、数字の並びが大きい
+        if start_b == j {
+            return if is_num {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            };
+        }
+
+        let mut seg_a = &a[start_a..i];
+        let mut seg_b = &b[start_b..j];
+        if is_num {
+            while seg_a.first() == Some(&b'0') {
+                seg_a = &seg_a[1..];
+            }
+            while seg_b.first() == Some(&b'0') {
+                seg_b = &seg_b[1..];
+            }
+            match seg_a.len().cmp(&seg_b.len()) {
+                Ordering::Equal => {}
+                ord => return ord,
+            }
+        }
+        match seg_a.cmp(seg_b) {
+            Ordering::Equal => {}
+            ord => return ord,
+        }
+    }
+
+    match (i >= a.len(), j >= b.len()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        _ => Ordering::Greater,
+    }
+}
+
+/// `rpmvercmp` で等しくなる文字列が同じ結果になるように正規化します。
+///
+/// 区切り文字は "." にまとめ (英字の直後の数字は区切らずに続け)、
+/// 数字の並びは先頭の0を取り除きます。
+fn canonical_part(s: &str) -> String {
+    let mut result = String::new();
+    // 直前に出力した並びの種類 (Some(true) は数字、Some(false) は英字)
+    let mut last_kind: Option<bool> = None;
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c == '~' || c == '^' {
+            result.push(c);
+            last_kind = None;
+            chars.next();
+        } else if c.is_ascii_alphanumeric() {
+            let is_num = c.is_ascii_digit();
+            let mut segment = String::new();
+            while let Some(&c) = chars.peek() {
+                if (is_num && c.is_ascii_digit()) || (!is_num && c.is_ascii_alphabetic()) {
+                    segment.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            if is_num {
+                let trimmed = segment.trim_start_matches('0');
+                segment = if trimmed.is_empty() { "0" } else { trimmed }.to_string();
+            }
+            if last_kind.is_some_and(|last_is_num| last_is_num || !is_num) {
+                result.push('.');
+            }
+            result.push_str(&segment);
+            last_kind = Some(is_num);
+        } else {
+            chars.next();
+        }
+    }
+    result
+}
+
+/// 分解済みの rpm バージョンの正規形を返します。
+///
+/// 比較して等しいバージョン同士は、同じ正規形になります。
+pub fn canonical(v: &RpmVersion) -> String {
+    let mut result = String::new();
+    if v.epoch != 0 {
+        result.push_str(&format!("{}:", v.epoch));
+    }
+    result.push_str(&canonical_part(v.version));
+    let release = canonical_part(v.release);
+    if !release.is_empty() {
+        result.push('-');
+        result.push_str(&release);
+    }
+    result
+}
+
+/// 分解済みの rpm バージョン同士を比較します。
+pub fn compare_parsed(a: &RpmVersion, b: &RpmVersion) -> Ordering {
+    a.epoch
+        .cmp(&b.epoch)
+        .then_with(|| rpmvercmp(a.version, b.version))
+        .then_with(|| rpmvercmp(a.release, b.release))
+}
+
+/// rpm 形式のバージョン文字列同士を比較します。
+///
+/// 構文が不正な文字列は、不正な方が常に小さいものとして扱います。
+pub fn compare(a: &str, b: &str) -> Ordering {
+    match (parse(a), parse(b)) {
+        (Ok(a), Ok(b)) => compare_parsed(&a, &b),
+        (Ok(_), Err(_)) => Ordering::Greater,
+        (Err(_), Ok(_)) => Ordering::Less,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rpmvercmp_cases() {
+        // rpm の tests/rpmvercmp.at より
+        let cases = [
+            ("1.0", "1.0", Ordering::Equal),
+            ("1.0", "2.0", Ordering::Less),
+            ("2.0.1", "2.0.1", Ordering::Equal),
+            ("2.0", "2.0.1", Ordering::Less),
+            ("2.0.1a", "2.0.1", Ordering::Greater),
+            ("5.5p1", "5.5p2", Ordering::Less),
+            ("5.5p10", "5.5p1", Ordering::Greater),
+            ("10xyz", "10.1xyz", Ordering::Less),
+            ("xyz10", "xyz10.1", Ordering::Less),
+            ("xyz.4", "8", Ordering::Less),
+            ("xyz.4", "2", Ordering::Less),
+            ("5.5p2", "5.6p1", Ordering::Less),
+            ("5.6p1", "6.5p1", Ordering::Less),
+            ("6.0.rc1", "6.0", Ordering::Greater),
+            ("10b2", "10a1", Ordering::Greater),
+            ("1.0aa", "1.0a", Ordering::Greater),
+            ("10.0001", "10.1", Ordering::Equal),
+            ("10.0001", "10.0039", Ordering::Less),
+            ("4.999.9", "5.0", Ordering::Less),
+            ("20101121", "20101122", Ordering::Less),
+            ("2_0", "2_0", Ordering::Equal),
+            ("2.0", "2_0", Ordering::Equal),
+            ("a", "a", Ordering::Equal),
+            ("a+", "a_", Ordering::Equal),
+            ("+", "_", Ordering::Equal),
+            ("1.0~rc1", "1.0", Ordering::Less),
+            ("1.0~rc1", "1.0~rc2", Ordering::Less),
+            ("1.0~rc1~git123", "1.0~rc1", Ordering::Less),
+            ("1.0^", "1.0", Ordering::Greater),
+            ("1.0^git1", "1.0", Ordering::Greater),
+            ("1.0^git1", "1.01", Ordering::Less),
+            ("1.0^20160101", "1.0.1", Ordering::Less),
+            ("1.0~rc1^git1", "1.0~rc1", Ordering::Greater),
+            ("1.0^git1~pre", "1.0^git1", Ordering::Less),
+        ];
+        for (a, b, expected) in cases {
+            assert_eq!(rpmvercmp(a, b), expected, "{} <=> {}", a, b);
+            assert_eq!(rpmvercmp(b, a), expected.reverse(), "{} <=> {}", b, a);
+        }
+    }
+
+    #[test]
+    fn epoch_version_release() {
+        assert_eq!(compare("1:1.0-1", "2.0-1"), Ordering::Greater);
+        assert_eq!(compare("0:1.0-1", "1.0-1"), Ordering::Equal);
+        assert_eq!(compare("1.0-1.el9", "1.0-2.el9"), Ordering::Less);
+        assert_eq!(compare("1.0", "1.0-1"), Ordering::Less);
+        assert_eq!(
+            parse("2:3.4.5-6.fc40"),
+            Ok(RpmVersion {
+                epoch: 2,
+                version: "3.4.5",
+                release: "6.fc40",
+            })
+        );
+    }
+
+    #[test]
+    fn canonical_form() {
+        let cases = [
+            ("1.0", "1.0"),
+            ("1_0", "1.0"),
+            ("0:01.0-1", "1.0-1"),
+            ("5.5p1", "5.5.p1"),
+            ("5.5.p1", "5.5.p1"),
+            ("1.0a", "1.0.a"),
+            ("1.0~rc1", "1.0~rc1"),
+            ("1.0^git1", "1.0^git1"),
+            ("1..0+", "1.0"),
+            ("1:2.0-1.el9", "1:2.0-1.el9"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(canonical(&parse(input).unwrap()), expected, "{}", input);
+            assert_eq!(compare(input, expected), Ordering::Equal, "{}", input);
+        }
+    }
+
+    #[test]
+    fn invalid_versions() {
+        for s in ["", "x:1.0", "1:", "1.0-", "1.0 1", "-1"] {
+            assert!(parse(s).is_err(), "{}", s);
+        }
+        let error = parse("2:1.0-1-el9").unwrap_err();
+        assert_eq!(error.kind, VersionErrorKind::InvalidCharacter('-'));
+        assert_eq!(error.fragment(), "-");
+        assert_eq!(error.span, 5..6);
+    }
+}
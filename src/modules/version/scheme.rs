@@ -0,0 +1,380 @@
+//! バージョン方式ごとの解釈と比較の実装。
+use super::error::{VersionError, VersionErrorKind};
+use super::{Version, dpkg, pep440, rpm};
+use std::cmp::Ordering;
+
+/// バージョン文字列の検証と比較の方法を定める方式。
+pub trait VersionScheme {
+    /// マニフェストなどで方式を指定するときの名前。
+    fn name(&self) -> &'static str;
+
+    /// 文字列がこの方式のバージョンとして有効かを検証します。
+    ///
+    /// # 引数
+    ///
+    /// * `s` - 検証するバージョン文字列。
+    ///
+    /// # 戻り値
+    ///
+    /// * `Ok(())` - 有効な場合。
+    /// * `Err(VersionError)` - 無効な場合、問題のある位置を含む。
+    fn validate(&self, s: &str) -> Result<(), VersionError>;
+
+    /// 二つのバージョンをこの方式の規則で比較します。
+    fn compare(&self, a: &Version, b: &Version) -> Ordering;
+
+    /// バージョンの正規形を返します。
+    ///
+    /// `compare` が `Ordering::Equal` を返すバージョン同士は同じ正規形になり、
+    /// それ以外は異なる正規形にならなければなりません。
+    fn canonical(&self, v: &Version) -> String;
+
+    /// バージョンがプレリリースかを判定します。
+    fn is_prerelease(&self, v: &Version) -> bool;
+
+    /// プレリリースの部分を除いたリリースの正規形を返します。
+    ///
+    /// プレリリースと、それが目指すリリースは同じ値になります (`1.0.0-rc.1` と `1.0.0`)。
+    fn release(&self, v: &Version) -> String;
+}
+
+/// 数値部分の末尾の0を取り除き、少なくとも `min_len` 個になるまで0で埋めます。
+fn padded_nums(nums: &[u32], min_len: usize) -> String {
+    let len = nums.iter().rposition(|&n| n != 0).map_or(0, |i| i + 1);
+    let mut parts: Vec<String> = nums[..len].iter().map(u32::to_string).collect();
+    while parts.len() < min_len {
+        parts.push("0".to_string());
+    }
+    parts.join(".")
+}
+
+/// SemVer 形式の正規形 (ビルドメタデータは含めない)。
+fn semver_canonical(v: &Version, min_len: usize) -> String {
+    let mut result = padded_nums(&v.nums, min_len);
+    if !v.pre.is_empty() {
+        let pre: Vec<String> = v.pre.iter().map(ToString::to_string).collect();
+        result.push('-');
+        result.push_str(&pre.join("."));
+    }
+    result
+}
+
+/// SemVer 2.0 (数値部分の個数は任意)。
+pub struct SemVer;
+/// `dpkg --compare-versions` 互換。
+pub struct Dpkg;
+/// `rpmvercmp` 互換。
+pub struct Rpm;
+/// Python の PEP 440。
+pub struct Pep440;
+/// 年・月から始まるカレンダーバージョン (例: 2026.10.17, 24.04)。
+pub struct CalVer;
+
+impl VersionScheme for SemVer {
+    fn name(&self) -> &'static str {
+        "semver"
+    }
+
+    fn validate(&self, s: &str) -> Result<(), VersionError> {
+        Version::parse_semver(s).map(|_| ())
+    }
+
+    fn compare(&self, a: &Version, b: &Version) -> Ordering {
+        // 足りない数値部分は0として比較する (1.0 == 1.0.0)
+        let len = a.nums.len().max(b.nums.len());
+        for i in 0..len {
+            let x = a.nums.get(i).copied().unwrap_or(0);
+            let y = b.nums.get(i).copied().unwrap_or(0);
+            match x.cmp(&y) {
+                Ordering::Equal => continue,
+                ord => return ord,
+            }
+        }
+        // 数値部分が等しい場合、プレリリースを持つ方が低い優先順位となる
+        // (ビルドメタデータは比較しない)
+        match (a.pre.is_empty(), b.pre.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => a.pre.cmp(&b.pre),
+        }
+    }
+
+    fn canonical(&self, v: &Version) -> String {
+        semver_canonical(v, 3)
+    }
+
+    fn is_prerelease(&self, v: &Version) -> bool {
+        !v.pre.is_empty()
+    }
+
+    fn release(&self, v: &Version) -> String {
+        padded_nums(&v.nums, 3)
+    }
+}
+
+impl VersionScheme for Dpkg {
+    fn name(&self) -> &'static str {
+        "dpkg"
+    }
+
+    fn validate(&self, s: &str) -> Result<(), VersionError> {
+        dpkg::parse(s).map(|_| ())
+    }
+
+    fn compare(&self, a: &Version, b: &Version) -> Ordering {
+        dpkg::compare(a.as_str(), b.as_str())
+    }
+
+    fn canonical(&self, v: &Version) -> String {
+        dpkg::parse(v.as_str()).map_or_else(|_| v.as_str().to_string(), |p| dpkg::canonical(&p))
+    }
+
+    fn is_prerelease(&self, v: &Version) -> bool {
+        dpkg::parse(v.as_str()).is_ok_and(|p| p.upstream.contains('~'))
+    }
+
+    fn release(&self, v: &Version) -> String {
+        // "~" 以降とリビジョンを除く
+        dpkg::parse(v.as_str()).map_or_else(
+            |_| v.as_str().to_string(),
+            |p| {
+                dpkg::canonical(&dpkg::DpkgVersion {
+                    upstream: p.upstream.split('~').next().unwrap_or_default(),
+                    revision: "",
+                    ..p
+                })
+            },
+        )
+    }
+}
+
+impl VersionScheme for Rpm {
+    fn name(&self) -> &'static str {
+        "rpm"
+    }
+
+    fn validate(&self, s: &str) -> Result<(), VersionError> {
+        rpm::parse(s).map(|_| ())
+    }
+
+    fn compare(&self, a: &Version, b: &Version) -> Ordering {
+        rpm::compare(a.as_str(), b.as_str())
+    }
+
+    fn canonical(&self, v: &Version) -> String {
+        rpm::parse(v.as_str()).map_or_else(|_| v.as_str().to_string(), |p| rpm::canonical(&p))
+    }
+
+    fn is_prerelease(&self, v: &Version) -> bool {
+        rpm::parse(v.as_str()).is_ok_and(|p| p.version.contains('~'))
+    }
+
+    fn release(&self, v: &Version) -> String {
+        // "~" 以降とリリースを除く
+        rpm::parse(v.as_str()).map_or_else(
+            |_| v.as_str().to_string(),
+            |p| {
+                rpm::canonical(&rpm::RpmVersion {
+                    version: p.version.split('~').next().unwrap_or_default(),
+                    release: "",
+                    ..p
+                })
+            },
+        )
+    }
+}
+
+impl VersionScheme for Pep440 {
+    fn name(&self) -> &'static str {
+        "pep440"
+    }
+
+    fn validate(&self, s: &str) -> Result<(), VersionError> {
+        pep440::parse(s).map(|_| ())
+    }
+
+    fn compare(&self, a: &Version, b: &Version) -> Ordering {
+        pep440::compare(a.as_str(), b.as_str())
+    }
+
+    fn canonical(&self, v: &Version) -> String {
+        pep440::parse(v.as_str()).map_or_else(|_| v.as_str().to_string(), |p| p.canonical())
+    }
+
+    fn is_prerelease(&self, v: &Version) -> bool {
+        // 開発版もプレリリースとして扱う (PEP 440 "Handling of pre-releases")
+        pep440::parse(v.as_str()).is_ok_and(|p| p.pre.is_some() || p.dev.is_some())
+    }
+
+    fn release(&self, v: &Version) -> String {
+        pep440::parse(v.as_str()).map_or_else(
+            |_| v.as_str().to_string(),
+            |p| {
+                pep440::Pep440Version {
+                    pre: None,
+                    post: None,
+                    dev: None,
+                    local: Vec::new(),
+                    ..p
+                }
+                .canonical()
+            },
+        )
+    }
+}
+
+impl VersionScheme for CalVer {
+    fn name(&self) -> &'static str {
+        "calver"
+    }
+
+    fn validate(&self, s: &str) -> Result<(), VersionError> {
+        Version::parse_semver(s)?;
+        let core = s.split(['-', '+']).next().unwrap_or_default();
+        // 各部分とその開始位置
+        let mut parts = Vec::new();
+        let mut offset = 0;
+        for part in core.split('.') {
+            parts.push((offset, part));
+            offset += part.len() + 1;
+        }
+        let error = |kind, start: usize, part: &str| {
+            Err(VersionError::new(kind, s, start..start + part.len()))
+        };
+        for &(start, part) in &parts {
+            if part.is_empty() {
+                return error(VersionErrorKind::EmptyComponent, start, part);
+            }
+            if !part.chars().all(|c| c.is_ascii_digit()) {
+                return error(VersionErrorKind::ExpectedNumber, start, part);
+            }
+        }
+        // 年は YYYY または YY
+        let (start, year) = parts[0];
+        if !matches!(year.len(), 2 | 4) {
+            return error(
+                VersionErrorKind::Malformed("calendar version must start with a year"),
+                start,
+                year,
+            );
+        }
+        if let Some(&(start, month)) = parts.get(1)
+            && !(1..=12).contains(&month.parse::<u32>().unwrap_or(0))
+        {
+            return error(
+                VersionErrorKind::Malformed("month must be between 1 and 12"),
+                start,
+                month,
+            );
+        }
+        Ok(())
+    }
+
+    fn compare(&self, a: &Version, b: &Version) -> Ordering {
+        SemVer.compare(a, b)
+    }
+
+    fn canonical(&self, v: &Version) -> String {
+        // 年と月は常に残す
+        semver_canonical(v, 2)
+    }
+
+    fn is_prerelease(&self, v: &Version) -> bool {
+        SemVer.is_prerelease(v)
+    }
+
+    fn release(&self, v: &Version) -> String {
+        padded_nums(&v.nums, 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Scheme;
+    use super::*;
+
+    fn cmp(scheme: Scheme, a: &str, b: &str) -> Ordering {
+        let a = Version::parse_with(a, scheme).unwrap();
+        let b = Version::parse_with(b, scheme).unwrap();
+        scheme.get().compare(&a, &b)
+    }
+
+    #[test]
+    fn each_scheme_orders_by_its_own_rules() {
+        assert_eq!(cmp(Scheme::SemVer, "1.0.0-rc.1", "1.0.0"), Ordering::Less);
+        assert_eq!(cmp(Scheme::Dpkg, "1.0~rc1", "1.0"), Ordering::Less);
+        assert_eq!(cmp(Scheme::Rpm, "1.0^git1", "1.0"), Ordering::Greater);
+        assert_eq!(cmp(Scheme::Pep440, "1.0.dev1", "1.0a1"), Ordering::Less);
+        assert_eq!(
+            cmp(Scheme::CalVer, "2026.10.17", "2026.9.30"),
+            Ordering::Greater
+        );
+        assert_eq!(cmp(Scheme::CalVer, "24.04-beta", "24.04"), Ordering::Less);
+    }
+
+    #[test]
+    fn canonical_forms() {
+        let cases = [
+            (Scheme::SemVer, "1", "1.0.0"),
+            (Scheme::SemVer, "v1.2", "1.2.0"),
+            (Scheme::SemVer, "1.2.3.0.0", "1.2.3"),
+            (Scheme::SemVer, "1.2.3.4", "1.2.3.4"),
+            (Scheme::SemVer, "1.0.0-rc.1+build.5", "1.0.0-rc.1"),
+            (Scheme::Dpkg, "0:1.00-0", "1.0"),
+            (Scheme::Rpm, "1_0-1", "1.0-1"),
+            (Scheme::Pep440, "1.0.0RC1", "1rc1"),
+            (Scheme::CalVer, "2026.10.0", "2026.10"),
+            (Scheme::CalVer, "24.04", "24.4"),
+        ];
+        for (scheme, input, expected) in cases {
+            let v = Version::parse_with(input, scheme).unwrap();
+            assert_eq!(scheme.get().canonical(&v), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn calver_validation() {
+        assert!(CalVer.validate("2026.10.17").is_ok());
+        assert!(CalVer.validate("24.04").is_ok());
+        assert!(CalVer.validate("2026.01.3-dev.1").is_ok());
+        assert_eq!(CalVer.validate("2026.13.1").unwrap_err().span, 5..7);
+        assert!(CalVer.validate("123.1").is_err());
+        assert!(CalVer.validate("v2026.1").is_err());
+    }
+
+    #[test]
+    fn prereleases_and_their_releases() {
+        let cases = [
+            (Scheme::SemVer, "1.0.0-rc.1", true, "1.0.0"),
+            (Scheme::SemVer, "1.0+b1", false, "1.0.0"),
+            (Scheme::Dpkg, "1:2.0~rc1-3", true, "1:2.0"),
+            (Scheme::Dpkg, "1:2.0-3", false, "1:2.0"),
+            (Scheme::Rpm, "2.0~beta-1", true, "2.0"),
+            (Scheme::Rpm, "2.0^git1", false, "2.0^git1"),
+            (Scheme::Pep440, "2.0.0rc1", true, "2"),
+            (Scheme::Pep440, "2.0.post1.dev1", true, "2"),
+            (Scheme::Pep440, "2.0.post1", false, "2"),
+            (Scheme::CalVer, "2026.10-beta", true, "2026.10"),
+        ];
+        for (scheme, input, prerelease, release) in cases {
+            let v = Version::parse_with(input, scheme).unwrap();
+            assert_eq!(scheme.get().is_prerelease(&v), prerelease, "{}", input);
+            assert_eq!(scheme.get().release(&v), release, "{}", input);
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for scheme in [
+            Scheme::SemVer,
+            Scheme::Dpkg,
+            Scheme::Rpm,
+            Scheme::Pep440,
+            Scheme::CalVer,
+        ] {
+            assert_eq!(scheme.get().name().parse::<Scheme>(), Ok(scheme));
+        }
+        assert!("maven".parse::<Scheme>().is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! バージョン関連の型の serde 対応。
+//!
+//! どの型も `Display` と `FromStr` の文字列表現で読み書きします。
+//! 範囲は `pep440: >= 1.0rc1` のように方式を文字列に含めるので、そのまま読み戻せます。
+//! バージョンは既定の方式で解釈されるので、方式を伴う場合は
+//! 文字列として受け取ってから [`Version::parse_with`] を使います。
+use super::{PrereleasePolicy, Scheme, Version, VersionRange};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::{fmt::Display, str::FromStr};
+
+/// 文字列表現を解釈して値を作ります。
+fn from_string<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(de::Error::custom)
+}
+
+macro_rules! serde_via_string {
+    ($($t:ty),*) => {$(
+        impl Serialize for $t {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $t {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                from_string(deserializer)
+            }
+        }
+    )*};
+}
+
+serde_via_string!(Version, VersionRange, Scheme, PrereleasePolicy);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::IntoDeserializer;
+    use serde::de::value::{Error, StrDeserializer};
+
+    fn from_str<'de, T: Deserialize<'de>>(s: &'de str) -> Result<T, Error> {
+        let deserializer: StrDeserializer<Error> = s.into_deserializer();
+        T::deserialize(deserializer)
+    }
+
+    #[test]
+    fn values_are_read_from_strings() {
+        assert_eq!(from_str::<Scheme>("dpkg").unwrap(), Scheme::Dpkg);
+        assert_eq!(
+            from_str::<PrereleasePolicy>("include").unwrap(),
+            PrereleasePolicy::Include
+        );
+        let range: VersionRange = from_str(">= 1.0, < 2.0").unwrap();
+        assert!(range.compare(&"1.5".parse().unwrap()));
+        assert_eq!(from_str::<Version>("1.2.3").unwrap().as_str(), "1.2.3");
+        let error = from_str::<VersionRange>(">= 1.0, ~> 2").unwrap_err();
+        assert!(error.to_string().contains("~>"), "{}", error);
+    }
+
+    #[test]
+    fn ranges_keep_their_scheme() {
+        let range = VersionRange::parse_with(">= 1.0rc1, < 2.0", Scheme::Pep440).unwrap();
+        let json = serde_json::to_string(&range).unwrap();
+        assert_eq!(json, r#""pep440: >= 1.0rc1, < 2.0""#);
+        assert_eq!(serde_json::from_str::<VersionRange>(&json).unwrap(), range);
+    }
+}
@@ -1,2 +1,4 @@
+// 引数の解析は元の入れ子の if の形のまま保つ
+#[allow(clippy::collapsible_if)]
 pub mod args;
 pub mod question;
@@ -1,6 +1,7 @@
 use colored::Colorize;
 use regex::Regex;
 use std::io::{Write, stdin, stdout};
+use std::sync::LazyLock;
 
 /// ユーザーにメッセージを表示し、標準入力から1行の入力を取得します。
 /// 
@@ -190,6 +191,17 @@ pub fn snake_loop(msg: &str) -> String {
     }
 }
 
+/// ケバブケース形式 (例: hello-world) の正規表現。パッケージ名の規則にも使われます。
+pub const KEBAB_CASE_PATTERN: &str = r"^[a-z0-9]+(?:-[a-z0-9]+)*$";
+
+static KEBAB_CASE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(KEBAB_CASE_PATTERN).unwrap());
+
+/// 文字列がケバブケース形式かを判定します。
+pub fn is_kebab_case(s: &str) -> bool {
+    KEBAB_CASE_REGEX.is_match(s)
+}
+
 /// ケバブケース形式の文字列を検証します（例: hello-world）。
 /// 
 /// # 引数
@@ -201,8 +213,7 @@ pub fn snake_loop(msg: &str) -> String {
 /// * `Ok(String)` - 入力が有効なケバブケースの場合。
 /// * `Err(String)` - 入力が無効な場合、エラーメッセージを含む。
 pub fn kebab_case(msg: &str) -> Result<String, String> {
-    let kebab_regex = Regex::new(r"^[a-z0-9]+(?:-[a-z0-9]+)*$").unwrap();
-    regex_string(msg, kebab_regex)
+    regex_string(msg, KEBAB_CASE_REGEX.clone())
 }
 
 /// 有効なケバブケース文字列が得られるまで入力を繰り返します。
@@ -264,6 +275,16 @@ pub fn screaming_snake_loop(msg: &str) -> String {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn test() {}
+
+    #[test]
+    fn kebab_case_rule() {
+        assert!(is_kebab_case("hello-world2"));
+        for s in ["Hello", "hello_world", "-hello", "hello--world", ""] {
+            assert!(!is_kebab_case(s), "{}", s);
+        }
+    }
 }
\ No newline at end of file